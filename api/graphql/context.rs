@@ -3,12 +3,16 @@
 //! Defines shared context (auth, state, metadata, config) for all GraphQL resolvers and middleware.
 
 use async_graphql::Context as GqlContext;
-use std::sync::Arc;
+use crate::core::ledger::fork_choice::Chain;
+use std::sync::{Arc, RwLock};
 
 /// Example state handles (customize as needed)
 pub struct DbHandle;        // Replace with actual DB/State/Storage types
 pub struct ChainHandle;     // Blockchain node handle (RPC, query, etc.)
 
+/// Ledger served by the chain-data resolvers (attached with `build_schema_with_ledger`)
+pub type SharedChain = Arc<RwLock<Chain>>;
+
 /// Auth info for current request (session, user, permissions)
 #[derive(Clone, Debug)]
 pub struct AuthInfo {
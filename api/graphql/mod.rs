@@ -16,7 +16,7 @@ pub mod extensions;
 pub mod tests;
 
 // -- Re-export top-level interfaces for external GraphQL server --
-pub use schema::{build_schema, QueryRoot, MutationRoot, SubscriptionRoot};
+pub use schema::{build_schema, build_schema_with_ledger, QueryRoot, MutationRoot, SubscriptionRoot};
 pub use context::GraphQLContext;
 pub use types::*;
 pub use resolvers::*;
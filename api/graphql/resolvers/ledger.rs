@@ -1,9 +1,14 @@
 //! OCOS-Chain: GraphQL Ledger Resolvers
 //!
 //! Implements query and mutation resolvers for chain blocks, transactions, and balances.
+//!
+//! `logs` answers event log filters from the ledger's log bloom index; it needs a
+//! schema built with `build_schema_with_ledger`.
 
-use async_graphql::{Context, Object, Result, ID};
-use crate::api::graphql::types::{Block, Transaction, Balance, Address, Pagination};
+use async_graphql::{Context, Error, Object, Result, ID};
+use crate::api::graphql::context::SharedChain;
+use crate::api::graphql::types::{Block, Transaction, Balance, Address, Pagination, EventLogEntry, LogFilterInput};
+use crate::core::ledger::log_index::{LogFilter, MAX_QUERY_RANGE};
 
 /// Query resolvers for ledger (read-only operations)
 #[derive(Default)]
@@ -65,6 +70,46 @@ impl LedgerQuery {
             token: "OCOS".into(),
         })
     }
+
+    /// Event logs matching a height range, emitting addresses and topics (hex)
+    async fn logs(&self, ctx: &Context<'_>, filter: LogFilterInput) -> Result<Vec<EventLogEntry>> {
+        let chain = ctx
+            .data_opt::<SharedChain>()
+            .ok_or_else(|| Error::new("ledger is not attached to the API"))?;
+        let chain = chain.read().map_err(|_| Error::new("ledger lock poisoned"))?;
+        let head = chain.store.get_stored(&chain.head).map(|b| b.height).unwrap_or(0);
+
+        let mut log_filter = LogFilter::new(filter.from_block.unwrap_or(0), filter.to_block.unwrap_or(head));
+        if log_filter.from_height > log_filter.to_height {
+            return Err(Error::new("fromBlock is above toBlock"));
+        }
+        if log_filter.to_height - log_filter.from_height >= MAX_QUERY_RANGE {
+            return Err(Error::new(format!("log queries may span at most {} blocks", MAX_QUERY_RANGE)));
+        }
+        for address in filter.addresses.unwrap_or_default() {
+            log_filter = log_filter.with_address(decode_hex(&address)?);
+        }
+        for (position, alternatives) in filter.topics.unwrap_or_default().iter().enumerate() {
+            let alternatives = alternatives.iter().map(|topic| decode_hex(topic)).collect::<Result<_>>()?;
+            log_filter = log_filter.with_topic(position, alternatives);
+        }
+
+        let logs = chain.logs(&log_filter).map_err(|err| Error::new(format!("{:?}", err)))?;
+        Ok(logs.into_iter().map(|matched| EventLogEntry {
+            block_number: matched.height,
+            block_hash: hex::encode(&matched.block_hash),
+            tx_hash: hex::encode(&matched.tx_hash),
+            tx_index: matched.tx_index,
+            log_index: matched.log_index,
+            address: hex::encode(&matched.log.address),
+            topics: matched.log.topics.iter().map(hex::encode).collect(),
+            data: hex::encode(&matched.log.data),
+        }).collect())
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| Error::new(format!("invalid hex value: {}", value)))
 }
 
 /// Mutation resolvers for ledger (optional, e.g. test faucet or manual block insert)
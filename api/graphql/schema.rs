@@ -4,7 +4,7 @@
 //! This is the entry point for the GraphQL server.
 
 use crate::api::graphql::{
-    context::GraphQLContext,
+    context::{GraphQLContext, SharedChain},
     resolvers::{
         ledger::{LedgerQuery, LedgerMutation},
         governance::{GovernanceQuery, GovernanceMutation},
@@ -49,3 +49,11 @@ pub fn build_schema() -> Schema<QueryRoot, MutationRoot, SubscriptionRoot> {
         // Optionally add extensions, tracing, and limiters here
         .finish()
 }
+
+/// Build the schema with ledger-backed resolvers (e.g. `logs`) reading from `chain`.
+pub fn build_schema_with_ledger(chain: SharedChain) -> Schema<QueryRoot, MutationRoot, SubscriptionRoot> {
+    Schema::build(QueryRoot::default(), MutationRoot::default(), SubscriptionRoot::default())
+        .data(GraphQLContext::default())
+        .data(chain)
+        .finish()
+}
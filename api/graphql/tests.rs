@@ -91,3 +91,35 @@ async fn test_subscription_block_stream() {
     // let mut stream = schema.execute_stream(Request::new("subscription { blockStream { number } }"));
     // assert!(stream.next().await.is_some());
 }
+
+#[tokio::test]
+async fn test_logs_query() {
+    use crate::api::graphql::schema::build_schema_with_ledger;
+    use crate::core::ledger::{
+        block::Block, fork_choice::Chain, log_index::IndexedBlock, receipt::{EventLog, Receipt}, state::State,
+    };
+    use std::sync::{Arc, RwLock};
+
+    let mut chain = Chain::new(Block::default(), State::new());
+    for height in 1..=3u8 {
+        let log = EventLog::new(vec![height; 20], vec![vec![0xdd; 32], vec![height; 32]], vec![height]);
+        chain.logs.add_block(height as u64, IndexedBlock::new(vec![height; 32], vec![Receipt::success(vec![height; 32], 21_000, vec![log])])).unwrap();
+    }
+    let schema = build_schema_with_ledger(Arc::new(RwLock::new(chain)));
+
+    let query = format!(
+        r#"{{ logs(filter: {{ fromBlock: 1, toBlock: 3, topics: [["{}"], ["{}", "{}"]] }}) {{ blockNumber address data }} }}"#,
+        "dd".repeat(32),
+        "01".repeat(32),
+        "03".repeat(32),
+    );
+    let resp = schema.execute(Request::new(query)).await;
+    assert!(resp.is_ok(), "Logs query failed");
+    let data = resp.data.into_json().unwrap();
+    let logs = data["logs"].as_array().unwrap();
+    assert_eq!(logs.iter().map(|l| l["blockNumber"].as_u64().unwrap()).collect::<Vec<_>>(), vec![1, 3]);
+    assert_eq!(logs[1]["address"].as_str().unwrap(), "03".repeat(20));
+
+    let resp = setup_schema().execute(Request::new(r#"{ logs(filter: {}) { blockNumber } }"#)).await;
+    assert!(resp.errors.len() > 0, "Expected error without an attached ledger");
+}
@@ -48,6 +48,26 @@ pub struct Balance {
     pub token: String,
 }
 
+#[derive(SimpleObject, Clone)]
+pub struct EventLogEntry {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub tx_index: usize,
+    pub log_index: usize,
+    pub address: Address,
+    pub topics: Vec<String>,
+    pub data: String,
+}
+
+#[derive(InputObject, Clone)]
+pub struct LogFilterInput {
+    pub from_block: Option<u64>,           // None = genesis
+    pub to_block: Option<u64>,             // None = current head
+    pub addresses: Option<Vec<Address>>,   // Any of these emitters
+    pub topics: Option<Vec<Vec<String>>>,  // Topic i is any of topics[i] (empty = any)
+}
+
 // ----- Governance Types -----
 #[derive(SimpleObject, Clone)]
 pub struct GovernanceProposal {
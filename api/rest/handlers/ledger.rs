@@ -2,9 +2,27 @@
 //!
 //! Implements HTTP handlers for blockchain data queries: blocks, transactions,
 //! account state, and explorer endpoints.
+//!
+//! State queries are answered from the node's `Chain`, attached to the router
+//! with `register_routes_with_ledger`. `/state/:address?height=N` reads the
+//! account as of block N (served in full by archive nodes, recent heights only
+//! by pruned ones).
+//!
+//! `POST /logs` takes a log filter (height range, emitting addresses, topics per
+//! position; all hex) and returns the matching event logs, found through the
+//! ledger's log bloom index.
 
-use axum::{extract::Path, Json};
+use crate::api::rest::error::ApiError;
+use crate::core::ledger::fork_choice::{Chain, ChainError};
+use crate::core::ledger::log_index::{LogFilter, MatchedLog, MAX_QUERY_RANGE};
+use crate::core::ledger::state::AccountState;
+use crate::core::ledger::state_history::HistoryError;
+use axum::{extract::{Path, Query}, Extension, Json};
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// Ledger handle shared with the state endpoints
+pub type SharedChain = Arc<RwLock<Chain>>;
 
 #[derive(Serialize)]
 pub struct BlockResponse {
@@ -32,9 +50,52 @@ pub struct AccountStateResponse {
     pub address: String,
     pub balance: String,
     pub nonce: u64,
+    pub height: u64, // Block the state was read at
     // ... extendable: storage, tokens, contract code hash, etc.
 }
 
+#[derive(Deserialize)]
+pub struct StateQuery {
+    pub height: Option<u64>, // None = current head
+}
+
+#[derive(Deserialize)]
+pub struct LogFilterRequest {
+    pub from_height: Option<u64>, // None = genesis
+    pub to_height: Option<u64>,   // None = current head
+    #[serde(default)]
+    pub addresses: Vec<String>,   // Any of these emitters (empty = any)
+    #[serde(default)]
+    pub topics: Vec<Vec<String>>, // Topic i is any of topics[i] (empty = any)
+}
+
+#[derive(Serialize)]
+pub struct EventLogResponse {
+    pub height: u64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub tx_index: usize,
+    pub log_index: usize,
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+}
+
+impl From<MatchedLog> for EventLogResponse {
+    fn from(matched: MatchedLog) -> Self {
+        EventLogResponse {
+            height: matched.height,
+            block_hash: hex::encode(matched.block_hash),
+            tx_hash: hex::encode(matched.tx_hash),
+            tx_index: matched.tx_index,
+            log_index: matched.log_index,
+            address: hex::encode(matched.log.address),
+            topics: matched.log.topics.iter().map(hex::encode).collect(),
+            data: hex::encode(matched.log.data),
+        }
+    }
+}
+
 // --- Handler: Get block by height ---
 pub async fn get_block_by_height(Path(height): Path<u64>) -> Json<BlockResponse> {
     // In real code, fetch block from storage/db
@@ -71,12 +132,74 @@ pub async fn get_transaction(Path(hash): Path<String>) -> Json<TransactionRespon
     })
 }
 
-// --- Handler: Get account state by address ---
-pub async fn get_account_state(Path(address): Path<String>) -> Json<AccountStateResponse> {
-    // In real code, fetch state from chain/ledger
-    Json(AccountStateResponse {
-        address: address.clone(),
-        balance: "10000.0".to_string(),
-        nonce: 47,
-    })
+// --- Handler: Get account state by address (optionally at a past height) ---
+pub async fn get_account_state(
+    Path(address): Path<String>,
+    Query(query): Query<StateQuery>,
+    chain: Option<Extension<SharedChain>>,
+) -> Result<Json<AccountStateResponse>, ApiError> {
+    let key = decode_hex(&address)?;
+    let chain = attached_chain(chain)?;
+    let chain = chain.read().map_err(|_| ApiError::Internal("ledger lock poisoned".to_string()))?;
+
+    let (height, account) = match query.height {
+        Some(height) => (height, chain.account_at(&key, height).map_err(state_query_error)?),
+        None => (head_height(&chain), chain.state.get_account(&key).cloned()),
+    };
+    let account = account.unwrap_or_else(|| AccountState::new(0));
+    Ok(Json(AccountStateResponse {
+        address,
+        balance: account.balance.to_string(),
+        nonce: account.nonce,
+        height,
+    }))
+}
+
+// --- Handler: Query event logs by height range, address and topics ---
+pub async fn get_logs(
+    chain: Option<Extension<SharedChain>>,
+    Json(request): Json<LogFilterRequest>,
+) -> Result<Json<Vec<EventLogResponse>>, ApiError> {
+    let chain = attached_chain(chain)?;
+    let chain = chain.read().map_err(|_| ApiError::Internal("ledger lock poisoned".to_string()))?;
+
+    let mut filter = LogFilter::new(request.from_height.unwrap_or(0), request.to_height.unwrap_or_else(|| head_height(&chain)));
+    if filter.from_height > filter.to_height {
+        return Err(ApiError::BadRequest("from_height is above to_height".to_string()));
+    }
+    if filter.to_height - filter.from_height >= MAX_QUERY_RANGE {
+        return Err(ApiError::BadRequest(format!("log queries may span at most {} blocks", MAX_QUERY_RANGE)));
+    }
+    for address in &request.addresses {
+        filter = filter.with_address(decode_hex(address)?);
+    }
+    for (position, alternatives) in request.topics.iter().enumerate() {
+        let alternatives = alternatives.iter().map(|topic| decode_hex(topic)).collect::<Result<_, _>>()?;
+        filter = filter.with_topic(position, alternatives);
+    }
+    let logs = chain.logs(&filter).map_err(|err| ApiError::Internal(format!("{:?}", err)))?;
+    Ok(Json(logs.into_iter().map(EventLogResponse::from).collect()))
+}
+
+fn attached_chain(chain: Option<Extension<SharedChain>>) -> Result<SharedChain, ApiError> {
+    chain
+        .map(|Extension(chain)| chain)
+        .ok_or_else(|| ApiError::Internal("ledger is not attached to the API".to_string()))
+}
+
+fn head_height(chain: &Chain) -> u64 {
+    chain.store.get_stored(&chain.head).map(|b| b.height).unwrap_or(0)
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| ApiError::BadRequest(format!("invalid hex value: {}", value)))
+}
+
+fn state_query_error(err: ChainError) -> ApiError {
+    match err {
+        ChainError::History(err @ HistoryError::Pruned { .. }) => ApiError::NotFound(err.to_string()),
+        ChainError::History(err @ HistoryError::Unknown { .. }) => ApiError::NotFound(err.to_string()),
+        ChainError::UnknownBlock => ApiError::NotFound("no canonical block at that height".to_string()),
+        other => ApiError::Internal(format!("{:?}", other)),
+    }
 }
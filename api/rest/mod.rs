@@ -17,7 +17,7 @@ pub mod middleware;
 pub mod tests;
 
 // Publicly re-export routes and types for application use
-pub use routes::register_routes;
+pub use routes::{register_routes, register_routes_with_ledger};
 pub use types::*;
 pub use auth::AuthLayer;
 pub use error::ApiError;
@@ -6,7 +6,7 @@ use crate::api::rest::handlers::{
     ledger::*, governance::*, identity::*, liquidity::*, dao::*, metrics::*,
 };
 use crate::api::rest::{auth::AuthLayer, error::ApiError};
-use axum::{Router, routing::{get, post, put, delete}};
+use axum::{Extension, Router, routing::{get, post, put, delete}};
 
 pub fn register_routes() -> Router {
     Router::new()
@@ -15,6 +15,7 @@ pub fn register_routes() -> Router {
         .route("/blocks/latest", get(get_latest_block))
         .route("/tx/:hash", get(get_transaction))
         .route("/state/:address", get(get_account_state))
+        .route("/logs", post(get_logs))
 
         // --- Governance endpoints ---
         .route("/governance/proposals", get(list_proposals).post(submit_proposal))
@@ -49,3 +50,8 @@ pub fn register_routes() -> Router {
         // --- Auth-protected layer (can wrap protected endpoints) ---
         .layer(AuthLayer::default())
 }
+
+/// All routes, with ledger and state endpoints served from `chain`
+pub fn register_routes_with_ledger(chain: SharedChain) -> Router {
+    register_routes().layer(Extension(chain))
+}
@@ -74,3 +74,71 @@ async fn test_api_version() {
     let version: serde_json::Value = serde_json::from_slice(&body).unwrap();
     assert_eq!(version.get("api_version").unwrap(), "v1.0.0");
 }
+
+#[tokio::test]
+async fn test_account_state_at_height() {
+    use crate::core::ledger::{block::Block, fork_choice::Chain, state::{AccountState, State}};
+    use std::sync::{Arc, RwLock};
+
+    let mut genesis_state = State::new();
+    genesis_state.update_account(vec![7; 20], AccountState::new(500));
+    let chain = Chain::new(Block::default(), genesis_state);
+    let address = hex::encode([7u8; 20]);
+    let app = crate::api::rest::register_routes_with_ledger(Arc::new(RwLock::new(chain)));
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move { app.oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap() }
+    };
+    for uri in [format!("/state/0x{}", address), format!("/state/{}?height=0", address)] {
+        let resp = get(uri).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let state: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(state.get("balance").unwrap(), "500");
+        assert_eq!(state.get("height").unwrap(), 0);
+    }
+    assert_eq!(get(format!("/state/{}?height=9", address)).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(get("/state/not-hex".to_string()).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_query_logs() {
+    use crate::core::ledger::{
+        block::Block, fork_choice::Chain, log_index::IndexedBlock, receipt::{EventLog, Receipt}, state::State,
+    };
+    use std::sync::{Arc, RwLock};
+
+    let mut chain = Chain::new(Block::default(), State::new());
+    for height in 1..=3u8 {
+        let log = EventLog::new(vec![height; 20], vec![vec![0xdd; 32]], vec![height]);
+        chain.logs.add_block(height as u64, IndexedBlock::new(vec![height; 32], vec![Receipt::success(vec![height; 32], 21_000, vec![log])])).unwrap();
+    }
+    let app = crate::api::rest::register_routes_with_ledger(Arc::new(RwLock::new(chain)));
+
+    let post = |filter: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/logs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(filter.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let resp = post(json!({ "from_height": 1, "to_height": 3, "addresses": [format!("0x{}", hex::encode([2u8; 20]))], "topics": [["dd".repeat(32)]] })).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    let logs: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(logs.as_array().unwrap().len(), 1);
+    assert_eq!(logs[0].get("height").unwrap(), 2);
+    assert_eq!(logs[0].get("data").unwrap(), "02");
+
+    assert_eq!(post(json!({ "from_height": 3, "to_height": 1 })).await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(post(json!({ "to_height": 3, "addresses": ["zz"] })).await.status(), StatusCode::BAD_REQUEST);
+}
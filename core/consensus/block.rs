@@ -3,16 +3,38 @@
 //! Defines the canonical block and header structures, supporting both
 //! quantum-resistant and classical cryptography. Includes hashing, signature
 //! validation and metadata for full auditability.
+//!
+//! `BlockLimits` holds the header validity rules the consensus engine enforces.
 
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Sha3_256};
 
-/// Canonical block header structure
-#[derive(Debug, Clone)]
+use crate::core::consensus::{
+    finality::CommitCertificate,
+    signer::{sign_bytes, SignKind},
+    slashing::{Evidence, BPS_DENOMINATOR},
+};
+
+/// Canonical block header structure.
+///
+/// `state_root` is the ledger's state trie root after executing the parent block, so a
+/// header authenticates the state its own transactions start from. `gas_used` and
+/// `logs_bloom` cover the block's own transactions and receipts: the proposer takes them
+/// from the ledger's `ExecutionSummary` before signing, the ledger rejects imported
+/// blocks whose header disagrees, and `ConsensusEngine::on_executed` checks the gas again
+/// once the block is finalized.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlockHeader {
     pub parent_hash: Vec<u8>,
     pub proposer_public_key: Vec<u8>,
     pub state_root: Vec<u8>,
     pub tx_root: Vec<u8>,
+    pub evidence_root: Vec<u8>,
+    pub logs_bloom: Vec<u8>,           // Bloom over this block's log addresses and topics
+    pub validators_hash: Vec<u8>,      // Epoch validator set that signs this block
+    pub next_validators_hash: Vec<u8>, // Epoch validator set that signs the next block
+    pub gas_used: u64,                 // Gas consumed by the block's transactions
+    pub base_fee_per_gas: u128,        // Burned per unit of gas (0 = before the fee market starts)
     pub height: u64,
     pub timestamp: u64,
     pub metadata: Option<Vec<u8>>, // extensible: e.g., for consensus params, governance info
@@ -25,38 +47,54 @@ impl BlockHeader {
         BlockHeader {
             parent_hash,
             proposer_public_key,
-            state_root: Self::calc_state_root(txs),
+            state_root: vec![0u8; 32], // to be set by consensus engine (post-parent state)
             tx_root: Self::calc_tx_root(txs),
+            evidence_root: Evidence::root(&[]),
+            logs_bloom: vec![],           // to be set from the ledger's execution
+            validators_hash: vec![],      // to be set by consensus engine
+            next_validators_hash: vec![], // to be set by consensus engine
+            gas_used: 0,
+            base_fee_per_gas: 0, // to be set by consensus engine
             height: 0, // to be set by consensus engine
-            timestamp: now,
+            timestamp: now, // clamped past the median time by the consensus engine
             metadata: None,
         }
     }
 
-    /// Calculate block header hash (chain ID, height, roots, etc.)
+    /// Calculate block header hash (chain ID, height, roots, etc.). Variable-length
+    /// fields are length-prefixed, so bytes cannot move from one field to the next.
     pub fn hash(&self) -> Vec<u8> {
         let mut hasher = Sha3_256::new();
-        hasher.update(&self.parent_hash);
-        hasher.update(&self.proposer_public_key);
-        hasher.update(&self.state_root);
-        hasher.update(&self.tx_root);
-        hasher.update(&self.height.to_be_bytes());
-        hasher.update(&self.timestamp.to_be_bytes());
-        if let Some(meta) = &self.metadata {
-            hasher.update(meta);
+        let mut field = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(bytes);
+        };
+        field(&self.parent_hash);
+        field(&self.proposer_public_key);
+        field(&self.state_root);
+        field(&self.tx_root);
+        field(&self.evidence_root);
+        field(&self.logs_bloom);
+        field(&self.validators_hash);
+        field(&self.next_validators_hash);
+        hasher.update(self.gas_used.to_be_bytes());
+        hasher.update(self.base_fee_per_gas.to_be_bytes());
+        hasher.update(self.height.to_be_bytes());
+        hasher.update(self.timestamp.to_be_bytes());
+        match &self.metadata {
+            Some(meta) => {
+                hasher.update([1u8]);
+                hasher.update((meta.len() as u32).to_be_bytes());
+                hasher.update(meta);
+            }
+            None => hasher.update([0u8]),
         }
         hasher.finalize().to_vec()
     }
 
-    /// Simulated state root (placeholder)
-    fn calc_state_root(_txs: &[u8]) -> Vec<u8> {
-        // Real implementation: Merkle/Patricia trie root of state after txs applied
-        vec![0u8; 32]
-    }
-
-    /// Simulated tx root (placeholder)
-    fn calc_tx_root(txs: &[u8]) -> Vec<u8> {
-        // Real implementation: Merkle root of all transactions
+    /// Transaction root: SHA3-256 of the encoded transaction batch. Consensus carries the
+    /// batch as opaque bytes, so this is a plain hash rather than a per-transaction Merkle root.
+    pub fn calc_tx_root(txs: &[u8]) -> Vec<u8> {
         let mut hasher = Sha3_256::new();
         hasher.update(txs);
         hasher.finalize().to_vec()
@@ -69,6 +107,77 @@ impl BlockHeader {
     }
 }
 
+/// Header validity limits. Timestamps must move past the median of recent blocks
+/// without running ahead of local time, and blocks are capped in size and gas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockLimits {
+    pub median_time_span: usize,    // Recent blocks whose median timestamp a header must exceed
+    pub max_future_drift_secs: u64, // Tolerated header timestamp ahead of local time
+    pub max_block_bytes: u64,       // Size cap on the transaction payload
+    pub max_block_gas: u64,         // Gas cap on the block's transactions
+    pub gas_target_bps: u64,        // Share of the gas cap the base fee steers usage toward
+    pub base_fee_max_change_bps: u64, // Largest base fee move per block
+    pub initial_base_fee: u128,     // Base fee of the first block after a header without one
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        BlockLimits {
+            median_time_span: 11,
+            max_future_drift_secs: 15,
+            max_block_bytes: 4_194_304, // 4 MiB
+            max_block_gas: 30_000_000,
+            gas_target_bps: 5_000,           // 50% of the cap
+            base_fee_max_change_bps: 1_250,  // 12.5%
+            initial_base_fee: 1_000_000_000,
+        }
+    }
+}
+
+impl BlockLimits {
+    /// Median of the given block timestamps (0 before any block)
+    pub fn median_time(timestamps: &[u64]) -> u64 {
+        let mut sorted = timestamps.to_vec();
+        sorted.sort_unstable();
+        sorted.get(sorted.len() / 2).copied().unwrap_or(0)
+    }
+
+    /// Target gas per block
+    pub fn gas_target(&self) -> u64 {
+        (self.max_block_gas as u128 * self.gas_target_bps as u128 / BPS_DENOMINATOR as u128).max(1) as u64
+    }
+
+    /// Base fee of the block following `parent` (EIP-1559): it rises when the parent used
+    /// more than the gas target and falls when it used less, by at most
+    /// `base_fee_max_change_bps` per block. Never drops below 1.
+    pub fn next_base_fee(&self, parent: &BlockHeader) -> u128 {
+        let base_fee = parent.base_fee_per_gas;
+        if base_fee == 0 {
+            return self.initial_base_fee;
+        }
+        let target = self.gas_target() as u128;
+        let used = parent.gas_used as u128;
+        let change = |gas_delta: u128| {
+            base_fee.saturating_mul(gas_delta).saturating_mul(self.base_fee_max_change_bps as u128)
+                / target
+                / BPS_DENOMINATOR as u128
+        };
+        if used > target {
+            base_fee.saturating_add(change(used - target).max(1))
+        } else {
+            base_fee.saturating_sub(change(target - used)).max(1)
+        }
+    }
+}
+
+/// Results of executing a block's transactions, reported by the ledger
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionSummary {
+    pub gas_used: u64,       // Gas consumed by the block's transactions
+    pub state_root: Vec<u8>, // State root after the block (committed by the next header)
+    pub logs_bloom: Vec<u8>, // Bloom over the logs of the block's receipts
+}
+
 /// Canonical OCOS-Chain block structure
 #[derive(Debug, Clone)]
 pub struct Block {
@@ -76,6 +185,8 @@ pub struct Block {
     pub signature: Vec<u8>,        // Block signature (quantum/classical)
     pub proposer: String,          // Validator address
     pub transactions: Vec<u8>,     // Transactions (batch, for simplicity)
+    pub commit: Option<CommitCertificate>, // Set once the block is finalized by BFT consensus
+    pub evidence: Vec<Evidence>,   // Misbehaviour evidence included by the proposer
 }
 
 impl Block {
@@ -84,14 +195,25 @@ impl Block {
         self.header.hash()
     }
 
-    /// Validate block header signature (via quantum/classical handler)
+    /// Size of the transaction payload in bytes
+    pub fn size(&self) -> u64 {
+        self.transactions.len() as u64
+    }
+
+    /// True once a commit certificate has been attached (hard finality)
+    pub fn is_final(&self) -> bool {
+        self.commit.is_some()
+    }
+
+    /// Validate block header signature (via quantum/classical handler) over the
+    /// proposal sign bytes of the header hash
     pub fn validate_signature<F>(&self, verify_fn: F) -> bool
     where
         F: Fn(&[u8], &[u8], &[u8]) -> bool,
     {
         verify_fn(
             &self.header.proposer_public_key,
-            &self.header.hash(),
+            &sign_bytes(SignKind::Proposal, self.header.height, 0, &self.header.hash()),
             &self.signature,
         )
     }
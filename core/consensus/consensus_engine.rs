@@ -6,11 +6,21 @@
 use crate::core::consensus::{
     validator::{Validator, ValidatorSet},
     quantum_sig::QuantumSignature,
-    block::{Block, BlockHeader},
-    slashing::SlashingManager,
-    governance::GovernanceHook,
+    signer::{sign_bytes, BlockSigner, SignKind, SignRequest},
+    block::{Block, BlockHeader, BlockLimits, ExecutionSummary},
+    slashing::{Evidence, SlashRecord, SlashingManager, UptimeStats, BPS_DENOMINATOR},
+    staking::{StakingConfig, StakingLedger, UnbondingEntry},
+    governance::{GovernanceHook, ProposalStatus},
+    params::{self, ScheduledChange},
+    vrf::VrfProof,
+    rewards::{RewardConfig, RewardDistributor},
+    finality::{verify_proof_of_lock, FinalityGadget, FinalityOutcome, TimeoutConfig, Vote, VoteType},
+    events::{CompositeSink, ConsensusEventSink},
     ConsensusError, ConsensusEvent,
 };
+use crate::core::ledger::{executor::{ExecutionError, Executor}, state::State};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 
 /// Consensus operation mode (PoS, PoA, Hybrid, etc.)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,47 +34,137 @@ pub enum ConsensusMode {
 pub struct ConsensusEngine {
     pub mode: ConsensusMode,
     pub validators: ValidatorSet,
+    pub epoch_validators: ValidatorSet, // Set as selected at the start of the epoch (committed in headers)
     pub slashing: SlashingManager,
+    pub staking: StakingLedger,
+    pub matured_unbonding: Vec<UnbondingEntry>, // Released stake not yet credited back to the ledger
     pub governance: GovernanceHook,
-    pub quantum_mode: bool,
+    pub finality: FinalityGadget,
+    pub local_validator: Option<String>, // Address this node signs votes with (None = observer)
+    pub last_finalized: Option<Block>,
+    pub beacon_seed: Vec<u8>, // VRF output of the last finalized block (leader election seed)
+    pub events: CompositeSink, // Destinations for consensus events (none by default)
+    pub rewards: RewardConfig, // Issuance schedule and fee split applied by the ledger executor
+    pub block_limits: BlockLimits, // Header validity rules (size and gas caps follow governance)
+    pub recent_timestamps: VecDeque<u64>, // Timestamps of the latest finalized blocks, oldest first
+    pub state_root: Vec<u8>, // Ledger state root after the last finalized block (committed by the next header)
+    pub awaiting_execution: bool, // The ledger has not yet reported executing the last finalized block
+    signers: HashMap<String, Arc<dyn BlockSigner>>, // Signing backends for validators hosted on this node
 }
 
 impl ConsensusEngine {
-    /// Create a new ConsensusEngine instance
+    /// Create a new ConsensusEngine instance from the genesis validators
     pub fn new(validators: Vec<Validator>, mode: ConsensusMode) -> Self {
+        let staking = StakingLedger::new(StakingConfig::default(), validators);
+        let validators = ValidatorSet::new(ValidatorSet::load_all(&staking));
         ConsensusEngine {
             mode,
-            quantum_mode: true, // default: quantum-safe
-            validators: ValidatorSet::new(validators),
+            epoch_validators: validators.clone(),
+            validators,
             slashing: SlashingManager::default(),
+            staking,
+            matured_unbonding: vec![],
             governance: GovernanceHook::default(),
+            finality: FinalityGadget::new(1, TimeoutConfig::default()),
+            local_validator: None,
+            last_finalized: None,
+            beacon_seed: vec![0u8; 32], // genesis seed
+            events: CompositeSink::new(),
+            rewards: RewardConfig::default(),
+            block_limits: BlockLimits::default(),
+            recent_timestamps: VecDeque::new(),
+            state_root: vec![0u8; 32], // empty state trie
+            awaiting_execution: false,
+            signers: HashMap::new(),
         }
     }
 
-    /// Propose a new block (by validator)
-    pub fn propose_block(&self, validator_addr: &str, prev_block: &Block, txs: &[u8]) -> Result<Block, ConsensusError> {
+    /// Set the validator address this node votes as
+    pub fn set_local_validator(&mut self, address: &str) {
+        self.local_validator = Some(address.to_owned());
+    }
+
+    /// Register the signing backend for a validator hosted on this node. The signer's
+    /// key and scheme must match the validator's registered public key.
+    pub fn add_signer(&mut self, address: &str, signer: Arc<dyn BlockSigner>) -> Result<(), ConsensusError> {
+        let validator = self.validators.get_by_address(address)
+            .or_else(|| self.staking.candidates.get(address))
+            .ok_or(ConsensusError::Unauthorized)?;
+        if !signer.scheme().is_supported() {
+            return Err(ConsensusError::UnsupportedScheme(signer.scheme()));
+        }
+        if signer.public_key() != validator.public_key || signer.scheme() != validator.scheme {
+            return Err(ConsensusError::Unauthorized);
+        }
+        self.signers.insert(address.to_owned(), signer);
+        Ok(())
+    }
+
+    /// Signing backend for a locally hosted validator
+    fn signer(&self, address: &str) -> Result<&Arc<dyn BlockSigner>, ConsensusError> {
+        self.signers.get(address).ok_or(ConsensusError::Unauthorized)
+    }
+
+    /// Register a destination for consensus events (ring buffer, file, channel, audit log…)
+    pub fn add_event_sink(&mut self, sink: Arc<dyn ConsensusEventSink>) {
+        self.events.push(sink);
+    }
+
+    /// Propose a new block (by a validator) at local time `now_ms`; `execution` is the
+    /// ledger's result of running `txs` on top of `prev_block`
+    pub fn propose_block(&self, validator_addr: &str, prev_block: &Block, txs: &[u8], execution: &ExecutionSummary, now_ms: u64) -> Result<Block, ConsensusError> {
         let validator = self.validators.get_by_address(validator_addr)
             .ok_or(ConsensusError::Unauthorized)?;
+        let signer = self.signer(validator_addr)?;
+        if self.awaiting_execution {
+            return Err(ConsensusError::ExecutionPending);
+        }
 
         // Create block header
-        let header = BlockHeader::new(
+        let mut header = BlockHeader::new(
             prev_block.header.hash(),
             validator.public_key.clone(),
             txs,
         );
+        header.height = prev_block.header.height + 1;
+        header.timestamp = (now_ms / 1_000).max(self.median_time_past() + 1);
+        header.state_root = self.state_root.clone();
+        header.base_fee_per_gas = self.block_limits.next_base_fee(&prev_block.header);
+        header.gas_used = execution.gas_used;
+        header.logs_bloom = execution.logs_bloom.clone();
+        if txs.len() as u64 > self.block_limits.max_block_bytes {
+            return Err(ConsensusError::BlockTooLarge { size: txs.len() as u64, max: self.block_limits.max_block_bytes });
+        }
+        if header.gas_used > self.block_limits.max_block_gas {
+            return Err(ConsensusError::BlockGasExceeded { gas: header.gas_used, max: self.block_limits.max_block_gas });
+        }
 
-        // Block signature (quantum or classic)
-        let signature = if self.quantum_mode {
-            QuantumSignature::sign_dilithium(&validator.private_key, &header.hash())
-        } else {
-            QuantumSignature::sign_ed25519(&validator.private_key, &header.hash())
-        };
+        // Random beacon contribution for the next leader election
+        let (height, round) = (header.height, self.finality.round);
+        let vrf = VrfProof::prove(
+            |msg| signer.sign(&SignRequest::new(SignKind::Beacon, height, round, msg.to_vec())),
+            &VrfProof::input(&self.beacon_seed, height),
+        )?;
+        header.metadata = Some(vrf.to_metadata());
+
+        // Include pooled misbehaviour evidence
+        let evidence = self.slashing.pending_evidence();
+        header.evidence_root = Evidence::root(&evidence);
+
+        // Validator-set commitments for light clients
+        header.validators_hash = self.epoch_validators.hash();
+        header.next_validators_hash = self.next_epoch_validators(height, &evidence).hash();
+
+        // Block signature with the proposer's own scheme (classic or post-quantum)
+        let signature = signer.sign(&SignRequest::new(SignKind::Proposal, height, round, header.hash()))?;
 
         let block = Block {
             header,
             signature,
             proposer: validator_addr.to_owned(),
             transactions: txs.to_vec(),
+            commit: None,
+            evidence,
         };
 
         // Audit event
@@ -76,36 +176,445 @@ impl ConsensusEngine {
         Ok(block)
     }
 
-    /// Validate block signature (for block finality)
-    pub fn verify_block(&self, block: &Block) -> Result<(), ConsensusError> {
-        let pub_key = &block.header.proposer_public_key;
-        let hash = block.header.hash();
-        let signature = &block.signature;
+    /// Validate a block received at `now_ms`: header rules, then the signature against the
+    /// proposer's registered key and scheme, the beacon, evidence and validator-set commitments
+    pub fn verify_block(&self, block: &Block, now_ms: u64) -> Result<(), ConsensusError> {
+        self.verify_header(block, now_ms)?;
+        let proposer = self.validators.get_by_address(&block.proposer)
+            .ok_or(ConsensusError::Unauthorized)?;
+        if block.header.proposer_public_key != proposer.public_key {
+            return Err(ConsensusError::InvalidSignature);
+        }
+        if !proposer.verify_signed(SignKind::Proposal, block.header.height, 0, &block.header.hash(), &block.signature) {
+            return Err(ConsensusError::InvalidSignature);
+        }
+        if self.mode != ConsensusMode::ProofOfAuthority {
+            self.verify_beacon(block)?;
+        }
+        if Evidence::root(&block.evidence) != block.header.evidence_root {
+            return Err(ConsensusError::InvalidEvidence);
+        }
+        if block.header.validators_hash != self.epoch_validators.hash()
+            || block.header.next_validators_hash != self.next_epoch_validators(block.header.height, &block.evidence).hash()
+        {
+            return Err(ConsensusError::InvalidValidatorSet);
+        }
+        let unbonding_period = self.staking.config.unbonding_period;
+        for evidence in &block.evidence {
+            let offender = self.validator_record(evidence.offender());
+            self.slashing.check_evidence(evidence, offender, block.header.height, unbonding_period)?;
+        }
+        Ok(())
+    }
 
-        let valid = if self.quantum_mode {
-            QuantumSignature::verify_dilithium(pub_key, &hash, signature)
-        } else {
-            QuantumSignature::verify_ed25519(pub_key, &hash, signature)
+    /// Check height and parent continuity, the timestamp window, size and gas caps, the base fee and the roots
+    pub fn verify_header(&self, block: &Block, now_ms: u64) -> Result<(), ConsensusError> {
+        let header = &block.header;
+        let expected = self.last_finalized.as_ref()
+            .map(|parent| parent.header.height + 1)
+            .unwrap_or(self.finality.height);
+        if header.height != expected {
+            return Err(ConsensusError::InvalidHeight { expected, found: header.height });
+        }
+        if let Some(parent) = &self.last_finalized {
+            if header.parent_hash != parent.hash() {
+                return Err(ConsensusError::InvalidParentHash);
+            }
+        }
+
+        let median = self.median_time_past();
+        if header.timestamp <= median {
+            return Err(ConsensusError::TimestampTooOld { median });
+        }
+        let max = now_ms / 1_000 + self.block_limits.max_future_drift_secs;
+        if header.timestamp > max {
+            return Err(ConsensusError::TimestampTooFarInFuture { max });
+        }
+
+        let limits = &self.block_limits;
+        if block.size() > limits.max_block_bytes {
+            return Err(ConsensusError::BlockTooLarge { size: block.size(), max: limits.max_block_bytes });
+        }
+        if header.gas_used > limits.max_block_gas {
+            return Err(ConsensusError::BlockGasExceeded { gas: header.gas_used, max: limits.max_block_gas });
+        }
+        let expected_base_fee = match &self.last_finalized {
+            Some(parent) => limits.next_base_fee(&parent.header),
+            None => limits.initial_base_fee,
         };
-        if !valid {
-            return Err(ConsensusError::InvalidSignature);
+        if header.base_fee_per_gas != expected_base_fee {
+            return Err(ConsensusError::InvalidBaseFee { expected: expected_base_fee, found: header.base_fee_per_gas });
+        }
+        if header.tx_root != BlockHeader::calc_tx_root(&block.transactions) {
+            return Err(ConsensusError::InvalidTxRoot);
+        }
+        if self.awaiting_execution {
+            return Err(ConsensusError::ExecutionPending);
+        }
+        if header.state_root != self.state_root {
+            return Err(ConsensusError::InvalidStateRoot);
+        }
+        Ok(())
+    }
+
+    /// Ledger hook: post-execution results of the last finalized block. The header's gas and
+    /// logs bloom must match the execution, and the reported state root is what the next header has to commit.
+    pub fn on_executed(&mut self, block_hash: &[u8], execution: &ExecutionSummary) -> Result<(), ConsensusError> {
+        let block = self.last_finalized.as_ref()
+            .filter(|b| b.hash() == block_hash)
+            .ok_or(ConsensusError::UnexpectedExecution)?;
+        if block.header.gas_used != execution.gas_used {
+            return Err(ConsensusError::GasUsedMismatch { header: block.header.gas_used, executed: execution.gas_used });
+        }
+        if block.header.logs_bloom != execution.logs_bloom {
+            return Err(ConsensusError::LogsBloomMismatch);
+        }
+        self.state_root = execution.state_root.clone();
+        self.awaiting_execution = false;
+        Ok(())
+    }
+
+    /// Median timestamp of the latest finalized blocks; new blocks must be stamped after it
+    pub fn median_time_past(&self) -> u64 {
+        let timestamps: Vec<u64> = self.recent_timestamps.iter().copied().collect();
+        BlockLimits::median_time(&timestamps)
+    }
+
+    /// Verify and pool misbehaviour evidence reported by any node (e.g. received via gossip)
+    pub fn submit_evidence(&mut self, evidence: Evidence) -> Result<bool, ConsensusError> {
+        let (validator, height) = (evidence.offender().to_owned(), evidence.height());
+        let current_height = self.finality.height;
+        let offender = self.validator_record(&validator).cloned();
+        let unbonding_period = self.staking.config.unbonding_period;
+        let added = self.slashing.submit_evidence(evidence, offender.as_ref(), current_height, unbonding_period)?;
+        if added {
+            self.log_event(ConsensusEvent::EvidenceSubmitted { validator, height });
+        }
+        Ok(added)
+    }
+
+    /// Verify the proposer's VRF proof over the current beacon seed
+    pub fn verify_beacon(&self, block: &Block) -> Result<(), ConsensusError> {
+        let vrf = block.header.metadata.as_deref()
+            .and_then(VrfProof::from_metadata)
+            .ok_or(ConsensusError::InvalidBeacon)?;
+        let proposer = self.validators.get_by_address(&block.proposer)
+            .ok_or(ConsensusError::Unauthorized)?;
+        let input = VrfProof::input(&self.beacon_seed, block.header.height);
+        let verify = |pk: &[u8], msg: &[u8], sig: &[u8]| QuantumSignature::verify(proposer.scheme, pk, &sign_bytes(SignKind::Beacon, 0, 0, msg), sig);
+        if !vrf.verify(verify, &proposer.public_key, &input) {
+            return Err(ConsensusError::InvalidBeacon);
+        }
+        Ok(())
+    }
+
+    /// Verify a finalized block's commit certificate against the current validator set
+    pub fn verify_commit(&self, block: &Block) -> Result<(), ConsensusError> {
+        let commit = block.commit.as_ref().ok_or(ConsensusError::InvalidCommit)?;
+        if commit.block_hash != block.hash() || commit.height != block.header.height {
+            return Err(ConsensusError::InvalidCommit);
+        }
+        commit.verify(&self.validators)
+    }
+
+    /// Start deciding the next height at `now_ms`
+    pub fn start_height(&mut self, height: u64, now_ms: u64) {
+        self.finality.reset(height, now_ms);
+    }
+
+    /// Handle a block that `sender` proposed for `round`; returns votes to broadcast. The
+    /// sender must be the round's leader. A block first proposed by an earlier round's
+    /// leader is only accepted with `pol`, the 2/3+ prevotes it received (its proof of
+    /// lock), which are recorded like any other votes.
+    pub fn handle_proposal(&mut self, sender: &str, block: Block, round: u32, pol: Vec<Vote>, now_ms: u64) -> Result<FinalityOutcome, ConsensusError> {
+        let expected = self.select_leader(block.header.height, round)
+            .ok_or(ConsensusError::Unauthorized)?;
+        if expected.address != sender {
+            return Err(ConsensusError::Unauthorized);
+        }
+        if block.proposer != sender {
+            verify_proof_of_lock(&pol, block.header.height, round, &block.hash(), &self.validators)?;
         }
+        self.verify_block(&block, now_ms)?;
+        let mut votes = vec![];
+        for vote in pol {
+            votes.extend(self.handle_vote(vote, now_ms)?.votes);
+        }
+        let outcome = self.finality.on_proposal(block, round, &self.validators, now_ms);
+        let mut result = self.process_outcome(outcome, now_ms)?;
+        votes.append(&mut result.votes);
+        result.votes = votes;
+        Ok(result)
+    }
+
+    /// Handle a prevote/precommit from any validator
+    pub fn handle_vote(&mut self, vote: Vote, now_ms: u64) -> Result<FinalityOutcome, ConsensusError> {
+        let power = self.verify_vote(&vote)?;
+        let outcome = match self.finality.on_vote(vote.clone(), power, &self.validators, now_ms) {
+            Err(ConsensusError::InvalidVote) => {
+                // Conflicting vote from the same validator: turn it into evidence
+                if let Some(existing) = self.finality.existing_vote(&vote).cloned() {
+                    self.submit_evidence(Evidence::DuplicateVote { vote_a: existing, vote_b: vote })?;
+                }
+                return Err(ConsensusError::InvalidVote);
+            }
+            other => other?,
+        };
+        self.process_outcome(outcome, now_ms)
+    }
+
+    /// Adopt a block finalized elsewhere (catch-up / block sync) after checking its commit certificate
+    pub fn handle_commit(&mut self, block: Block, now_ms: u64) -> Result<(), ConsensusError> {
+        if block.header.height != self.finality.height {
+            return Err(ConsensusError::InvalidCommit);
+        }
+        self.verify_block(&block, now_ms)?;
+        self.verify_commit(&block)?;
+        self.on_finalized(&block, now_ms);
         Ok(())
     }
 
-    /// Select next leader/validator (PoS/PoA)
-    pub fn select_leader(&self, height: u64) -> Option<&Validator> {
+    /// Drive round timeouts; call periodically with the current time
+    pub fn handle_timeout(&mut self, now_ms: u64) -> Result<FinalityOutcome, ConsensusError> {
+        let outcome = self.finality.on_timeout(&self.validators, now_ms);
+        self.process_outcome(outcome, now_ms)
+    }
+
+    /// Sign a vote on behalf of a validator
+    pub fn sign_vote(&self, validator_addr: &str, vote_type: VoteType, height: u64, round: u32, block_hash: Option<Vec<u8>>) -> Result<Vote, ConsensusError> {
+        self.validators.get_by_address(validator_addr)
+            .ok_or(ConsensusError::Unauthorized)?;
+        let kind = match vote_type {
+            VoteType::Prevote => SignKind::Prevote,
+            VoteType::Precommit => SignKind::Precommit,
+        };
+        let mut vote = Vote::unsigned(vote_type, height, round, block_hash);
+        vote.validator = validator_addr.to_owned();
+        vote.signature = self.signer(validator_addr)?.sign(&SignRequest::new(kind, height, round, vote.sign_bytes()))?;
+        Ok(vote)
+    }
+
+    /// Check a vote's signer and signature; returns the signer's voting power
+    fn verify_vote(&self, vote: &Vote) -> Result<u64, ConsensusError> {
+        let validator = self.validators.get_by_address(&vote.validator)
+            .ok_or(ConsensusError::Unauthorized)?;
+        let power = self.validators.voting_power(&vote.validator);
+        if power == 0 {
+            return Err(ConsensusError::ValidatorSlashed);
+        }
+        if !vote.is_signed_by(validator) {
+            return Err(ConsensusError::InvalidSignature);
+        }
+        Ok(power)
+    }
+
+    /// Sign the gadget's vote intents as the local validator, feed them back in,
+    /// and record finalization
+    fn process_outcome(&mut self, outcome: FinalityOutcome, now_ms: u64) -> Result<FinalityOutcome, ConsensusError> {
+        let mut result = FinalityOutcome { votes: vec![], finalized: outcome.finalized };
+        let mut pending: VecDeque<Vote> = outcome.votes.into();
+
+        while let Some(intent) = pending.pop_front() {
+            let local = match (&self.local_validator, &result.finalized) {
+                (Some(addr), None) if self.validators.voting_power(addr) > 0 => addr.clone(),
+                _ => break,
+            };
+            let vote = self.sign_vote(&local, intent.vote_type, intent.height, intent.round, intent.block_hash)?;
+            let next = self.finality.on_vote(vote.clone(), self.validators.voting_power(&local), &self.validators, now_ms)?;
+            result.votes.push(vote);
+            pending.extend(next.votes);
+            if next.finalized.is_some() {
+                result.finalized = next.finalized;
+            }
+        }
+
+        if let Some(block) = &result.finalized {
+            self.on_finalized(block, now_ms);
+        }
+        Ok(result)
+    }
+
+    /// Apply the consequences of a finalized block and move to the next height
+    fn on_finalized(&mut self, block: &Block, now_ms: u64) {
+        let height = block.header.height;
+        self.log_event(ConsensusEvent::BlockFinalized {
+            hash: hex::encode(block.hash()),
+            height,
+        });
+
+        // Evidence punishments are known to the proposer and shape the next epoch set
+        let mut punished = vec![];
+        for evidence in &block.evidence {
+            if let Some(record) = self.slashing.apply_evidence(evidence, &mut self.validators, height) {
+                self.staking.jail_candidate(&record.validator, height);
+                punished.push(record);
+            }
+        }
+        self.apply_punishments(punished, height);
+        let transition = if self.staking.is_epoch_boundary(height) {
+            Some(self.staking.end_epoch(height, &self.validators))
+        } else {
+            None
+        };
+
+        // Downtime depends on this block's commit, so it only takes effect after the rotation
+        let signers: HashSet<String> = block.commit.iter()
+            .flat_map(|c| c.precommits.iter().map(|v| v.validator.clone()))
+            .collect();
+        let downtime = self.slashing.record_participation(&signers, &mut self.validators, height);
+        for record in &downtime {
+            self.staking.jail_candidate(&record.validator, height);
+        }
+
+        if let Some(transition) = transition {
+            self.epoch_validators = ValidatorSet::new(transition.active);
+            self.validators = self.epoch_validators.clone();
+            for record in &downtime {
+                if let Some(validator) = self.validators.get_by_address_mut(&record.validator) {
+                    validator.jail(height);
+                }
+            }
+            self.matured_unbonding.extend(transition.released);
+            self.log_event(ConsensusEvent::EpochTransition {
+                epoch: transition.epoch,
+                height,
+                validators: self.validators.validators.len(),
+            });
+        }
+        self.apply_punishments(downtime, height);
+
+        if let Some(vrf) = block.header.metadata.as_deref().and_then(VrfProof::from_metadata) {
+            self.beacon_seed = vrf.output;
+        }
+        self.recent_timestamps.push_back(block.header.timestamp);
+        while self.recent_timestamps.len() > self.block_limits.median_time_span {
+            self.recent_timestamps.pop_front();
+        }
+        self.finalize_governance(height, block.header.timestamp);
+        self.activate_parameters(height + 1);
+        self.last_finalized = Some(block.clone());
+        self.awaiting_execution = true;
+        self.finality.reset(height + 1, now_ms);
+    }
+
+    /// Mirror slashes into the staking ledger and report them; the reported amount
+    /// includes delegations and unbonding stake of offenders outside the active set
+    fn apply_punishments(&mut self, punished: Vec<SlashRecord>, height: u64) {
+        for record in punished {
+            let burned = self.staking.apply_slash(&record, height);
+            let amount = record.amount.max(burned);
+            self.log_event(ConsensusEvent::ValidatorSlashed { validator: record.validator.clone(), amount });
+            self.log_event(ConsensusEvent::ValidatorJailed { validator: record.validator, height });
+        }
+    }
+
+    /// Epoch set that will sign `height + 1` once the block at `height` carrying `evidence`
+    /// is finalized (changes only at epoch boundaries)
+    pub fn next_epoch_validators(&self, height: u64, evidence: &[Evidence]) -> ValidatorSet {
+        if !self.staking.is_epoch_boundary(height) {
+            return self.epoch_validators.clone();
+        }
+        let (mut slashing, mut validators, mut staking) = (self.slashing.clone(), self.validators.clone(), self.staking.clone());
+        for e in evidence {
+            if let Some(record) = slashing.apply_evidence(e, &mut validators, height) {
+                staking.jail_candidate(&record.validator, height);
+                staking.apply_slash(&record, height);
+            }
+        }
+        ValidatorSet::new(staking.end_epoch(height, &validators).active)
+    }
+
+    /// Select the leader for a round (PoS: beacon-seeded stake draw, PoA: round robin)
+    pub fn select_leader(&self, height: u64, round: u32) -> Option<&Validator> {
         match self.mode {
-            ConsensusMode::ProofOfStake | ConsensusMode::Hybrid => self.validators.select_by_stake(height),
-            ConsensusMode::ProofOfAuthority => self.validators.select_by_round_robin(height),
+            ConsensusMode::ProofOfStake | ConsensusMode::Hybrid => self.validators.select_by_stake(&self.beacon_seed, height, round),
+            ConsensusMode::ProofOfAuthority => self.validators.select_by_round_robin(height, round),
+        }
+    }
+
+    /// Live record of a validator: the active-set entry if present, else its staking candidacy
+    fn validator_record(&self, address: &str) -> Option<&Validator> {
+        self.validators.get_by_address(address)
+            .or_else(|| self.staking.candidates.get(address))
+    }
+
+    /// Sign an unjail transaction on behalf of a jailed validator hosted on this node
+    pub fn sign_unjail(&self, validator_addr: &str) -> Result<Vec<u8>, ConsensusError> {
+        let jailed_since = self.validator_record(validator_addr)
+            .and_then(|v| v.jailed_since)
+            .ok_or(ConsensusError::ValidatorNotJailed)?;
+        let payload = SlashingManager::unjail_sign_bytes(validator_addr, jailed_since);
+        self.signer(validator_addr)?.sign(&SignRequest::new(SignKind::Unjail, self.finality.height, 0, payload))
+    }
+
+    /// Release a jailed validator once its minimum jail duration has passed. It votes again
+    /// right away and is eligible for the active set from the next epoch.
+    pub fn submit_unjail(&mut self, validator_addr: &str, signature: &[u8]) -> Result<(), ConsensusError> {
+        let height = self.finality.height;
+        let validator = self.validator_record(validator_addr).ok_or(ConsensusError::Unauthorized)?;
+        self.slashing.check_unjail(validator, height)?;
+        let jailed_since = validator.jailed_since.unwrap_or_default();
+        if !validator.verify_signed(SignKind::Unjail, 0, 0, &SlashingManager::unjail_sign_bytes(validator_addr, jailed_since), signature) {
+            return Err(ConsensusError::InvalidSignature);
         }
+        let self_key = (validator_addr.to_owned(), validator_addr.to_owned());
+        if self.staking.delegations.get(&self_key).copied().unwrap_or(0) < self.staking.config.min_self_bond {
+            return Err(ConsensusError::Unauthorized);
+        }
+
+        if let Some(validator) = self.validators.get_by_address_mut(validator_addr) {
+            validator.unjail();
+        }
+        if let Some(candidate) = self.staking.candidates.get_mut(validator_addr) {
+            candidate.unjail();
+        }
+        self.log_event(ConsensusEvent::ValidatorUnjailed { validator: validator_addr.to_owned(), height });
+        Ok(())
+    }
+
+    /// Uptime of a validator (active or candidate)
+    pub fn uptime(&self, address: &str) -> Option<UptimeStats> {
+        self.validator_record(address).map(|v| self.slashing.uptime(v))
+    }
+
+    /// Uptime of every candidate, most reliable first
+    pub fn uptime_stats(&self) -> Vec<UptimeStats> {
+        let mut stats: Vec<UptimeStats> = self.staking.candidates
+            .keys()
+            .filter_map(|address| self.uptime(address))
+            .collect();
+        stats.sort_by(|a, b| b.uptime_bps.cmp(&a.uptime_bps).then_with(|| a.validator.cmp(&b.validator)));
+        stats
+    }
+
+    /// Reward snapshot of the current active set, for the ledger to pay out executed blocks
+    pub fn reward_distributor(&self) -> RewardDistributor {
+        RewardDistributor::snapshot(self.rewards.clone(), &self.validators, &self.staking)
+    }
+
+    /// Credit unbonding released at past epoch boundaries back to the delegators'
+    /// ledger accounts and drain it; on failure nothing is credited and the entries stay queued
+    pub fn take_matured_unbonding(&mut self, state: &mut State) -> Result<Vec<UnbondingEntry>, ExecutionError> {
+        Executor::release_unbonding(state, &self.matured_unbonding)?;
+        Ok(std::mem::take(&mut self.matured_unbonding))
     }
 
     /// Slash validator (for misbehavior)
     pub fn slash_validator(&mut self, address: &str, reason: &str, amount: u64) -> Result<(), ConsensusError> {
+        let height = self.finality.height;
         let validator = self.validators.get_by_address_mut(address)
             .ok_or(ConsensusError::Unauthorized)?;
-        self.slashing.slash(validator, amount);
+        let fraction_bps = match validator.stake {
+            0 => 0,
+            stake => (amount.min(stake) as u128 * BPS_DENOMINATOR as u128 / stake as u128) as u64,
+        };
+        self.slashing.slash(validator, amount, height);
+        self.staking.apply_slash(&SlashRecord {
+            validator: address.to_owned(),
+            amount,
+            fraction_bps,
+            infraction_height: height,
+        }, height);
         self.log_event(ConsensusEvent::ValidatorSlashed {
             validator: address.to_owned(),
             amount,
@@ -113,23 +622,105 @@ impl ConsensusEngine {
         Ok(())
     }
 
-    /// Apply governance update (on-chain config changes)
-    pub fn apply_governance_update(&mut self, key: &str, value: &str) -> Result<(), ConsensusError> {
-        if self.governance.validate_update(key, value) {
-            self.governance.apply_update(key, value);
+    /// Submit a parameter-change proposal; voting power is snapshotted at the current height
+    pub fn submit_governance_proposal(&mut self, proposer: &str, title: &str, description: &str, key: &str, value: &str, voting_period_secs: u64, now_secs: u64) -> Result<u64, ConsensusError> {
+        self.governance.submit_proposal(
+            proposer.to_owned(),
+            title.to_owned(),
+            description.to_owned(),
+            key.to_owned(),
+            value.to_owned(),
+            voting_period_secs,
+            now_secs,
+            self.finality.height,
+            &self.validators,
+        )
+    }
+
+    /// Sign a governance vote on behalf of a validator
+    pub fn sign_governance_vote(&self, validator_addr: &str, proposal_id: u64, approve: bool) -> Result<Vec<u8>, ConsensusError> {
+        let payload = GovernanceHook::vote_sign_bytes(proposal_id, approve);
+        self.signer(validator_addr)?.sign(&SignRequest::new(SignKind::Governance, self.finality.height, 0, payload))
+    }
+
+    /// Cast a signed, stake-weighted governance vote
+    pub fn submit_governance_vote(&mut self, proposal_id: u64, voter: &str, approve: bool, signature: &[u8], now_secs: u64) -> Result<ProposalStatus, ConsensusError> {
+        let validator = self.validators.get_by_address(voter)
+            .or_else(|| self.staking.candidates.get(voter))
+            .ok_or(ConsensusError::Unauthorized)?;
+        if !validator.verify_signed(SignKind::Governance, 0, 0, &GovernanceHook::vote_sign_bytes(proposal_id, approve), signature) {
+            return Err(ConsensusError::InvalidSignature);
+        }
+        self.governance.vote(proposal_id, voter, approve, now_secs)
+    }
+
+    /// Decide proposals whose voting period ended and schedule the approved changes
+    pub fn finalize_governance(&mut self, height: u64, now_secs: u64) {
+        for (id, status) in self.governance.finalize_expired(now_secs) {
+            if status != ProposalStatus::Approved {
+                continue;
+            }
+            let (key, value) = match self.governance.proposals.get(&id) {
+                Some(p) => (p.action_key.clone(), p.action_value.clone()),
+                None => continue,
+            };
+            let activation_height = height + self.governance.params.min_activation_delay;
+            if self.apply_governance_update(&key, &value, activation_height).is_ok() {
+                self.governance.mark_executed(id);
+            }
+        }
+    }
+
+    /// Validate a governance parameter change and schedule it for `activation_height`
+    pub fn apply_governance_update(&mut self, key: &str, value: &str, activation_height: u64) -> Result<ScheduledChange, ConsensusError> {
+        let current_height = self.finality.height;
+        let change = self.governance.schedule_update(key, value, current_height, activation_height)?;
+        self.log_event(ConsensusEvent::GovernanceScheduled {
+            key: change.key.clone(),
+            value: change.value.to_string(),
+            activation_height,
+        });
+        Ok(change)
+    }
+
+    /// Activate parameter changes due at `height` (called when that height starts)
+    pub fn activate_parameters(&mut self, height: u64) -> Vec<ScheduledChange> {
+        let applied = self.governance.params.activate(height);
+        if applied.is_empty() {
+            return applied;
+        }
+        self.sync_params();
+        for change in &applied {
             self.log_event(ConsensusEvent::GovernanceUpdate {
-                key: key.to_owned(),
-                value: value.to_owned(),
+                key: change.key.clone(),
+                value: change.value.to_string(),
             });
-            Ok(())
-        } else {
-            Err(ConsensusError::GovernanceRejected)
         }
+        applied
+    }
+
+    /// Copy the active governance parameters into the block limits, round
+    /// timeouts, staking and slashing configs
+    fn sync_params(&mut self) {
+        let registry = &self.governance.params;
+        let get = |key: &str| registry.get(key).unwrap_or_default();
+        self.finality.timeouts = TimeoutConfig::from_block_time(registry.block_time_ms());
+        self.block_limits.max_block_gas = get(params::MAX_BLOCK_GAS);
+        self.block_limits.max_block_bytes = get(params::MAX_BLOCK_BYTES);
+        self.block_limits.gas_target_bps = get(params::GAS_TARGET_BPS);
+        self.block_limits.base_fee_max_change_bps = get(params::BASE_FEE_MAX_CHANGE_BPS);
+        self.staking.config.max_validators = get(params::VALIDATOR_SET_SIZE) as usize;
+        self.staking.set_epoch_length(get(params::EPOCH_LENGTH));
+        self.staking.config.unbonding_period = get(params::UNBONDING_PERIOD);
+        self.slashing.config.double_sign_slash_bps = get(params::DOUBLE_SIGN_SLASH_BPS);
+        self.slashing.config.downtime_slash_bps = get(params::DOWNTIME_SLASH_BPS);
+        self.slashing.set_signed_blocks_window(get(params::SIGNED_BLOCKS_WINDOW) as usize);
+        self.slashing.config.min_signed_per_window_bps = get(params::MIN_SIGNED_PER_WINDOW_BPS);
+        self.slashing.config.min_jail_blocks = get(params::MIN_JAIL_BLOCKS);
     }
 
-    /// Emit consensus events (for audit/tracing)
+    /// Emit consensus events to every registered sink (for audit/tracing)
     fn log_event(&self, event: ConsensusEvent) {
-        // Here you can implement full audit log integration, persistent storage, etc.
-        println!("[CONSENSUS EVENT] {:?}", event);
+        self.events.emit(&event);
     }
 }
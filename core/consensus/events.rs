@@ -0,0 +1,253 @@
+//! OCOS-Chain: Consensus Event Sinks
+//!
+//! Destinations for `ConsensusEvent`s emitted by the engine: an in-memory ring
+//! buffer, a JSON-lines file, a broadcast channel for the API layer, and an
+//! adapter into the contracts audit log. Sinks are shared via `Arc` and can be
+//! combined with `CompositeSink`, so one engine can feed dashboards and forensic
+//! storage at the same time.
+//!
+//! Sinks never fail the consensus path: I/O or delivery errors are counted and
+//! the event is dropped for that sink only.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::contracts::audit::{ActionType, Address, AuditLog, LogLevel};
+use crate::core::consensus::ConsensusEvent;
+
+/// Receiver of consensus events
+pub trait ConsensusEventSink: Send + Sync {
+    fn emit(&self, event: &ConsensusEvent);
+}
+
+/// Fan-out to any number of sinks, in insertion order
+#[derive(Default, Clone)]
+pub struct CompositeSink {
+    sinks: Vec<Arc<dyn ConsensusEventSink>>,
+}
+
+impl CompositeSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder-style add
+    pub fn with(mut self, sink: Arc<dyn ConsensusEventSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub fn push(&mut self, sink: Arc<dyn ConsensusEventSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl ConsensusEventSink for CompositeSink {
+    fn emit(&self, event: &ConsensusEvent) {
+        for sink in &self.sinks {
+            sink.emit(event);
+        }
+    }
+}
+
+/// Keeps the most recent `capacity` events in memory
+pub struct RingBufferSink {
+    events: Mutex<VecDeque<ConsensusEvent>>,
+    capacity: usize,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> Self {
+        RingBufferSink {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Buffered events, oldest first
+    pub fn snapshot(&self) -> Vec<ConsensusEvent> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Remove and return all buffered events, oldest first
+    pub fn drain(&self) -> Vec<ConsensusEvent> {
+        self.lock().drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<ConsensusEvent>> {
+        // A panic while holding the lock cannot leave the deque inconsistent
+        self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ConsensusEventSink for RingBufferSink {
+    fn emit(&self, event: &ConsensusEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.lock();
+        if events.len() >= self.capacity {
+            events.pop_front(); // oldest removed
+        }
+        events.push_back(event.clone());
+    }
+}
+
+/// One JSON object per line: `{"timestamp_ms":..,"event":{..}}`
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp_ms: u64,
+    event: &'a ConsensusEvent,
+}
+
+/// Appends events to a file as JSON lines (flushed per event)
+pub struct JsonLinesSink {
+    writer: Mutex<BufWriter<File>>,
+    errors: AtomicU64,
+}
+
+impl JsonLinesSink {
+    /// Open (or create) `path` in append mode
+    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(JsonLinesSink {
+            writer: Mutex::new(BufWriter::new(file)),
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    /// Number of events that could not be written
+    pub fn error_count(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    fn write_line(&self, event: &ConsensusEvent) -> std::io::Result<()> {
+        let line = serde_json::to_string(&JsonLine { timestamp_ms: unix_millis(), event })?;
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+}
+
+impl ConsensusEventSink for JsonLinesSink {
+    fn emit(&self, event: &ConsensusEvent) {
+        if self.write_line(event).is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Publishes events on a tokio broadcast channel (e.g. for API subscriptions)
+pub struct BroadcastSink {
+    sender: broadcast::Sender<ConsensusEvent>,
+}
+
+impl BroadcastSink {
+    /// `capacity` is the per-subscriber backlog before slow receivers lag
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        BroadcastSink { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConsensusEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl ConsensusEventSink for BroadcastSink {
+    fn emit(&self, event: &ConsensusEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+/// `ActionType::Custom` codes used for consensus entries in the audit log
+pub mod audit_codes {
+    pub const BLOCK_PROPOSED: u8 = 0xC0;
+    pub const BLOCK_FINALIZED: u8 = 0xC1;
+    pub const VALIDATOR_SLASHED: u8 = 0xC2;
+    pub const VALIDATOR_JAILED: u8 = 0xC3;
+    pub const EVIDENCE_SUBMITTED: u8 = 0xC4;
+    pub const EPOCH_TRANSITION: u8 = 0xC5;
+    pub const VALIDATOR_UNJAILED: u8 = 0xC6;
+}
+
+/// Records events into a shared contracts `AuditLog`
+pub struct AuditLogSink {
+    log: Arc<Mutex<AuditLog>>,
+    actor: Address, // Node / module identity recorded as the actor
+    next_trace: AtomicU64,
+}
+
+impl AuditLogSink {
+    pub fn new(log: Arc<Mutex<AuditLog>>, actor: Address) -> Self {
+        AuditLogSink {
+            log,
+            actor,
+            next_trace: AtomicU64::new(1),
+        }
+    }
+
+    /// Audit action and severity for an event
+    pub fn classify(event: &ConsensusEvent) -> (ActionType, LogLevel) {
+        use audit_codes::*;
+        match event {
+            ConsensusEvent::BlockProposed { .. } => (ActionType::Custom(BLOCK_PROPOSED), LogLevel::Info),
+            ConsensusEvent::BlockFinalized { .. } => (ActionType::Custom(BLOCK_FINALIZED), LogLevel::Info),
+            ConsensusEvent::ValidatorSlashed { .. } => (ActionType::Custom(VALIDATOR_SLASHED), LogLevel::Warn),
+            ConsensusEvent::ValidatorJailed { .. } => (ActionType::Custom(VALIDATOR_JAILED), LogLevel::Warn),
+            ConsensusEvent::ValidatorUnjailed { .. } => (ActionType::Custom(VALIDATOR_UNJAILED), LogLevel::Info),
+            ConsensusEvent::EvidenceSubmitted { .. } => (ActionType::Custom(EVIDENCE_SUBMITTED), LogLevel::Warn),
+            ConsensusEvent::EpochTransition { .. } => (ActionType::Custom(EPOCH_TRANSITION), LogLevel::Info),
+            ConsensusEvent::GovernanceScheduled { .. } | ConsensusEvent::GovernanceUpdate { .. } => {
+                (ActionType::GovernanceProposal, LogLevel::Info)
+            }
+        }
+    }
+}
+
+impl ConsensusEventSink for AuditLogSink {
+    fn emit(&self, event: &ConsensusEvent) {
+        let (action_type, level) = Self::classify(event);
+        let trace_id = self.next_trace.fetch_add(1, Ordering::Relaxed);
+        let details = serde_json::to_string(event).unwrap_or_else(|_| format!("{:?}", event));
+        let mut log = self.log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        log.record(unix_millis() / 1_000, trace_id, self.actor, action_type, level, details);
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
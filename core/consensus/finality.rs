@@ -0,0 +1,515 @@
+//! OCOS-Chain: BFT Finality Gadget
+//!
+//! Tendermint-style round state machine (propose → prevote → precommit → commit).
+//! A block becomes final once validators holding more than 2/3 of the active
+//! stake precommit it in the same round; the collected precommits form a
+//! `CommitCertificate` that is attached to the block and verifiable by any node.
+//!
+//! A validator that sees 2/3+ prevotes for a block (a proof of lock, POL) locks
+//! on it and prevotes nothing else, unless a later proposal carries a POL from a
+//! round at or after its lock. The latest block with a POL is the node's valid
+//! block: when it leads a round it re-proposes that block with its prevotes
+//! instead of a new one, so validators locked on different blocks converge.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, HashSet};
+
+use crate::core::consensus::{
+    block::Block,
+    signer::SignKind,
+    validator::{Validator, ValidatorSet},
+    ConsensusError,
+};
+
+/// Step inside a single consensus round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundStep {
+    Propose,
+    Prevote,
+    Precommit,
+    Commit,
+}
+
+/// Vote kind (first and second voting phase)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VoteType {
+    Prevote,
+    Precommit,
+}
+
+/// Signed validator vote; `block_hash: None` is a nil vote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    pub vote_type: VoteType,
+    pub height: u64,
+    pub round: u32,
+    pub block_hash: Option<Vec<u8>>,
+    pub validator: String,
+    pub signature: Vec<u8>,
+}
+
+impl Vote {
+    /// Create an unsigned vote (validator and signature are filled in by the signer)
+    pub fn unsigned(vote_type: VoteType, height: u64, round: u32, block_hash: Option<Vec<u8>>) -> Self {
+        Vote {
+            vote_type,
+            height,
+            round,
+            block_hash,
+            validator: String::new(),
+            signature: vec![],
+        }
+    }
+
+    /// Signer kind of this vote (selects its signature domain and guard step)
+    pub fn sign_kind(&self) -> SignKind {
+        match self.vote_type {
+            VoteType::Prevote => SignKind::Prevote,
+            VoteType::Precommit => SignKind::Precommit,
+        }
+    }
+
+    /// True when `validator` signed this vote at its height and round
+    pub fn is_signed_by(&self, validator: &Validator) -> bool {
+        validator.verify_signed(self.sign_kind(), self.height, self.round, &self.sign_bytes(), &self.signature)
+    }
+
+    /// Canonical bytes covered by the vote signature
+    pub fn sign_bytes(&self) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-VOTE");
+        hasher.update([match self.vote_type {
+            VoteType::Prevote => 1u8,
+            VoteType::Precommit => 2u8,
+        }]);
+        hasher.update(self.height.to_be_bytes());
+        hasher.update(self.round.to_be_bytes());
+        match &self.block_hash {
+            Some(hash) => {
+                hasher.update([1u8]);
+                hasher.update(hash);
+            }
+            None => hasher.update([0u8]),
+        }
+        hasher.finalize().to_vec()
+    }
+}
+
+/// Votes of one type collected for one round, tallied by stake
+#[derive(Debug, Clone, Default)]
+pub struct VoteSet {
+    pub votes: HashMap<String, Vote>,
+    pub stake_by_block: HashMap<Option<Vec<u8>>, u64>,
+    pub total_stake: u64,
+}
+
+impl VoteSet {
+    /// Add a vote weighted by `power`. Returns false if the vote was already known.
+    /// A different vote from the same validator is rejected as a conflicting vote.
+    pub fn add_vote(&mut self, vote: Vote, power: u64) -> Result<bool, ConsensusError> {
+        if let Some(existing) = self.votes.get(&vote.validator) {
+            if existing.block_hash == vote.block_hash {
+                return Ok(false);
+            }
+            return Err(ConsensusError::InvalidVote);
+        }
+        *self.stake_by_block.entry(vote.block_hash.clone()).or_insert(0) += power;
+        self.total_stake += power;
+        self.votes.insert(vote.validator.clone(), vote);
+        Ok(true)
+    }
+
+    /// Value (block hash or nil) that reached the quorum, if any
+    pub fn two_thirds_majority(&self, quorum: u64) -> Option<Option<Vec<u8>>> {
+        self.stake_by_block
+            .iter()
+            .find(|(_, &stake)| stake >= quorum)
+            .map(|(hash, _)| hash.clone())
+    }
+
+    /// True if the quorum voted, regardless of the value
+    pub fn has_two_thirds_any(&self, quorum: u64) -> bool {
+        self.total_stake >= quorum
+    }
+
+    /// All votes for the given value
+    pub fn votes_for(&self, block_hash: &Option<Vec<u8>>) -> Vec<Vote> {
+        let mut votes: Vec<Vote> = self.votes
+            .values()
+            .filter(|v| &v.block_hash == block_hash)
+            .cloned()
+            .collect();
+        votes.sort_by(|a, b| a.validator.cmp(&b.validator));
+        votes
+    }
+}
+
+/// Proof of finality: 2/3+ stake of precommits for one block in one round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitCertificate {
+    pub height: u64,
+    pub round: u32,
+    pub block_hash: Vec<u8>,
+    pub precommits: Vec<Vote>,
+}
+
+impl CommitCertificate {
+    /// Stake of distinct active validators that precommitted the certified block
+    pub fn signed_stake(&self, validators: &ValidatorSet) -> u64 {
+        let mut seen = std::collections::HashSet::new();
+        self.precommits
+            .iter()
+            .filter(|v| v.block_hash.as_deref() == Some(self.block_hash.as_slice()))
+            .filter(|v| seen.insert(v.validator.clone()))
+            .map(|v| validators.voting_power(&v.validator))
+            .sum()
+    }
+
+    /// Verify every precommit signature and the 2/3+ stake threshold
+    pub fn verify(&self, validators: &ValidatorSet) -> Result<(), ConsensusError> {
+        for vote in &self.precommits {
+            if vote.vote_type != VoteType::Precommit
+                || vote.height != self.height
+                || vote.round != self.round
+                || vote.block_hash.as_deref() != Some(self.block_hash.as_slice())
+            {
+                return Err(ConsensusError::InvalidCommit);
+            }
+            let validator = validators
+                .get_by_address(&vote.validator)
+                .ok_or(ConsensusError::InvalidCommit)?;
+            if !vote.is_signed_by(validator) {
+                return Err(ConsensusError::InvalidSignature);
+            }
+        }
+        if self.signed_stake(validators) < validators.quorum_threshold() {
+            return Err(ConsensusError::InvalidCommit);
+        }
+        Ok(())
+    }
+}
+
+/// Check a proof of lock: signed prevotes of distinct validators holding 2/3+ of the
+/// stake for `block_hash`, all from one round before `round`. Returns that round.
+pub fn verify_proof_of_lock(
+    pol: &[Vote],
+    height: u64,
+    round: u32,
+    block_hash: &[u8],
+    validators: &ValidatorSet,
+) -> Result<u32, ConsensusError> {
+    let pol_round = pol.first().map(|v| v.round).ok_or(ConsensusError::InvalidProofOfLock)?;
+    let mut signers = HashSet::new();
+    for vote in pol {
+        if vote.vote_type != VoteType::Prevote
+            || vote.height != height
+            || vote.round != pol_round
+            || pol_round >= round
+            || vote.block_hash.as_deref() != Some(block_hash)
+        {
+            return Err(ConsensusError::InvalidProofOfLock);
+        }
+        let validator = validators
+            .get_by_address(&vote.validator)
+            .ok_or(ConsensusError::InvalidProofOfLock)?;
+        if !vote.is_signed_by(validator) {
+            return Err(ConsensusError::InvalidSignature);
+        }
+        signers.insert(vote.validator.as_str());
+    }
+    let stake: u64 = signers.iter().map(|v| validators.voting_power(v)).sum();
+    if stake < validators.quorum_threshold() {
+        return Err(ConsensusError::InvalidProofOfLock);
+    }
+    Ok(pol_round)
+}
+
+/// Round timeouts in milliseconds; each round adds `delta_ms` to every step
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutConfig {
+    pub propose_ms: u64,
+    pub prevote_ms: u64,
+    pub precommit_ms: u64,
+    pub delta_ms: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            propose_ms: 3_000,
+            prevote_ms: 1_000,
+            precommit_ms: 1_000,
+            delta_ms: 500,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// Timeouts scaled to a target block time: three block times to propose,
+    /// one per vote step, and half of one added per round (the default at 1s)
+    pub fn from_block_time(block_time_ms: u64) -> Self {
+        TimeoutConfig {
+            propose_ms: block_time_ms * 3,
+            prevote_ms: block_time_ms,
+            precommit_ms: block_time_ms,
+            delta_ms: block_time_ms / 2,
+        }
+    }
+
+    /// Timeout of a step in the given round
+    pub fn for_step(&self, step: RoundStep, round: u32) -> u64 {
+        let base = match step {
+            RoundStep::Propose => self.propose_ms,
+            RoundStep::Prevote => self.prevote_ms,
+            RoundStep::Precommit => self.precommit_ms,
+            RoundStep::Commit => 0,
+        };
+        base + self.delta_ms * round as u64
+    }
+}
+
+/// Result of feeding a message or timeout into the gadget
+#[derive(Debug, Default)]
+pub struct FinalityOutcome {
+    /// Votes the local validator should sign and broadcast
+    pub votes: Vec<Vote>,
+    /// Block finalized by this step, with its commit certificate attached
+    pub finalized: Option<Block>,
+}
+
+/// Round state for the height currently being decided
+#[derive(Debug, Clone)]
+pub struct FinalityGadget {
+    pub height: u64,
+    pub round: u32,
+    pub step: RoundStep,
+    pub timeouts: TimeoutConfig,
+    pub step_deadline_ms: u64,
+    pub proposals: HashMap<u32, Block>,
+    pub prevotes: HashMap<u32, VoteSet>,
+    pub precommits: HashMap<u32, VoteSet>,
+    /// Round and hash of the block this node is locked on
+    pub locked: Option<(u32, Vec<u8>)>,
+    /// Latest round in which a proposal reached 2/3+ prevotes, and that block
+    pub valid: Option<(u32, Block)>,
+}
+
+impl FinalityGadget {
+    /// Create a gadget deciding `height`
+    pub fn new(height: u64, timeouts: TimeoutConfig) -> Self {
+        FinalityGadget {
+            height,
+            round: 0,
+            step: RoundStep::Propose,
+            timeouts,
+            step_deadline_ms: 0,
+            proposals: HashMap::new(),
+            prevotes: HashMap::new(),
+            precommits: HashMap::new(),
+            locked: None,
+            valid: None,
+        }
+    }
+
+    /// Move on to a new height, discarding all round state
+    pub fn reset(&mut self, height: u64, now_ms: u64) {
+        *self = FinalityGadget::new(height, self.timeouts);
+        self.start_round(0, now_ms);
+    }
+
+    /// Enter the propose step of `round`
+    pub fn start_round(&mut self, round: u32, now_ms: u64) {
+        self.round = round;
+        self.enter_step(RoundStep::Propose, now_ms);
+    }
+
+    /// Handle a (pre-verified) proposal for `round`
+    pub fn on_proposal(&mut self, block: Block, round: u32, validators: &ValidatorSet, now_ms: u64) -> FinalityOutcome {
+        let mut outcome = FinalityOutcome::default();
+        if block.header.height != self.height || self.step == RoundStep::Commit {
+            return outcome;
+        }
+        self.proposals.entry(round).or_insert(block);
+        self.advance(validators, now_ms, &mut outcome);
+        outcome
+    }
+
+    /// Handle a (pre-verified) vote weighted by the voter's stake
+    pub fn on_vote(&mut self, vote: Vote, power: u64, validators: &ValidatorSet, now_ms: u64) -> Result<FinalityOutcome, ConsensusError> {
+        let mut outcome = FinalityOutcome::default();
+        if vote.height != self.height || self.step == RoundStep::Commit {
+            return Ok(outcome);
+        }
+        let set = match vote.vote_type {
+            VoteType::Prevote => self.prevotes.entry(vote.round).or_default(),
+            VoteType::Precommit => self.precommits.entry(vote.round).or_default(),
+        };
+        if set.add_vote(vote, power)? {
+            self.advance(validators, now_ms, &mut outcome);
+        }
+        Ok(outcome)
+    }
+
+    /// Block to propose when this node leads the current round: its valid block, if
+    /// any, with the prevotes that form its proof of lock
+    pub fn valid_proposal(&self) -> Option<(Block, Vec<Vote>)> {
+        let (round, block) = self.valid.as_ref()?;
+        let pol = self.prevotes.get(round)?.votes_for(&Some(block.hash()));
+        Some((block.clone(), pol))
+    }
+
+    /// Latest round before the current one with 2/3+ prevotes for `hash`
+    pub fn pol_round(&self, hash: &[u8], quorum: u64) -> Option<u32> {
+        self.prevotes
+            .iter()
+            .filter(|&(&round, set)| round < self.round && set.two_thirds_majority(quorum).flatten().as_deref() == Some(hash))
+            .map(|(&round, _)| round)
+            .max()
+    }
+
+    /// Vote already recorded from the same validator for the same round and type
+    pub fn existing_vote(&self, vote: &Vote) -> Option<&Vote> {
+        let sets = match vote.vote_type {
+            VoteType::Prevote => &self.prevotes,
+            VoteType::Precommit => &self.precommits,
+        };
+        sets.get(&vote.round)?.votes.get(&vote.validator)
+    }
+
+    /// Handle the expiry of the current step timer
+    pub fn on_timeout(&mut self, validators: &ValidatorSet, now_ms: u64) -> FinalityOutcome {
+        let mut outcome = FinalityOutcome::default();
+        if now_ms < self.step_deadline_ms {
+            return outcome;
+        }
+        match self.step {
+            RoundStep::Propose => {
+                outcome.votes.push(Vote::unsigned(VoteType::Prevote, self.height, self.round, None));
+                self.enter_step(RoundStep::Prevote, now_ms);
+            }
+            RoundStep::Prevote => {
+                outcome.votes.push(Vote::unsigned(VoteType::Precommit, self.height, self.round, None));
+                self.enter_step(RoundStep::Precommit, now_ms);
+            }
+            RoundStep::Precommit => self.start_round(self.round + 1, now_ms),
+            RoundStep::Commit => {}
+        }
+        self.advance(validators, now_ms, &mut outcome);
+        outcome
+    }
+
+    /// Apply threshold rules until the round state stops changing
+    fn advance(&mut self, validators: &ValidatorSet, now_ms: u64, outcome: &mut FinalityOutcome) {
+        loop {
+            let before = (self.round, self.step);
+            self.apply_rules(validators, now_ms, outcome);
+            if self.step == RoundStep::Commit || before == (self.round, self.step) {
+                break;
+            }
+        }
+    }
+
+    fn apply_rules(&mut self, validators: &ValidatorSet, now_ms: u64, outcome: &mut FinalityOutcome) {
+        let quorum = validators.quorum_threshold();
+
+        // Commit: 2/3+ precommits for a known proposal in any round
+        let mut rounds: Vec<u32> = self.precommits.keys().cloned().collect();
+        rounds.sort_unstable();
+        for round in rounds {
+            let set = &self.precommits[&round];
+            if let Some(Some(hash)) = set.two_thirds_majority(quorum) {
+                let proposal = self.proposals.values().find(|b| b.hash() == hash).cloned();
+                if let Some(mut block) = proposal {
+                    block.commit = Some(CommitCertificate {
+                        height: self.height,
+                        round,
+                        block_hash: hash.clone(),
+                        precommits: set.votes_for(&Some(hash)),
+                    });
+                    self.step = RoundStep::Commit;
+                    outcome.finalized = Some(block);
+                    return;
+                }
+            }
+        }
+
+        // Round skip: validators with 1/3+ stake are already voting in a later round
+        if let Some(round) = self.later_round_with_third(validators) {
+            self.start_round(round, now_ms);
+        }
+
+        // Proposal for the current round: prevote it unless locked on another block and
+        // no proof of lock for the proposal is at least as recent as the lock
+        if self.step == RoundStep::Propose {
+            if let Some(hash) = self.proposals.get(&self.round).map(|b| b.hash()) {
+                let acceptable = match &self.locked {
+                    Some((locked_round, locked_hash)) => {
+                        locked_hash == &hash || self.pol_round(&hash, quorum).is_some_and(|pol| pol >= *locked_round)
+                    }
+                    None => true,
+                };
+                let prevote = acceptable.then_some(hash);
+                outcome.votes.push(Vote::unsigned(VoteType::Prevote, self.height, self.round, prevote));
+                self.enter_step(RoundStep::Prevote, now_ms);
+            }
+        }
+
+        // Prevote quorum in the current round: lock and precommit (or precommit nil)
+        if self.step == RoundStep::Prevote {
+            let majority = self.prevotes.get(&self.round).and_then(|s| s.two_thirds_majority(quorum));
+            if let Some(value) = majority {
+                if let Some(hash) = &value {
+                    self.locked = Some((self.round, hash.clone()));
+                }
+                outcome.votes.push(Vote::unsigned(VoteType::Precommit, self.height, self.round, value));
+                self.enter_step(RoundStep::Precommit, now_ms);
+            }
+        }
+
+        // Prevote quorum for a known proposal in the current round: it becomes the valid block
+        if matches!(self.step, RoundStep::Prevote | RoundStep::Precommit)
+            && !matches!(&self.valid, Some((round, _)) if *round >= self.round)
+        {
+            let majority = self.prevotes.get(&self.round).and_then(|s| s.two_thirds_majority(quorum)).flatten();
+            let block = majority.and_then(|hash| self.proposals.values().find(|b| b.hash() == hash).cloned());
+            if let Some(block) = block {
+                self.valid = Some((self.round, block));
+            }
+        }
+
+        // Nil precommit quorum in the current round: move to the next round
+        if self.step == RoundStep::Precommit {
+            let majority = self.precommits.get(&self.round).and_then(|s| s.two_thirds_majority(quorum));
+            if let Some(None) = majority {
+                self.start_round(self.round + 1, now_ms);
+            }
+        }
+    }
+
+    /// Highest round above the current one in which voters hold more than 1/3 of the stake
+    fn later_round_with_third(&self, validators: &ValidatorSet) -> Option<u32> {
+        let threshold = validators.total_stake() / 3;
+        let mut rounds: Vec<u32> = self.prevotes
+            .keys()
+            .chain(self.precommits.keys())
+            .filter(|&&r| r > self.round)
+            .cloned()
+            .collect();
+        rounds.sort_unstable();
+        rounds.dedup();
+        rounds.into_iter().rev().find(|round| {
+            let voters: HashSet<&String> = [&self.prevotes, &self.precommits]
+                .iter()
+                .filter_map(|sets| sets.get(round))
+                .flat_map(|set| set.votes.keys())
+                .collect();
+            voters.iter().map(|v| validators.voting_power(v)).sum::<u64>() > threshold
+        })
+    }
+
+    fn enter_step(&mut self, step: RoundStep, now_ms: u64) {
+        self.step = step;
+        self.step_deadline_ms = now_ms + self.timeouts.for_step(step, self.round);
+    }
+}
@@ -3,8 +3,21 @@
 //! Modular on-chain governance engine supporting proposals, voting,
 //! dynamic consensus/config updates, and audit logging. Designed for
 //! fully decentralized, DAO-driven protocol management.
+//!
+//! Voting is identity-bound and stake-weighted: each active validator votes
+//! once, with the bonded stake it held when the proposal started. A proposal
+//! passes only if turnout reaches the quorum and the "for" side reaches the
+//! supermajority; it is decided automatically once `end_time` has passed.
+
+use sha3::{Digest, Sha3_256};
+use std::collections::{BTreeMap, HashMap};
 
-use std::collections::HashMap;
+use crate::core::consensus::{
+    params::{ParamError, ParamRegistry, ScheduledChange},
+    slashing::BPS_DENOMINATOR,
+    validator::ValidatorSet,
+    ConsensusError,
+};
 
 /// Governance action status
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -16,6 +29,22 @@ pub enum ProposalStatus {
     Expired,
 }
 
+/// Voting thresholds (basis points)
+#[derive(Debug, Clone)]
+pub struct GovernanceConfig {
+    pub quorum_bps: u64,        // Share of snapshot stake that must vote
+    pub supermajority_bps: u64, // Share of cast stake that must vote "for"
+}
+
+impl Default for GovernanceConfig {
+    fn default() -> Self {
+        GovernanceConfig {
+            quorum_bps: 3_340,        // 33.4%
+            supermajority_bps: 6_667, // 2/3
+        }
+    }
+}
+
 /// Governance proposal structure
 #[derive(Debug, Clone)]
 pub struct Proposal {
@@ -23,21 +52,45 @@ pub struct Proposal {
     pub proposer: String,
     pub title: String,
     pub description: String,
-    pub action_key: String,     // Target config/protocol parameter (e.g., "block_time")
+    pub action_key: String,     // Target protocol parameter (e.g., "block_time_ms")
     pub action_value: String,   // Proposed new value
-    pub votes_for: u64,
-    pub votes_against: u64,
+    pub votes_for: u64,         // Stake voting for
+    pub votes_against: u64,     // Stake voting against
+    pub voters: BTreeMap<String, bool>, // Validator address → vote (one per validator)
+    pub start_height: u64,
+    pub stake_snapshot: BTreeMap<String, u64>, // Voting power at `start_height`
     pub start_time: u64,
     pub end_time: u64,
     pub status: ProposalStatus,
     pub metadata: Option<HashMap<String, String>>, // Custom (KYC, tags, etc)
 }
 
+impl Proposal {
+    /// Total stake eligible to vote
+    pub fn eligible_stake(&self) -> u64 {
+        self.stake_snapshot.values().sum()
+    }
+
+    /// Outcome of the votes cast so far under the given thresholds
+    pub fn tally(&self, config: &GovernanceConfig) -> ProposalStatus {
+        let cast = self.votes_for as u128 + self.votes_against as u128;
+        let eligible = self.eligible_stake() as u128;
+        let quorum_met = eligible > 0 && cast * BPS_DENOMINATOR as u128 >= eligible * config.quorum_bps as u128;
+        let supermajority = cast > 0 && self.votes_for as u128 * BPS_DENOMINATOR as u128 >= cast * config.supermajority_bps as u128;
+        if quorum_met && supermajority {
+            ProposalStatus::Approved
+        } else {
+            ProposalStatus::Rejected
+        }
+    }
+}
+
 /// Governance state and hooks
 #[derive(Debug, Default, Clone)]
 pub struct GovernanceHook {
     pub proposals: HashMap<u64, Proposal>,
-    pub current_config: HashMap<String, String>, // On-chain key-value config store
+    pub params: ParamRegistry, // Typed on-chain parameters and scheduled changes
+    pub config: GovernanceConfig,
     pub next_proposal_id: u64,
 }
 
@@ -46,14 +99,37 @@ impl GovernanceHook {
     pub fn default() -> Self {
         GovernanceHook {
             proposals: HashMap::new(),
-            current_config: HashMap::new(),
+            params: ParamRegistry::default(),
+            config: GovernanceConfig::default(),
             next_proposal_id: 1,
         }
     }
 
-    /// Submit a new proposal (returns proposal ID)
-    pub fn submit_proposal(&mut self, proposer: String, title: String, desc: String, key: String, value: String, voting_period_secs: u64) -> u64 {
-        let now = Self::current_unix_timestamp();
+    /// Submit a new parameter-change proposal (returns proposal ID). The proposer must be
+    /// an active validator; voting power is snapshotted from `validators` at `start_height`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit_proposal(
+        &mut self,
+        proposer: String,
+        title: String,
+        desc: String,
+        key: String,
+        value: String,
+        voting_period_secs: u64,
+        start_time: u64,
+        start_height: u64,
+        validators: &ValidatorSet,
+    ) -> Result<u64, ConsensusError> {
+        if validators.voting_power(&proposer) == 0 {
+            return Err(ConsensusError::Unauthorized);
+        }
+        ParamRegistry::validate(&key, &value)?;
+
+        let stake_snapshot = validators
+            .sorted_active()
+            .into_iter()
+            .map(|v| (v.address.clone(), v.stake))
+            .collect();
         let prop = Proposal {
             id: self.next_proposal_id,
             proposer,
@@ -63,65 +139,100 @@ impl GovernanceHook {
             action_value: value,
             votes_for: 0,
             votes_against: 0,
-            start_time: now,
-            end_time: now + voting_period_secs,
+            voters: BTreeMap::new(),
+            start_height,
+            stake_snapshot,
+            start_time,
+            end_time: start_time + voting_period_secs,
             status: ProposalStatus::Pending,
             metadata: None,
         };
         self.proposals.insert(self.next_proposal_id, prop);
         self.next_proposal_id += 1;
-        self.next_proposal_id - 1
+        Ok(self.next_proposal_id - 1)
     }
 
-    /// Vote on a proposal (true = for, false = against)
-    pub fn vote(&mut self, proposal_id: u64, for_vote: bool) -> Option<ProposalStatus> {
-        let prop = self.proposals.get_mut(&proposal_id)?;
-        if prop.status != ProposalStatus::Pending || Self::current_unix_timestamp() > prop.end_time {
-            return None;
+    /// Bytes a validator signs to cast a vote
+    pub fn vote_sign_bytes(proposal_id: u64, approve: bool) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-GOV-VOTE");
+        hasher.update(proposal_id.to_be_bytes());
+        hasher.update([approve as u8]);
+        hasher.finalize().to_vec()
+    }
+
+    /// Record a validator's vote (true = for, false = against), weighted by its snapshot stake.
+    /// Each validator votes once; the caller is responsible for authenticating `voter`.
+    pub fn vote(&mut self, proposal_id: u64, voter: &str, approve: bool, now: u64) -> Result<ProposalStatus, ConsensusError> {
+        let prop = self.proposals.get_mut(&proposal_id).ok_or(ConsensusError::GovernanceRejected)?;
+        if prop.status != ProposalStatus::Pending || now > prop.end_time {
+            return Err(ConsensusError::GovernanceRejected);
         }
-        if for_vote {
-            prop.votes_for += 1;
+        let weight = match prop.stake_snapshot.get(voter) {
+            Some(&stake) if stake > 0 => stake,
+            _ => return Err(ConsensusError::Unauthorized),
+        };
+        if prop.voters.contains_key(voter) {
+            return Err(ConsensusError::InvalidVote);
+        }
+        prop.voters.insert(voter.to_owned(), approve);
+        if approve {
+            prop.votes_for += weight;
         } else {
-            prop.votes_against += 1;
+            prop.votes_against += weight;
         }
-        Some(prop.status.clone())
+        Ok(prop.status.clone())
     }
 
-    /// Finalize a proposal (can be called by anyone after voting period)
-    pub fn finalize_proposal(&mut self, proposal_id: u64) -> Option<ProposalStatus> {
+    /// Decide a proposal once its voting period is over
+    pub fn finalize_proposal(&mut self, proposal_id: u64, now: u64) -> Option<ProposalStatus> {
         let prop = self.proposals.get_mut(&proposal_id)?;
-        let now = Self::current_unix_timestamp();
         if prop.status != ProposalStatus::Pending || now < prop.end_time {
             return None;
         }
-        if prop.votes_for > prop.votes_against {
-            prop.status = ProposalStatus::Approved;
-        } else {
-            prop.status = ProposalStatus::Rejected;
-        }
+        prop.status = prop.tally(&self.config);
         Some(prop.status.clone())
     }
 
-    /// Apply an approved proposal (on-chain config update)
+    /// Decide every pending proposal whose voting period ended by `now` (in id order)
+    pub fn finalize_expired(&mut self, now: u64) -> Vec<(u64, ProposalStatus)> {
+        let mut due: Vec<u64> = self.proposals
+            .values()
+            .filter(|p| p.status == ProposalStatus::Pending && now >= p.end_time)
+            .map(|p| p.id)
+            .collect();
+        due.sort_unstable();
+        due.into_iter()
+            .filter_map(|id| self.finalize_proposal(id, now).map(|status| (id, status)))
+            .collect()
+    }
+
+    /// Mark an approved proposal as executed
+    pub fn mark_executed(&mut self, proposal_id: u64) {
+        if let Some(prop) = self.proposals.get_mut(&proposal_id) {
+            if prop.status == ProposalStatus::Approved {
+                prop.status = ProposalStatus::Executed;
+            }
+        }
+    }
+
+    /// Apply a parameter value immediately (genesis / tests); false if it fails validation
     pub fn apply_update(&mut self, key: &str, value: &str) -> bool {
-        self.current_config.insert(key.to_string(), value.to_string());
-        true
+        self.params.set(key, value).is_ok()
     }
 
-    /// Governance policy check for updates (can be extended with multi-sig, DAO token voting, etc)
-    pub fn validate_update(&self, _key: &str, _value: &str) -> bool {
-        // Always true. Real: Check DAO rules, token weights, KYC, etc.
-        true
+    /// Check an update against the parameter schema (known key, integer value, within bounds)
+    pub fn validate_update(&self, key: &str, value: &str) -> bool {
+        ParamRegistry::validate(key, value).is_ok()
     }
 
-    /// Get current config parameter value
-    pub fn get_config(&self, key: &str) -> Option<&String> {
-        self.current_config.get(key)
+    /// Validate an update and schedule it for a future activation height
+    pub fn schedule_update(&mut self, key: &str, value: &str, current_height: u64, activation_height: u64) -> Result<ScheduledChange, ParamError> {
+        self.params.schedule(key, value, current_height, activation_height)
     }
 
-    /// Helper: UNIX timestamp
-    fn current_unix_timestamp() -> u64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    /// Get current config parameter value
+    pub fn get_config(&self, key: &str) -> Option<u64> {
+        self.params.get(key)
     }
 }
@@ -0,0 +1,244 @@
+//! OCOS-Chain: Light Client
+//!
+//! Verifies block headers without executing blocks or storing state, for mobile
+//! wallets and bridges. Starting from a trusted checkpoint, each new header is
+//! accepted only with a valid commit certificate:
+//! - adjacent headers must extend the trusted header and be signed by 2/3+ of the
+//!   validator set it announced in `next_validators_hash` (validator-set transition)
+//! - skipped headers must be signed by 2/3+ of their own set *and* by more than
+//!   1/3 of the trusted set, so at least one honest trusted validator vouches for them
+//!
+//! Account balances are then checked with state-trie proofs (inclusion, or
+//! non-inclusion for unknown accounts) against the `state_root` of a verified header.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::core::consensus::{
+    block::BlockHeader,
+    finality::{CommitCertificate, VoteType},
+    slashing::BPS_DENOMINATOR,
+    validator::{Validator, ValidatorSet, ValidatorStatus},
+};
+
+pub use crate::core::ledger::state::AccountProof;
+
+/// Light client verification errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LightClientError {
+    #[error("Header does not extend the trusted header")]
+    NotAdjacent,
+    #[error("Header height must be above the trusted height {trusted}")]
+    NonIncreasingHeight { trusted: u64 },
+    #[error("Validator set does not match the header commitment")]
+    ValidatorSetMismatch,
+    #[error("Commit certificate is invalid for this header")]
+    InvalidCommit,
+    #[error("Less than the trust threshold of the trusted set signed the header")]
+    InsufficientTrust,
+    #[error("Trusted header is outside the trusting period")]
+    TrustExpired,
+    #[error("Header timestamp is in the future")]
+    HeaderFromFuture,
+    #[error("No verified header at height {0}")]
+    UnknownHeight(u64),
+    #[error("State proof does not match the verified state root")]
+    InvalidStateProof,
+}
+
+/// Trust parameters
+#[derive(Debug, Clone)]
+pub struct LightClientConfig {
+    pub trust_threshold_bps: u64,   // Share of trusted stake that must sign a skipped header (> 1/3)
+    pub trusting_period_secs: u64,  // Trusted headers older than this can no longer vouch
+    pub max_clock_drift_secs: u64,  // Tolerated header timestamp ahead of local time
+}
+
+impl Default for LightClientConfig {
+    fn default() -> Self {
+        LightClientConfig {
+            trust_threshold_bps: 3_334,
+            trusting_period_secs: 14 * 24 * 3_600, // below the unbonding period
+            max_clock_drift_secs: 10,
+        }
+    }
+}
+
+/// Header with its commit and the validator set that signed it (served by full nodes)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightBlock {
+    pub header: BlockHeader,
+    pub commit: CommitCertificate,
+    pub validators: Vec<Validator>, // Public keys and stake; must hash to `header.validators_hash`
+}
+
+impl LightBlock {
+    pub fn height(&self) -> u64 {
+        self.header.height
+    }
+
+    /// Validator set of this block, checked against the header commitment. Status is not
+    /// committed, so every member counts as active (as when the epoch set was selected).
+    pub fn validator_set(&self) -> Result<ValidatorSet, LightClientError> {
+        let set = ValidatorSet::new(self.validators.iter().cloned().map(|mut v| {
+            v.status = ValidatorStatus::Active;
+            v.jailed_since = None;
+            v
+        }).collect());
+        if set.hash() != self.header.validators_hash {
+            return Err(LightClientError::ValidatorSetMismatch);
+        }
+        Ok(set)
+    }
+
+    /// Check the commit certifies this header with 2/3+ of its own validator set
+    fn verify_commit(&self) -> Result<ValidatorSet, LightClientError> {
+        let set = self.validator_set()?;
+        if self.commit.height != self.header.height || self.commit.block_hash != self.header.hash() {
+            return Err(LightClientError::InvalidCommit);
+        }
+        self.commit.verify(&set).map_err(|_| LightClientError::InvalidCommit)?;
+        Ok(set)
+    }
+}
+
+/// Header the client currently trusts, with the set that signed it
+#[derive(Debug, Clone)]
+pub struct TrustedState {
+    pub header: BlockHeader,
+    pub validators: ValidatorSet,
+}
+
+/// Header-only chain follower
+#[derive(Debug, Clone)]
+pub struct LightClient {
+    pub config: LightClientConfig,
+    pub trusted: TrustedState,
+    verified: BTreeMap<u64, BlockHeader>,
+}
+
+impl LightClient {
+    /// Start from a checkpoint obtained out of band (e.g. hard-coded in the app);
+    /// only its internal consistency is checked
+    pub fn new(config: LightClientConfig, checkpoint: LightBlock) -> Result<Self, LightClientError> {
+        let validators = checkpoint.verify_commit()?;
+        let mut verified = BTreeMap::new();
+        verified.insert(checkpoint.height(), checkpoint.header.clone());
+        Ok(LightClient {
+            config,
+            trusted: TrustedState { header: checkpoint.header, validators },
+            verified,
+        })
+    }
+
+    pub fn trusted_height(&self) -> u64 {
+        self.trusted.header.height
+    }
+
+    /// Header verified at `height`
+    pub fn verified_header(&self, height: u64) -> Option<&BlockHeader> {
+        self.verified.get(&height)
+    }
+
+    /// Verify the header directly after the trusted one
+    pub fn verify_adjacent(&self, block: &LightBlock, now_secs: u64) -> Result<(), LightClientError> {
+        self.check_time(block, now_secs)?;
+        let trusted = &self.trusted.header;
+        if block.height() != trusted.height + 1 || block.header.parent_hash != trusted.hash() {
+            return Err(LightClientError::NotAdjacent);
+        }
+        // Transition proof: the trusted header announced the set that signs its successor
+        if block.header.validators_hash != trusted.next_validators_hash {
+            return Err(LightClientError::ValidatorSetMismatch);
+        }
+        block.verify_commit().map(|_| ())
+    }
+
+    /// Verify a later header by trusting that more than 1/3 of the trusted set is honest
+    pub fn verify_skipping(&self, block: &LightBlock, now_secs: u64) -> Result<(), LightClientError> {
+        self.check_time(block, now_secs)?;
+        if block.height() <= self.trusted_height() {
+            return Err(LightClientError::NonIncreasingHeight { trusted: self.trusted_height() });
+        }
+        block.verify_commit()?;
+
+        let signed = self.trusted_signed_stake(&block.commit);
+        let total = self.trusted.validators.total_stake() as u128;
+        if total == 0 || signed * BPS_DENOMINATOR as u128 <= total * self.config.trust_threshold_bps as u128 {
+            return Err(LightClientError::InsufficientTrust);
+        }
+        Ok(())
+    }
+
+    /// Verify a header (adjacent or skipping) and make it the trusted header
+    pub fn update(&mut self, block: LightBlock, now_secs: u64) -> Result<(), LightClientError> {
+        if block.height() == self.trusted_height() + 1 {
+            self.verify_adjacent(&block, now_secs)?;
+        } else {
+            self.verify_skipping(&block, now_secs)?;
+        }
+        let validators = block.validator_set()?;
+        self.verified.insert(block.height(), block.header.clone());
+        self.trusted = TrustedState { header: block.header, validators };
+        Ok(())
+    }
+
+    /// Reach `target` by bisection: skip as far as trust allows, fetching intermediate
+    /// headers from `fetch` when the validator set changed too much in between
+    pub fn verify_to<F>(&mut self, target: u64, fetch: F, now_secs: u64) -> Result<(), LightClientError>
+    where
+        F: Fn(u64) -> Option<LightBlock>,
+    {
+        let mut pending = vec![target];
+        while let Some(&height) = pending.last() {
+            if height <= self.trusted_height() {
+                pending.pop();
+                continue;
+            }
+            let block = fetch(height).ok_or(LightClientError::UnknownHeight(height))?;
+            match self.update(block, now_secs) {
+                Ok(()) => {
+                    pending.pop();
+                }
+                Err(LightClientError::InsufficientTrust) if height > self.trusted_height() + 1 => {
+                    pending.push(self.trusted_height() + (height - self.trusted_height()) / 2);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Balance of the proven account at a verified height (0 if proven absent),
+    /// checked against that header's state root
+    pub fn verify_account(&self, height: u64, proof: &AccountProof) -> Result<u128, LightClientError> {
+        let header = self.verified.get(&height).ok_or(LightClientError::UnknownHeight(height))?;
+        if !proof.verify(&header.state_root) {
+            return Err(LightClientError::InvalidStateProof);
+        }
+        Ok(proof.balance())
+    }
+
+    fn check_time(&self, block: &LightBlock, now_secs: u64) -> Result<(), LightClientError> {
+        if now_secs > self.trusted.header.timestamp + self.config.trusting_period_secs {
+            return Err(LightClientError::TrustExpired);
+        }
+        if block.header.timestamp > now_secs + self.config.max_clock_drift_secs {
+            return Err(LightClientError::HeaderFromFuture);
+        }
+        Ok(())
+    }
+
+    /// Trusted stake among valid precommits for the certified block (keys from the trusted set)
+    fn trusted_signed_stake(&self, commit: &CommitCertificate) -> u128 {
+        let mut seen = HashSet::new();
+        commit.precommits
+            .iter()
+            .filter(|v| v.vote_type == VoteType::Precommit && v.block_hash.as_deref() == Some(commit.block_hash.as_slice()))
+            .filter_map(|v| self.trusted.validators.get_by_address(&v.validator).map(|t| (v, t)))
+            .filter(|(v, t)| v.is_signed_by(t))
+            .filter(|(v, _)| seen.insert(v.validator.clone()))
+            .map(|(v, _)| self.trusted.validators.voting_power(&v.validator) as u128)
+            .sum()
+    }
+}
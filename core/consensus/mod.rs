@@ -15,41 +15,124 @@ pub mod quantum_sig;
 pub mod block;
 pub mod slashing;
 pub mod governance;
+pub mod finality;
+pub mod vrf;
+pub mod staking;
+pub mod events;
+pub mod simulator;
+pub mod params;
+pub mod signer;
+pub mod rewards;
+pub mod light_client;
 
 // Public re-exports for external use
 pub use consensus_engine::{ConsensusEngine, ConsensusMode};
 pub use validator::Validator;
-pub use quantum_sig::QuantumSignature;
-pub use block::{BlockHeader, Block};
-pub use slashing::SlashingManager;
-pub use governance::GovernanceHook;
+pub use quantum_sig::{QuantumSignature, SignatureScheme};
+pub use block::{BlockHeader, Block, BlockLimits, ExecutionSummary};
+pub use slashing::{DoubleSignEvidence, Evidence, Participation, SlashRecord, SlashingConfig, SlashingManager, UptimeStats};
+pub use governance::{GovernanceConfig, GovernanceHook, Proposal, ProposalStatus};
+pub use finality::{verify_proof_of_lock, CommitCertificate, FinalityGadget, RoundStep, Vote, VoteType};
+pub use vrf::VrfProof;
+pub use staking::{StakingConfig, StakingLedger, UnbondingEntry};
+pub use events::{AuditLogSink, BroadcastSink, CompositeSink, ConsensusEventSink, JsonLinesSink, RingBufferSink};
+
+pub use params::{ParamError, ParamRegistry, ParamSpec, ScheduledChange};
+pub use simulator::{Behaviour, Partition, SimConfig, SimulationReport, Simulator};
+pub use signer::{sign_bytes, BlockSigner, DoubleSignGuard, KeystoreSigner, LocalSigner, SignKind, SignRequest};
+#[cfg(unix)]
+pub use signer::{RemoteSigner, RemoteSignerServer};
+pub use light_client::{AccountProof, LightBlock, LightClient, LightClientConfig, LightClientError};
+pub use rewards::{IssuanceSchedule, RewardConfig, RewardDistribution, RewardDistributor, ValidatorReward};
+
+use serde::{Deserialize, Serialize};
 
 /// Global consensus error type
 #[derive(Debug, thiserror::Error)]
 pub enum ConsensusError {
     #[error("Invalid signature")]
     InvalidSignature,
+    #[error("Signing failed (invalid key or unsupported scheme)")]
+    SigningFailed,
+    #[error("Signature scheme {0:?} has no verified backend")]
+    UnsupportedScheme(SignatureScheme),
     #[error("Validator slashed")]
     ValidatorSlashed,
     #[error("Unauthorized consensus operation")]
     Unauthorized,
     #[error("Governance update rejected")]
     GovernanceRejected,
+    #[error("Invalid governance parameter: {0}")]
+    InvalidParameter(#[from] ParamError),
+    #[error("Invalid or conflicting vote")]
+    InvalidVote,
+    #[error("Invalid commit certificate")]
+    InvalidCommit,
+    #[error("Invalid proof of lock for a re-proposed block")]
+    InvalidProofOfLock,
+    #[error("Invalid random beacon proof")]
+    InvalidBeacon,
+    #[error("Invalid misbehaviour evidence")]
+    InvalidEvidence,
+    #[error("Header commits to a different validator set")]
+    InvalidValidatorSet,
+    #[error("Block height {found} does not follow the parent (expected {expected})")]
+    InvalidHeight { expected: u64, found: u64 },
+    #[error("Block does not extend the last finalized block")]
+    InvalidParentHash,
+    #[error("Block timestamp must be after the median time {median}")]
+    TimestampTooOld { median: u64 },
+    #[error("Block timestamp is ahead of the allowed {max}")]
+    TimestampTooFarInFuture { max: u64 },
+    #[error("Block size {size} exceeds the limit {max}")]
+    BlockTooLarge { size: u64, max: u64 },
+    #[error("Block gas {gas} exceeds the limit {max}")]
+    BlockGasExceeded { gas: u64, max: u64 },
+    #[error("Block base fee {found} does not follow the parent (expected {expected})")]
+    InvalidBaseFee { expected: u128, found: u128 },
+    #[error("Transaction root does not match the block's transactions")]
+    InvalidTxRoot,
+    #[error("State root does not match recomputation")]
+    InvalidStateRoot,
+    #[error("Waiting for the ledger to execute the last finalized block")]
+    ExecutionPending,
+    #[error("Execution reported for a block that is not the last finalized one")]
+    UnexpectedExecution,
+    #[error("Header gas used {header} differs from execution ({executed})")]
+    GasUsedMismatch { header: u64, executed: u64 },
+    #[error("Header logs bloom differs from execution")]
+    LogsBloomMismatch,
+    #[error("Keystore error: {0}")]
+    Keystore(String),
+    #[error("Remote signer error: {0}")]
+    RemoteSigner(String),
+    #[error("Validator is not jailed")]
+    ValidatorNotJailed,
+    #[error("Validator stays jailed until height {until}")]
+    JailPeriodNotElapsed { until: u64 },
+    #[error("Refused to double-sign at height {height}, round {round}")]
+    DoubleSignPrevented { height: u64, round: u32 },
+    #[error("Ledger account {account} cannot fund a bond of {amount}")]
+    InsufficientBond { account: String, amount: u64 },
     #[error("Unknown consensus error")]
     Unknown,
 }
 
-/// Consensus event types for audit/logging
-#[derive(Debug, Clone)]
+/// Consensus event types for audit/logging (delivered to `ConsensusEventSink`s)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConsensusEvent {
     BlockProposed { proposer: String, height: u64 },
     BlockFinalized { hash: String, height: u64 },
     ValidatorSlashed { validator: String, amount: u64 },
+    ValidatorJailed { validator: String, height: u64 },
+    ValidatorUnjailed { validator: String, height: u64 },
+    EvidenceSubmitted { validator: String, height: u64 },
+    EpochTransition { epoch: u64, height: u64, validators: usize },
+    GovernanceScheduled { key: String, value: String, activation_height: u64 },
     GovernanceUpdate { key: String, value: String },
 }
 
-/// Consensus initialization entrypoint
-pub fn initialize_consensus(mode: ConsensusMode) -> ConsensusEngine {
-    let validators = Validator::load_all();
-    ConsensusEngine::new(validators, mode)
+/// Consensus initialization entrypoint (genesis validators are bonded with their stake)
+pub fn initialize_consensus(mode: ConsensusMode, genesis: Vec<Validator>) -> ConsensusEngine {
+    ConsensusEngine::new(genesis, mode)
 }
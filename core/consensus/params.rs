@@ -0,0 +1,188 @@
+//! OCOS-Chain: On-Chain Consensus Parameter Registry
+//!
+//! Typed schema for the protocol parameters that governance may change. Every
+//! parameter has a unit, inclusive bounds and a default; updates are validated
+//! against the schema and scheduled for a future activation height so that all
+//! nodes switch at the same block.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::core::consensus::slashing::BPS_DENOMINATOR;
+
+pub const BLOCK_TIME_MS: &str = "block_time_ms";
+pub const MAX_BLOCK_GAS: &str = "max_block_gas";
+pub const MAX_BLOCK_BYTES: &str = "max_block_bytes";
+pub const GAS_TARGET_BPS: &str = "gas_target_bps";
+pub const BASE_FEE_MAX_CHANGE_BPS: &str = "base_fee_max_change_bps";
+pub const VALIDATOR_SET_SIZE: &str = "validator_set_size";
+pub const EPOCH_LENGTH: &str = "epoch_length";
+pub const UNBONDING_PERIOD: &str = "unbonding_period";
+pub const DOUBLE_SIGN_SLASH_BPS: &str = "double_sign_slash_bps";
+pub const DOWNTIME_SLASH_BPS: &str = "downtime_slash_bps";
+pub const SIGNED_BLOCKS_WINDOW: &str = "signed_blocks_window";
+pub const MIN_SIGNED_PER_WINDOW_BPS: &str = "min_signed_per_window_bps";
+pub const MIN_JAIL_BLOCKS: &str = "min_jail_blocks";
+
+/// Unit of a parameter value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParamKind {
+    Millis,
+    Gas,
+    Bytes,
+    Count,
+    Blocks,
+    BasisPoints, // 10_000 = 100%
+}
+
+/// Schema entry for one governable parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamSpec {
+    pub key: &'static str,
+    pub kind: ParamKind,
+    pub min: u64,
+    pub max: u64,
+    pub default: u64,
+}
+
+/// Every parameter governance may change
+pub const PARAM_SPECS: &[ParamSpec] = &[
+    ParamSpec { key: BLOCK_TIME_MS, kind: ParamKind::Millis, min: 100, max: 60_000, default: 1_000 },
+    ParamSpec { key: MAX_BLOCK_GAS, kind: ParamKind::Gas, min: 1_000_000, max: 1_000_000_000, default: 30_000_000 },
+    ParamSpec { key: MAX_BLOCK_BYTES, kind: ParamKind::Bytes, min: 65_536, max: 104_857_600, default: 4_194_304 },
+    ParamSpec { key: GAS_TARGET_BPS, kind: ParamKind::BasisPoints, min: 1_000, max: BPS_DENOMINATOR, default: 5_000 },
+    ParamSpec { key: BASE_FEE_MAX_CHANGE_BPS, kind: ParamKind::BasisPoints, min: 1, max: 5_000, default: 1_250 },
+    ParamSpec { key: VALIDATOR_SET_SIZE, kind: ParamKind::Count, min: 1, max: 1_000, default: 100 },
+    ParamSpec { key: EPOCH_LENGTH, kind: ParamKind::Blocks, min: 10, max: 1_000_000, default: 1_000 },
+    ParamSpec { key: UNBONDING_PERIOD, kind: ParamKind::Blocks, min: 1, max: 10_000_000, default: 100_800 },
+    ParamSpec { key: DOUBLE_SIGN_SLASH_BPS, kind: ParamKind::BasisPoints, min: 0, max: BPS_DENOMINATOR, default: 500 },
+    ParamSpec { key: DOWNTIME_SLASH_BPS, kind: ParamKind::BasisPoints, min: 0, max: BPS_DENOMINATOR, default: 10 },
+    ParamSpec { key: SIGNED_BLOCKS_WINDOW, kind: ParamKind::Blocks, min: 10, max: 100_000, default: 100 },
+    ParamSpec { key: MIN_SIGNED_PER_WINDOW_BPS, kind: ParamKind::BasisPoints, min: 0, max: BPS_DENOMINATOR, default: 5_000 },
+    ParamSpec { key: MIN_JAIL_BLOCKS, kind: ParamKind::Blocks, min: 1, max: 1_000_000, default: 600 },
+];
+
+/// Parameter validation errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParamError {
+    #[error("Unknown parameter: {0}")]
+    UnknownParameter(String),
+    #[error("Value is not a valid integer: {0}")]
+    InvalidValue(String),
+    #[error("Value {value} outside bounds [{min}, {max}]")]
+    OutOfBounds { value: u64, min: u64, max: u64 },
+    #[error("Activation height must be at least {earliest}")]
+    ActivationTooEarly { earliest: u64 },
+}
+
+/// A validated change waiting for its activation height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledChange {
+    pub key: String,
+    pub value: u64,
+    pub activation_height: u64,
+}
+
+/// Current parameter values plus changes scheduled for future heights
+#[derive(Debug, Clone)]
+pub struct ParamRegistry {
+    values: BTreeMap<String, u64>,
+    /// Activation height → changes, in scheduling order
+    pub scheduled: BTreeMap<u64, Vec<ScheduledChange>>,
+    /// Minimum number of blocks between scheduling and activation
+    pub min_activation_delay: u64,
+}
+
+impl Default for ParamRegistry {
+    fn default() -> Self {
+        ParamRegistry {
+            values: PARAM_SPECS.iter().map(|s| (s.key.to_string(), s.default)).collect(),
+            scheduled: BTreeMap::new(),
+            min_activation_delay: 10,
+        }
+    }
+}
+
+impl ParamRegistry {
+    /// Schema entry for a key
+    pub fn spec(key: &str) -> Option<&'static ParamSpec> {
+        PARAM_SPECS.iter().find(|s| s.key == key)
+    }
+
+    /// Parse and bounds-check a proposed value
+    pub fn validate(key: &str, raw: &str) -> Result<u64, ParamError> {
+        let spec = Self::spec(key).ok_or_else(|| ParamError::UnknownParameter(key.to_string()))?;
+        let value: u64 = raw.trim().parse().map_err(|_| ParamError::InvalidValue(raw.to_string()))?;
+        if value < spec.min || value > spec.max {
+            return Err(ParamError::OutOfBounds { value, min: spec.min, max: spec.max });
+        }
+        Ok(value)
+    }
+
+    /// Current value of a parameter
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.values.get(key).copied()
+    }
+
+    /// All current values, by key
+    pub fn values(&self) -> &BTreeMap<String, u64> {
+        &self.values
+    }
+
+    /// Set a value immediately (genesis configuration); validated against the schema
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<u64, ParamError> {
+        let value = Self::validate(key, raw)?;
+        self.values.insert(key.to_string(), value);
+        Ok(value)
+    }
+
+    /// Validate a change and schedule it for `activation_height`
+    pub fn schedule(&mut self, key: &str, raw: &str, current_height: u64, activation_height: u64) -> Result<ScheduledChange, ParamError> {
+        let value = Self::validate(key, raw)?;
+        let earliest = current_height + self.min_activation_delay;
+        if activation_height < earliest {
+            return Err(ParamError::ActivationTooEarly { earliest });
+        }
+        let change = ScheduledChange {
+            key: key.to_string(),
+            value,
+            activation_height,
+        };
+        self.scheduled.entry(activation_height).or_default().push(change.clone());
+        Ok(change)
+    }
+
+    /// Changes not yet active, in activation order
+    pub fn pending(&self) -> Vec<&ScheduledChange> {
+        self.scheduled.values().flatten().collect()
+    }
+
+    /// Apply every change due at or before `height`; returns the applied changes
+    pub fn activate(&mut self, height: u64) -> Vec<ScheduledChange> {
+        let later = self.scheduled.split_off(&(height + 1));
+        let due = std::mem::replace(&mut self.scheduled, later);
+        let applied: Vec<ScheduledChange> = due.into_values().flatten().collect();
+        for change in &applied {
+            self.values.insert(change.key.clone(), change.value);
+        }
+        applied
+    }
+
+    pub fn block_time_ms(&self) -> u64 {
+        self.value_or_default(BLOCK_TIME_MS)
+    }
+
+    pub fn max_block_gas(&self) -> u64 {
+        self.value_or_default(MAX_BLOCK_GAS)
+    }
+
+    pub fn max_block_bytes(&self) -> u64 {
+        self.value_or_default(MAX_BLOCK_BYTES)
+    }
+
+    fn value_or_default(&self, key: &str) -> u64 {
+        self.get(key)
+            .or_else(|| Self::spec(key).map(|s| s.default))
+            .unwrap_or(0)
+    }
+}
@@ -3,67 +3,90 @@
 //! Provides unified interfaces for both classical (Ed25519) and post-quantum (Dilithium, XMSS)
 //! digital signatures. All cryptographic operations should be implemented via
 //! this module to ensure future-proof security and upgradability.
+//!
+//! Every validator declares its `SignatureScheme`; signing and verification dispatch
+//! on that scheme, so classical and post-quantum validators can share one set.
+//!
+//! XMSS has no real backend yet (the crypto module only simulates it), so it is
+//! not accepted: XMSS signatures never verify, and validators and signers cannot
+//! be registered with the scheme.
+
+use serde::{Deserialize, Serialize};
 
-// Real world: These would use external cryptographic libraries
-// (e.g., libsodium for Ed25519, pqcrypto for Dilithium, xmss-rs for XMSS, etc.)
-use sha3::{Digest, Sha3_256};
+use crate::core::consensus::ConsensusError;
+use crate::core::crypto::{
+    dilithium_sign, dilithium_verify, ed25519_sign, ed25519_verify, xmss_sign, xmss_verify,
+    Ed25519Keypair, XmssKeypair,
+};
 
+/// Signature algorithm bound to a validator key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum SignatureScheme {
+    #[default]
     Ed25519,
     Dilithium,
     XMSS,
 }
 
+impl SignatureScheme {
+    /// False for schemes without a verified implementation (XMSS)
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, SignatureScheme::XMSS)
+    }
+}
+
 /// Public interface for all supported signature operations
 pub struct QuantumSignature;
 
 impl QuantumSignature {
+    /// Sign a message with the given scheme.
+    /// XMSS is stateful and must be signed through `sign_xmss` with its keypair.
+    pub fn sign(scheme: SignatureScheme, priv_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, ConsensusError> {
+        match scheme {
+            SignatureScheme::Ed25519 => Self::sign_ed25519(priv_key, msg),
+            SignatureScheme::Dilithium => Self::sign_dilithium(priv_key, msg),
+            SignatureScheme::XMSS => Err(ConsensusError::SigningFailed),
+        }
+    }
+
+    /// Verify a signature with the given scheme
+    pub fn verify(scheme: SignatureScheme, pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
+        match scheme {
+            SignatureScheme::Ed25519 => Self::verify_ed25519(pub_key, msg, sig),
+            SignatureScheme::Dilithium => Self::verify_dilithium(pub_key, msg, sig),
+            SignatureScheme::XMSS => Self::verify_xmss(pub_key, msg, sig),
+        }
+    }
+
     /// Sign a message using Ed25519 (classical)
-    pub fn sign_ed25519(_priv_key: &[u8], msg: &[u8]) -> Vec<u8> {
-        // Placeholder for demonstration.
-        // Real implementation should use cryptography crate, e.g., ed25519-dalek
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().to_vec()
+    pub fn sign_ed25519(priv_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, ConsensusError> {
+        // Validate the key up front: the underlying signer panics on malformed keys
+        Ed25519Keypair::from_private_key(priv_key).map_err(|_| ConsensusError::SigningFailed)?;
+        Ok(ed25519_sign(priv_key, msg))
     }
 
     /// Verify an Ed25519 signature
-    pub fn verify_ed25519(_pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
-        // Placeholder: In production use ed25519-dalek or libsodium
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().as_slice() == sig
+    pub fn verify_ed25519(pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
+        ed25519_verify(pub_key, msg, sig)
     }
 
     /// Sign a message using Dilithium (post-quantum)
-    pub fn sign_dilithium(_priv_key: &[u8], msg: &[u8]) -> Vec<u8> {
-        // Placeholder: Call real pqcrypto_dilithium implementation in production
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().to_vec()
+    pub fn sign_dilithium(priv_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, ConsensusError> {
+        dilithium_sign(priv_key, msg).map_err(|_| ConsensusError::SigningFailed)
     }
 
     /// Verify a Dilithium signature
-    pub fn verify_dilithium(_pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
-        // Placeholder: Call real pqcrypto_dilithium implementation in production
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().as_slice() == sig
+    pub fn verify_dilithium(pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
+        dilithium_verify(pub_key, msg, sig)
     }
 
-    /// Sign a message using XMSS (post-quantum, stateful)
-    pub fn sign_xmss(_priv_key: &[u8], msg: &[u8]) -> Vec<u8> {
-        // Placeholder: Integrate real xmss-rs crate in production
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().to_vec()
+    /// Sign a message using XMSS (post-quantum, stateful: advances the OTS index)
+    pub fn sign_xmss(keypair: &mut XmssKeypair, msg: &[u8]) -> Vec<u8> {
+        xmss_sign(keypair, msg)
     }
 
-    /// Verify an XMSS signature
-    pub fn verify_xmss(_pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
-        // Placeholder: Integrate real xmss-rs crate in production
-        let mut hasher = Sha3_256::new();
-        hasher.update(msg);
-        hasher.finalize().as_slice() == sig
+    /// Verify an XMSS signature: always false until a real XMSS backend exists
+    pub fn verify_xmss(pub_key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
+        xmss_verify(pub_key, msg, sig)
     }
 }
@@ -0,0 +1,267 @@
+//! OCOS-Chain: Block Rewards, Issuance & Fee Distribution
+//!
+//! Every executed block pays out newly minted tokens. The base fee its
+//! transactions were charged is burned by the ledger (EIP-1559), so there is no
+//! fee pool to share: a community tax on issuance goes to the treasury; the
+//! proposer receives a bonus on top of its share; the remainder is split across
+//! the active set by stake. Each validator keeps its commission and passes the
+//! rest to its delegators pro rata.
+//!
+//! Priority-fee tips are not part of the distribution: they belong to the
+//! proposer alone, and the ledger executor credits them in full to the account
+//! of the proposer's key, whether or not a distributor is used.
+//!
+//! A `RewardDistribution` records every amount paid, so the invariant
+//! `paid out == minted` can be checked for each block.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::core::consensus::{
+    slashing::BPS_DENOMINATOR,
+    staking::StakingLedger,
+    validator::ValidatorSet,
+};
+
+/// Stepwise decaying block reward with a perpetual tail emission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuanceSchedule {
+    pub initial_block_reward: u128,
+    pub reduction_interval: u64, // Blocks between reductions (0 = constant reward)
+    pub reduction_bps: u64,      // Cut applied at every reduction
+    pub tail_block_reward: u128, // Floor reached once reductions run out
+}
+
+impl Default for IssuanceSchedule {
+    fn default() -> Self {
+        IssuanceSchedule {
+            initial_block_reward: 10_000_000,
+            reduction_interval: 5_256_000, // ~1 year of 6s blocks
+            reduction_bps: 1_500,          // -15% per year
+            tail_block_reward: 500_000,
+        }
+    }
+}
+
+impl IssuanceSchedule {
+    /// Newly minted amount for the block at `height` (genesis mints nothing)
+    pub fn block_reward(&self, height: u64) -> u128 {
+        if height == 0 {
+            return 0;
+        }
+        let periods = match self.reduction_interval {
+            0 => 0,
+            interval => (height - 1) / interval,
+        };
+        let keep = BPS_DENOMINATOR.saturating_sub(self.reduction_bps) as u128;
+        let mut reward = self.initial_block_reward;
+        for _ in 0..periods {
+            reward = reward * keep / BPS_DENOMINATOR as u128;
+            if reward <= self.tail_block_reward {
+                break;
+            }
+        }
+        reward.max(self.tail_block_reward)
+    }
+
+    /// Total minted by blocks `1..=height`
+    pub fn total_issued(&self, height: u64) -> u128 {
+        let interval = match self.reduction_interval {
+            0 => return self.block_reward(1) * height as u128,
+            interval => interval,
+        };
+        let mut total = 0u128;
+        let mut start = 1u64;
+        while start <= height {
+            let end = (start + interval - 1).min(height);
+            total += self.block_reward(start) * (end - start + 1) as u128;
+            start = end + 1;
+        }
+        total
+    }
+}
+
+/// Reward policy parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardConfig {
+    pub issuance: IssuanceSchedule,
+    pub proposer_bonus_bps: u64,  // Share of the validator pool paid to the proposer first
+    pub community_tax_bps: u64,   // Share of minted rewards sent to the treasury
+    pub treasury: String,         // Treasury account address
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        RewardConfig {
+            issuance: IssuanceSchedule::default(),
+            proposer_bonus_bps: 500, // 5%
+            community_tax_bps: 200,  // 2%
+            treasury: "treasury".to_string(),
+        }
+    }
+}
+
+/// One validator's allocation and how it was split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorReward {
+    pub validator: String,
+    pub amount: u128,                         // Total allocated (incl. proposer bonus)
+    pub commission: u128,                     // Kept by the operator (incl. rounding dust)
+    pub delegator_shares: Vec<(String, u128)>, // Delegator → share, sorted by delegator
+}
+
+/// Audit record of one block's payout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardDistribution {
+    pub height: u64,
+    pub proposer: Option<String>,
+    pub minted: u128,
+    pub treasury_account: String,
+    pub treasury: u128,
+    pub proposer_bonus: u128,
+    pub validators: Vec<ValidatorReward>,
+}
+
+impl RewardDistribution {
+    /// Sum of everything paid out
+    pub fn total_paid(&self) -> u128 {
+        self.treasury + self.validators.iter().map(|v| v.amount).sum::<u128>()
+    }
+
+    /// True if every minted token was accounted for, exactly once
+    pub fn is_balanced(&self) -> bool {
+        let split_ok = self.validators.iter().all(|v| {
+            v.commission + v.delegator_shares.iter().map(|(_, s)| s).sum::<u128>() == v.amount
+        });
+        split_ok && self.total_paid() == self.minted
+    }
+
+    /// Amount credited per recipient account (commission, delegator shares, treasury)
+    pub fn payouts(&self) -> BTreeMap<String, u128> {
+        let mut payouts = BTreeMap::new();
+        let mut credit = |address: &str, amount: u128| {
+            if amount > 0 {
+                *payouts.entry(address.to_owned()).or_insert(0) += amount;
+            }
+        };
+        credit(&self.treasury_account, self.treasury);
+        for reward in &self.validators {
+            credit(&reward.validator, reward.commission);
+            for (delegator, share) in &reward.delegator_shares {
+                credit(delegator, *share);
+            }
+        }
+        payouts
+    }
+}
+
+/// Stake and commission snapshot used to pay out blocks of one epoch
+#[derive(Debug, Clone, Default)]
+pub struct RewardDistributor {
+    pub config: RewardConfig,
+    /// Active validators by address: (public key, voting power, commission bps)
+    validators: BTreeMap<String, (Vec<u8>, u64, u64)>,
+    /// Validator → (delegator, bonded), sorted by delegator
+    delegations: BTreeMap<String, Vec<(String, u64)>>,
+}
+
+impl RewardDistributor {
+    /// Snapshot the active set and its delegations
+    pub fn snapshot(config: RewardConfig, validators: &ValidatorSet, staking: &StakingLedger) -> Self {
+        let mut distributor = RewardDistributor { config, ..Default::default() };
+        for v in validators.sorted_active() {
+            let commission = staking.commission_of(&v.address);
+            distributor.validators.insert(v.address.clone(), (v.public_key.clone(), v.stake, commission));
+            distributor.delegations.insert(v.address.clone(), staking.delegations_of(&v.address));
+        }
+        distributor
+    }
+
+    /// Split `minted(height)` for a block proposed by the holder of `proposer_public_key`
+    pub fn distribute(&self, height: u64, proposer_public_key: &[u8]) -> RewardDistribution {
+        let minted = self.config.issuance.block_reward(height);
+        let proposer = self.validators
+            .iter()
+            .find(|(_, (pk, _, _))| pk.as_slice() == proposer_public_key)
+            .map(|(address, _)| address.clone());
+
+        let mut treasury = bps(minted, self.config.community_tax_bps);
+        let pool = minted - treasury;
+        let proposer_bonus = match proposer {
+            Some(_) => bps(pool, self.config.proposer_bonus_bps),
+            None => 0,
+        };
+
+        let shared = pool - proposer_bonus;
+        let total_stake: u128 = self.validators.values().map(|(_, stake, _)| *stake as u128).sum();
+        let mut allocated = 0u128;
+        let mut validators = vec![];
+        for (address, (_, stake, commission_bps)) in &self.validators {
+            let mut amount = match total_stake {
+                0 => 0,
+                total => shared * *stake as u128 / total,
+            };
+            allocated += amount;
+            if proposer.as_deref() == Some(address.as_str()) {
+                amount += proposer_bonus;
+            }
+            if amount > 0 {
+                validators.push(self.split(address, amount, *commission_bps));
+            }
+        }
+        // Stake-rounding dust, or the whole pool if nobody is bonded
+        treasury += shared - allocated;
+        let proposer_bonus = if validators.is_empty() {
+            treasury += proposer_bonus;
+            0
+        } else {
+            proposer_bonus
+        };
+
+        RewardDistribution {
+            height,
+            proposer,
+            minted,
+            treasury_account: self.config.treasury.clone(),
+            treasury,
+            proposer_bonus,
+            validators,
+        }
+    }
+
+    /// Take commission, then share the rest among delegators pro rata
+    fn split(&self, validator: &str, amount: u128, commission_bps: u64) -> ValidatorReward {
+        let mut commission = bps(amount, commission_bps);
+        let remainder = amount - commission;
+        let delegations = self.delegations.get(validator).cloned().unwrap_or_default();
+        let bonded: u128 = delegations.iter().map(|(_, a)| *a as u128).sum();
+
+        let mut delegator_shares = vec![];
+        let mut paid = 0u128;
+        if bonded > 0 {
+            for (delegator, stake) in delegations {
+                let share = remainder * stake as u128 / bonded;
+                paid += share;
+                if share > 0 {
+                    delegator_shares.push((delegator, share));
+                }
+            }
+        }
+        commission += remainder - paid;
+        ValidatorReward {
+            validator: validator.to_owned(),
+            amount,
+            commission,
+            delegator_shares,
+        }
+    }
+}
+
+fn bps(amount: u128, bps: u64) -> u128 {
+    amount * bps.min(BPS_DENOMINATOR) as u128 / BPS_DENOMINATOR as u128
+}
+
+/// Ledger account for a consensus address: hex-encoded account bytes, else the raw string bytes
+pub fn ledger_account(address: &str) -> Vec<u8> {
+    hex::decode(address.trim_start_matches("0x")).unwrap_or_else(|_| address.as_bytes().to_vec())
+}
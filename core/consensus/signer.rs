@@ -0,0 +1,470 @@
+//! OCOS-Chain: Validator Signing Backends
+//!
+//! Private keys never live in `Validator`; the engine asks a `BlockSigner` for
+//! every consensus signature. Backends:
+//! - `LocalSigner`: key held in process memory (development, tests, simulation)
+//! - `KeystoreSigner`: key loaded from an encrypted keystore file (Argon2id + AES-256-GCM)
+//! - `RemoteSigner`: key held by a separate signing service reached over a Unix
+//!   socket (tmkms style). The service refuses to double-sign and persists the last
+//!   signed height/round/step before releasing a signature.
+//!
+//! Every backend signs `sign_bytes(kind, height, round, payload)`, never the raw
+//! payload: a per-kind domain tag, then the height (proposals) or height and
+//! round (votes) the double-sign guard checked, then the payload. A signature
+//! obtained under one kind or position therefore never verifies as another, so
+//! a compromised node cannot route a conflicting vote around the guard by
+//! mislabelling it. Verifiers recompute the same bytes (`Validator::verify_signed`).
+//!
+//! Keystores authenticate their scheme and public key as AES-GCM associated data,
+//! and `open` checks the decrypted key against the stored public key.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::path::{Path, PathBuf};
+
+use crate::core::consensus::{
+    quantum_sig::{QuantumSignature, SignatureScheme},
+    ConsensusError,
+};
+use crate::core::crypto::{
+    aes_decrypt_with_aad, aes_encrypt_with_aad, argon2_derive, random_bytes,
+    classical::aes_gcm::{AES_KEY_LEN, AES_NONCE_LEN},
+};
+
+/// What a signature is for (drives double-sign protection)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignKind {
+    Proposal,
+    Prevote,
+    Precommit,
+    /// VRF beacon proof (deterministic per height, not guarded)
+    Beacon,
+    /// Governance vote (not bound to a consensus round)
+    Governance,
+    /// Unjail transaction (bound to the jailing it ends)
+    Unjail,
+}
+
+impl SignKind {
+    /// Position inside a round for guarded kinds
+    fn step(&self) -> Option<u8> {
+        match self {
+            SignKind::Proposal => Some(1),
+            SignKind::Prevote => Some(2),
+            SignKind::Precommit => Some(3),
+            SignKind::Beacon | SignKind::Governance | SignKind::Unjail => None,
+        }
+    }
+
+    /// Domain tag prefixed to everything signed as this kind
+    fn domain(&self) -> &'static [u8] {
+        match self {
+            SignKind::Proposal => b"OCOS-SIG/proposal",
+            SignKind::Prevote => b"OCOS-SIG/prevote",
+            SignKind::Precommit => b"OCOS-SIG/precommit",
+            SignKind::Beacon => b"OCOS-SIG/beacon",
+            SignKind::Governance => b"OCOS-SIG/governance",
+            SignKind::Unjail => b"OCOS-SIG/unjail",
+        }
+    }
+}
+
+/// Bytes a validator key actually signs for `payload`: the kind's domain tag, the
+/// height for proposals, height and round for votes, then the payload. Unguarded
+/// kinds ignore `height` and `round`.
+pub fn sign_bytes(kind: SignKind, height: u64, round: u32, payload: &[u8]) -> Vec<u8> {
+    let domain = kind.domain();
+    let mut bytes = Vec::with_capacity(domain.len() + 13 + payload.len());
+    bytes.extend_from_slice(domain);
+    bytes.push(0);
+    match kind {
+        SignKind::Proposal => bytes.extend_from_slice(&height.to_be_bytes()),
+        SignKind::Prevote | SignKind::Precommit => {
+            bytes.extend_from_slice(&height.to_be_bytes());
+            bytes.extend_from_slice(&round.to_be_bytes());
+        }
+        SignKind::Beacon | SignKind::Governance | SignKind::Unjail => {}
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// A signing request: the bytes to sign plus the consensus position they belong to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub kind: SignKind,
+    pub height: u64,
+    pub round: u32,
+    pub payload: Vec<u8>,
+}
+
+impl SignRequest {
+    pub fn new(kind: SignKind, height: u64, round: u32, payload: Vec<u8>) -> Self {
+        SignRequest { kind, height, round, payload }
+    }
+
+    /// Domain-separated bytes signed for this request
+    pub fn sign_bytes(&self) -> Vec<u8> {
+        sign_bytes(self.kind, self.height, self.round, &self.payload)
+    }
+}
+
+/// Source of consensus signatures for one validator key
+pub trait BlockSigner: Send + Sync {
+    fn public_key(&self) -> Vec<u8>;
+    fn scheme(&self) -> SignatureScheme;
+    fn sign(&self, request: &SignRequest) -> Result<Vec<u8>, ConsensusError>;
+}
+
+/// Signer holding the private key in memory (no double-sign protection)
+#[derive(Clone)]
+pub struct LocalSigner {
+    scheme: SignatureScheme,
+    public_key: Vec<u8>,
+    private_key: Vec<u8>,
+}
+
+impl LocalSigner {
+    pub fn new(scheme: SignatureScheme, public_key: Vec<u8>, private_key: Vec<u8>) -> Self {
+        LocalSigner { scheme, public_key, private_key }
+    }
+}
+
+impl std::fmt::Debug for LocalSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalSigner")
+            .field("scheme", &self.scheme)
+            .field("public_key", &hex::encode(&self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+impl BlockSigner for LocalSigner {
+    fn public_key(&self) -> Vec<u8> {
+        self.public_key.clone()
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<Vec<u8>, ConsensusError> {
+        QuantumSignature::sign(self.scheme, &self.private_key, &request.sign_bytes())
+    }
+}
+
+/// On-disk keystore format (JSON, hex-encoded binary fields)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u8,
+    scheme: SignatureScheme,
+    public_key: String,
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+const KEYSTORE_VERSION: u8 = 2;
+const KEYSTORE_KDF: &str = "argon2id";
+const KEYSTORE_SALT_LEN: usize = 16;
+
+/// Signer whose key is decrypted from a password-protected keystore file
+#[derive(Debug, Clone)]
+pub struct KeystoreSigner {
+    inner: LocalSigner,
+}
+
+impl KeystoreSigner {
+    /// Encrypt a private key into a new keystore file at `path`
+    pub fn create<P: AsRef<Path>>(path: P, password: &[u8], scheme: SignatureScheme, public_key: &[u8], private_key: &[u8]) -> Result<(), ConsensusError> {
+        let salt = random_bytes(KEYSTORE_SALT_LEN);
+        let nonce = random_bytes(AES_NONCE_LEN);
+        let key = Self::derive_key(password, &salt)?;
+        let ciphertext = aes_encrypt_with_aad(&key, &nonce, private_key, &Self::aad(scheme, public_key))
+            .map_err(|e| ConsensusError::Keystore(e.to_string()))?;
+
+        let file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            scheme,
+            public_key: hex::encode(public_key),
+            kdf: KEYSTORE_KDF.to_string(),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(|e| ConsensusError::Keystore(e.to_string()))?;
+        write_atomically(path.as_ref(), &json).map_err(|e| ConsensusError::Keystore(e.to_string()))
+    }
+
+    /// Decrypt a keystore; fails on a wrong password, a tampered file (including its
+    /// scheme or public key) or a key that does not match the public key
+    pub fn open<P: AsRef<Path>>(path: P, password: &[u8]) -> Result<Self, ConsensusError> {
+        let keystore_err = |e: &dyn std::fmt::Display| ConsensusError::Keystore(e.to_string());
+        let json = std::fs::read(path.as_ref()).map_err(|e| keystore_err(&e))?;
+        let file: KeystoreFile = serde_json::from_slice(&json).map_err(|e| keystore_err(&e))?;
+        if file.version != KEYSTORE_VERSION || file.kdf != KEYSTORE_KDF {
+            return Err(ConsensusError::Keystore("unsupported keystore format".into()));
+        }
+        let decode = |field: &str| hex::decode(field).map_err(|e| keystore_err(&e));
+        let (salt, nonce, ciphertext) = (decode(&file.salt)?, decode(&file.nonce)?, decode(&file.ciphertext)?);
+        let public_key = decode(&file.public_key)?;
+
+        let key = Self::derive_key(password, &salt)?;
+        let private_key = aes_decrypt_with_aad(&key, &nonce, &ciphertext, &Self::aad(file.scheme, &public_key))
+            .map_err(|_| ConsensusError::Keystore("wrong password or corrupted keystore".into()))?;
+
+        // The key must sign for the public key the file claims
+        let probe = b"OCOS-KEYSTORE/probe";
+        let matches = QuantumSignature::sign(file.scheme, &private_key, probe)
+            .map(|signature| QuantumSignature::verify(file.scheme, &public_key, probe, &signature))
+            .unwrap_or(false);
+        if !matches {
+            return Err(ConsensusError::Keystore("private key does not match the public key".into()));
+        }
+        Ok(KeystoreSigner {
+            inner: LocalSigner::new(file.scheme, public_key, private_key),
+        })
+    }
+
+    /// Associated data binding the ciphertext to the key's scheme and public key
+    fn aad(scheme: SignatureScheme, public_key: &[u8]) -> Vec<u8> {
+        let mut aad = format!("ocos-keystore-v{}:{:?}:", KEYSTORE_VERSION, scheme).into_bytes();
+        aad.extend_from_slice(public_key);
+        aad
+    }
+
+    fn derive_key(password: &[u8], salt: &[u8]) -> Result<Vec<u8>, ConsensusError> {
+        argon2_derive(password, salt, AES_KEY_LEN).map_err(|e| ConsensusError::Keystore(e.to_string()))
+    }
+}
+
+impl BlockSigner for KeystoreSigner {
+    fn public_key(&self) -> Vec<u8> {
+        self.inner.public_key()
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        self.inner.scheme()
+    }
+
+    fn sign(&self, request: &SignRequest) -> Result<Vec<u8>, ConsensusError> {
+        self.inner.sign(request)
+    }
+}
+
+/// Last guarded position signed by a key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignState {
+    pub height: u64,
+    pub round: u32,
+    pub step: u8,
+    pub payload_hash: Vec<u8>,
+}
+
+/// Double-sign protection: guarded signatures must move strictly forward in
+/// (height, round, step); re-signing the identical payload is allowed. The state
+/// is persisted before a signature is released, so a restart cannot roll it back.
+#[derive(Debug, Clone, Default)]
+pub struct DoubleSignGuard {
+    pub state_path: Option<PathBuf>,
+    pub last: Option<SignState>,
+}
+
+impl DoubleSignGuard {
+    /// In-memory guard (state lost on restart)
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Guard backed by a state file; loads the previous state if present
+    pub fn with_state_file<P: AsRef<Path>>(path: P) -> Result<Self, ConsensusError> {
+        let path = path.as_ref().to_path_buf();
+        let last = match std::fs::read(&path) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes).map_err(|e| ConsensusError::RemoteSigner(e.to_string()))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(ConsensusError::RemoteSigner(e.to_string())),
+        };
+        Ok(DoubleSignGuard { state_path: Some(path), last })
+    }
+
+    /// Check a request against the last signed position and record it
+    pub fn check_and_record(&mut self, request: &SignRequest) -> Result<(), ConsensusError> {
+        let Some(step) = request.kind.step() else {
+            return Ok(());
+        };
+        let payload_hash = Sha3_256::digest(&request.payload).to_vec();
+        let refused = ConsensusError::DoubleSignPrevented { height: request.height, round: request.round };
+
+        if let Some(last) = &self.last {
+            match (request.height, request.round, step).cmp(&(last.height, last.round, last.step)) {
+                std::cmp::Ordering::Less => return Err(refused),
+                std::cmp::Ordering::Equal if last.payload_hash != payload_hash => return Err(refused),
+                std::cmp::Ordering::Equal => return Ok(()),
+                std::cmp::Ordering::Greater => {}
+            }
+        }
+
+        let state = SignState { height: request.height, round: request.round, step, payload_hash };
+        if let Some(path) = &self.state_path {
+            let json = serde_json::to_vec(&state).map_err(|e| ConsensusError::RemoteSigner(e.to_string()))?;
+            write_atomically(path, &json).map_err(|e| ConsensusError::RemoteSigner(e.to_string()))?;
+        }
+        self.last = Some(state);
+        Ok(())
+    }
+}
+
+/// Write via a temporary file and rename, so readers never see a partial file
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Wire protocol between `RemoteSigner` and `RemoteSignerServer` (one JSON object per line)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RemoteRequest {
+    PublicKey,
+    Sign(SignRequest),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RemoteResponse {
+    PublicKey { scheme: SignatureScheme, public_key: Vec<u8> },
+    Signature(Vec<u8>),
+    DoubleSignRefused { height: u64, round: u32 },
+    Error(String),
+}
+
+#[cfg(unix)]
+pub use remote::{RemoteSigner, RemoteSignerServer};
+
+#[cfg(unix)]
+mod remote {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    const REMOTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Client side: forwards signing requests to a signing service over a Unix socket
+    #[derive(Debug, Clone)]
+    pub struct RemoteSigner {
+        socket_path: PathBuf,
+        scheme: SignatureScheme,
+        public_key: Vec<u8>,
+    }
+
+    impl RemoteSigner {
+        /// Connect to the service and fetch the public key it signs for
+        pub fn connect<P: AsRef<Path>>(socket_path: P) -> Result<Self, ConsensusError> {
+            let socket_path = socket_path.as_ref().to_path_buf();
+            match Self::call(&socket_path, &RemoteRequest::PublicKey)? {
+                RemoteResponse::PublicKey { scheme, public_key } => Ok(RemoteSigner { socket_path, scheme, public_key }),
+                other => Err(ConsensusError::RemoteSigner(format!("unexpected response: {:?}", other))),
+            }
+        }
+
+        fn call(socket_path: &Path, request: &RemoteRequest) -> Result<RemoteResponse, ConsensusError> {
+            let io_err = |e: std::io::Error| ConsensusError::RemoteSigner(e.to_string());
+            let mut stream = UnixStream::connect(socket_path).map_err(io_err)?;
+            stream.set_read_timeout(Some(REMOTE_TIMEOUT)).map_err(io_err)?;
+            stream.set_write_timeout(Some(REMOTE_TIMEOUT)).map_err(io_err)?;
+
+            let mut line = serde_json::to_vec(request).map_err(|e| ConsensusError::RemoteSigner(e.to_string()))?;
+            line.push(b'\n');
+            stream.write_all(&line).map_err(io_err)?;
+
+            let mut response = String::new();
+            BufReader::new(stream).read_line(&mut response).map_err(io_err)?;
+            serde_json::from_str(&response).map_err(|e| ConsensusError::RemoteSigner(e.to_string()))
+        }
+    }
+
+    impl BlockSigner for RemoteSigner {
+        fn public_key(&self) -> Vec<u8> {
+            self.public_key.clone()
+        }
+
+        fn scheme(&self) -> SignatureScheme {
+            self.scheme
+        }
+
+        fn sign(&self, request: &SignRequest) -> Result<Vec<u8>, ConsensusError> {
+            match Self::call(&self.socket_path, &RemoteRequest::Sign(request.clone()))? {
+                RemoteResponse::Signature(signature) => Ok(signature),
+                RemoteResponse::DoubleSignRefused { height, round } => Err(ConsensusError::DoubleSignPrevented { height, round }),
+                RemoteResponse::Error(msg) => Err(ConsensusError::RemoteSigner(msg)),
+                other => Err(ConsensusError::RemoteSigner(format!("unexpected response: {:?}", other))),
+            }
+        }
+    }
+
+    /// Service side: owns the key and the double-sign guard
+    pub struct RemoteSignerServer {
+        signer: Box<dyn BlockSigner>,
+        guard: Mutex<DoubleSignGuard>,
+    }
+
+    impl RemoteSignerServer {
+        pub fn new(signer: Box<dyn BlockSigner>, guard: DoubleSignGuard) -> Self {
+            RemoteSignerServer { signer, guard: Mutex::new(guard) }
+        }
+
+        /// Bind the service socket (removes a stale socket file first)
+        pub fn bind<P: AsRef<Path>>(socket_path: P) -> std::io::Result<UnixListener> {
+            let path = socket_path.as_ref();
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            UnixListener::bind(path)
+        }
+
+        /// Serve connections until the listener fails
+        pub fn serve(&self, listener: UnixListener) -> std::io::Result<()> {
+            for stream in listener.incoming() {
+                self.handle_connection(stream?)?;
+            }
+            Ok(())
+        }
+
+        /// Answer every request line on one connection
+        pub fn handle_connection(&self, stream: UnixStream) -> std::io::Result<()> {
+            let mut writer = stream.try_clone()?;
+            for line in BufReader::new(stream).lines() {
+                let response = match serde_json::from_str::<RemoteRequest>(&line?) {
+                    Ok(request) => self.handle(request),
+                    Err(e) => RemoteResponse::Error(e.to_string()),
+                };
+                let mut out = serde_json::to_vec(&response).map_err(std::io::Error::other)?;
+                out.push(b'\n');
+                writer.write_all(&out)?;
+            }
+            Ok(())
+        }
+
+        /// Process one request (guard first, then sign)
+        pub fn handle(&self, request: RemoteRequest) -> RemoteResponse {
+            match request {
+                RemoteRequest::PublicKey => RemoteResponse::PublicKey {
+                    scheme: self.signer.scheme(),
+                    public_key: self.signer.public_key(),
+                },
+                RemoteRequest::Sign(request) => {
+                    let mut guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    match guard.check_and_record(&request) {
+                        Err(ConsensusError::DoubleSignPrevented { height, round }) => RemoteResponse::DoubleSignRefused { height, round },
+                        Err(e) => RemoteResponse::Error(e.to_string()),
+                        Ok(()) => match self.signer.sign(&request) {
+                            Ok(signature) => RemoteResponse::Signature(signature),
+                            Err(e) => RemoteResponse::Error(e.to_string()),
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,563 @@
+//! OCOS-Chain: Deterministic Consensus Simulator
+//!
+//! Test harness running one `ConsensusEngine` per validator over an in-memory
+//! message bus. Time is virtual and every random choice (delays, drops) comes
+//! from a seeded generator, so a scenario replays identically for the same seed.
+//!
+//! Scenarios can script network partitions, message delays and drops, and
+//! Byzantine validators (equivocation, withholding). The report lists safety
+//! violations (two honest nodes finalizing different blocks at one height) and
+//! liveness stalls (an honest node not finalizing for longer than a threshold).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::core::consensus::{
+    block::{Block, BlockHeader, ExecutionSummary},
+    consensus_engine::{ConsensusEngine, ConsensusMode},
+    finality::{FinalityOutcome, RoundStep, TimeoutConfig, Vote},
+    signer::BlockSigner,
+    slashing::BPS_DENOMINATOR,
+    validator::Validator,
+    ConsensusError,
+};
+
+/// Simulation parameters
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub seed: u64,
+    pub mode: ConsensusMode,
+    pub timeouts: TimeoutConfig,
+    pub tick_ms: u64,             // Virtual clock resolution
+    pub min_delay_ms: u64,        // Message latency range (uniform)
+    pub max_delay_ms: u64,
+    pub drop_rate_bps: u64,       // Share of messages lost at random
+    pub stall_threshold_ms: u64,  // No finalization for longer than this is a stall
+    pub sync_retry_ms: u64,       // Minimum gap between catch-up requests of a node
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            seed: 0,
+            mode: ConsensusMode::ProofOfStake,
+            timeouts: TimeoutConfig::default(),
+            tick_ms: 10,
+            min_delay_ms: 5,
+            max_delay_ms: 50,
+            drop_rate_bps: 0,
+            stall_threshold_ms: 10_000,
+            sync_retry_ms: 500,
+        }
+    }
+}
+
+/// How a simulated validator behaves on the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
+    Honest,
+    /// Sends conflicting proposals and votes to different halves of its peers:
+    /// a twin block when it leads, and votes for the twin (or nil) against its own
+    Equivocate,
+    /// Runs consensus locally but never sends anything
+    Withhold,
+}
+
+/// Scripted partition: between `start_ms` and `end_ms`, messages between
+/// different groups are lost. Nodes not listed in any group are isolated.
+#[derive(Debug, Clone)]
+pub struct Partition {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub groups: Vec<Vec<String>>,
+}
+
+impl Partition {
+    /// True if the partition blocks `from → to` at `now_ms`
+    pub fn separates(&self, from: &str, to: &str, now_ms: u64) -> bool {
+        if now_ms < self.start_ms || now_ms >= self.end_ms {
+            return false;
+        }
+        let group_of = |addr: &str| self.groups.iter().position(|g| g.iter().any(|a| a == addr));
+        match (group_of(from), group_of(to)) {
+            (Some(a), Some(b)) => a != b,
+            _ => true,
+        }
+    }
+}
+
+/// Two honest nodes finalized different blocks at the same height
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyViolation {
+    pub height: u64,
+    pub first: (String, Vec<u8>),
+    pub conflicting: (String, Vec<u8>),
+}
+
+/// An honest node made no finality progress between `since_ms` and `until_ms`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessStall {
+    pub node: String,
+    pub height: u64, // Height the node was stuck on
+    pub since_ms: u64,
+    pub until_ms: u64,
+}
+
+/// Outcome of a simulation run
+#[derive(Debug, Clone, Default)]
+pub struct SimulationReport {
+    pub elapsed_ms: u64,
+    /// Node address → last finalized height
+    pub finalized_heights: BTreeMap<String, u64>,
+    pub safety_violations: Vec<SafetyViolation>,
+    pub liveness_stalls: Vec<LivenessStall>,
+    pub messages_sent: u64,
+    pub messages_delivered: u64,
+    pub messages_dropped: u64,
+    pub messages_rejected: u64,
+    /// Votes for a twin block sent by equivocating nodes
+    pub twin_votes_sent: u64,
+}
+
+impl SimulationReport {
+    pub fn is_safe(&self) -> bool {
+        self.safety_violations.is_empty()
+    }
+
+    /// Lowest finalized height among the given nodes (all nodes if empty)
+    pub fn min_height(&self, nodes: &[&str]) -> u64 {
+        self.finalized_heights
+            .iter()
+            .filter(|(addr, _)| nodes.is_empty() || nodes.contains(&addr.as_str()))
+            .map(|(_, h)| *h)
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Messages exchanged between simulated nodes
+#[derive(Debug, Clone)]
+enum SimMessage {
+    Proposal { block: Block, round: u32, pol: Vec<Vote> },
+    Vote(Vote),
+    SyncRequest { from_height: u64 },
+    SyncResponse(Vec<Block>),
+}
+
+#[derive(Debug, Clone)]
+struct Envelope {
+    from: usize,
+    to: usize,
+    msg: SimMessage,
+}
+
+struct SimNode {
+    address: String,
+    engine: ConsensusEngine,
+    behaviour: Behaviour,
+    finalized: Vec<Block>,
+    proposed: HashSet<(u64, u32)>,
+    last_progress_ms: u64,
+    last_sync_request_ms: Option<u64>,
+}
+
+/// SplitMix64: tiny, seedable and stable across platforms
+#[derive(Debug, Clone)]
+struct SimRng(u64);
+
+impl SimRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[low, high]`
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        if high <= low {
+            return low;
+        }
+        low + self.next_u64() % (high - low + 1)
+    }
+}
+
+/// N consensus engines on a virtual network
+pub struct Simulator {
+    pub config: SimConfig,
+    pub now_ms: u64,
+    genesis: Block,
+    nodes: Vec<SimNode>,
+    queue: BTreeMap<(u64, u64), Envelope>, // (deliver_at, sequence) → message
+    seq: u64,
+    rng: SimRng,
+    partitions: Vec<Partition>,
+    first_finalized: BTreeMap<u64, (String, Vec<u8>)>,
+    /// Block hash ↔ hash of the conflicting twin an equivocating leader sent alongside it
+    twins: HashMap<Vec<u8>, Vec<u8>>,
+    report: SimulationReport,
+}
+
+impl Simulator {
+    /// One honest node per genesis validator, all starting height 1 at time 0.
+    /// Each node only holds the signer of its own validator.
+    pub fn new(config: SimConfig, genesis: Vec<(Validator, Arc<dyn BlockSigner>)>) -> Self {
+        let genesis_validators: Vec<Validator> = genesis.iter().map(|(v, _)| v.clone()).collect();
+        let mut hosted: Vec<(String, Arc<dyn BlockSigner>)> = genesis
+            .into_iter()
+            .map(|(v, signer)| (v.address, signer))
+            .collect();
+        hosted.sort_by(|a, b| a.0.cmp(&b.0));
+        let nodes = hosted
+            .into_iter()
+            .map(|(address, signer)| {
+                let mut engine = ConsensusEngine::new(genesis_validators.clone(), config.mode);
+                engine.finality.timeouts = config.timeouts;
+                engine
+                    .add_signer(&address, signer)
+                    .expect("genesis signer must match its validator key");
+                engine.set_local_validator(&address);
+                engine.start_height(1, 0);
+                SimNode {
+                    address,
+                    engine,
+                    behaviour: Behaviour::Honest,
+                    finalized: vec![],
+                    proposed: HashSet::new(),
+                    last_progress_ms: 0,
+                    last_sync_request_ms: None,
+                }
+            })
+            .collect();
+
+        Simulator {
+            rng: SimRng(config.seed),
+            config,
+            now_ms: 0,
+            genesis: Block {
+                header: BlockHeader::default(),
+                signature: vec![],
+                proposer: String::new(),
+                transactions: vec![],
+                commit: None,
+                evidence: vec![],
+            },
+            nodes,
+            queue: BTreeMap::new(),
+            seq: 0,
+            partitions: vec![],
+            first_finalized: BTreeMap::new(),
+            twins: HashMap::new(),
+            report: SimulationReport::default(),
+        }
+    }
+
+    /// Change how a node behaves from now on
+    pub fn set_behaviour(&mut self, address: &str, behaviour: Behaviour) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.address == address) {
+            node.behaviour = behaviour;
+        }
+    }
+
+    /// Script a network partition
+    pub fn add_partition(&mut self, partition: Partition) {
+        self.partitions.push(partition);
+    }
+
+    /// Engine of a node (for scenario-specific assertions)
+    pub fn engine(&self, address: &str) -> Option<&ConsensusEngine> {
+        self.nodes.iter().find(|n| n.address == address).map(|n| &n.engine)
+    }
+
+    /// Mutable engine of a node, for scripting rounds that bypass the network
+    pub fn engine_mut(&mut self, address: &str) -> Option<&mut ConsensusEngine> {
+        self.nodes.iter_mut().find(|n| n.address == address).map(|n| &mut n.engine)
+    }
+
+    /// Blocks finalized by a node, in order
+    pub fn finalized_chain(&self, address: &str) -> &[Block] {
+        self.nodes
+            .iter()
+            .find(|n| n.address == address)
+            .map(|n| n.finalized.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Advance virtual time to `end_ms` and return the report so far
+    pub fn run_until(&mut self, end_ms: u64) -> SimulationReport {
+        let tick = self.config.tick_ms.max(1);
+        while self.now_ms < end_ms {
+            self.now_ms = (self.now_ms + tick).min(end_ms);
+            self.deliver_due();
+            for idx in 0..self.nodes.len() {
+                let now = self.now_ms;
+                let result = self.nodes[idx].engine.handle_timeout(now);
+                self.handle_result(idx, result);
+                self.maybe_propose(idx);
+            }
+        }
+        self.report()
+    }
+
+    /// Current report, including stalls still ongoing at `now_ms`
+    pub fn report(&self) -> SimulationReport {
+        let mut report = self.report.clone();
+        report.elapsed_ms = self.now_ms;
+        for node in &self.nodes {
+            report.finalized_heights.insert(node.address.clone(), node.finalized.len() as u64);
+            if node.behaviour == Behaviour::Honest
+                && self.now_ms - node.last_progress_ms > self.config.stall_threshold_ms
+            {
+                report.liveness_stalls.push(LivenessStall {
+                    node: node.address.clone(),
+                    height: node.engine.finality.height,
+                    since_ms: node.last_progress_ms,
+                    until_ms: self.now_ms,
+                });
+            }
+        }
+        report
+    }
+
+    /// Deliver every message due by `now_ms`, in (time, sequence) order
+    fn deliver_due(&mut self) {
+        while let Some(entry) = self.queue.first_entry() {
+            if entry.key().0 > self.now_ms {
+                break;
+            }
+            let envelope = entry.remove();
+            self.report.messages_delivered += 1;
+            self.receive(envelope);
+        }
+    }
+
+    fn receive(&mut self, envelope: Envelope) {
+        let Envelope { from, to, msg } = envelope;
+        let now = self.now_ms;
+        let height = self.nodes[to].engine.finality.height;
+        match msg {
+            SimMessage::Proposal { block, round, pol } => {
+                if block.header.height > height {
+                    self.request_sync(to, from);
+                } else if block.header.height == height {
+                    let sender = self.nodes[from].address.clone();
+                    let result = self.nodes[to].engine.handle_proposal(&sender, block, round, pol, now);
+                    self.handle_result(to, result);
+                }
+            }
+            SimMessage::Vote(vote) => {
+                if vote.height > height {
+                    self.request_sync(to, from);
+                } else if vote.height == height {
+                    let result = self.nodes[to].engine.handle_vote(vote, now);
+                    self.handle_result(to, result);
+                }
+            }
+            SimMessage::SyncRequest { from_height } => {
+                let blocks: Vec<Block> = self.nodes[to]
+                    .finalized
+                    .iter()
+                    .filter(|b| b.header.height >= from_height)
+                    .cloned()
+                    .collect();
+                if !blocks.is_empty() {
+                    self.send(to, from, SimMessage::SyncResponse(blocks));
+                }
+            }
+            SimMessage::SyncResponse(blocks) => {
+                for block in blocks {
+                    if block.header.height != self.nodes[to].engine.finality.height {
+                        continue;
+                    }
+                    match self.nodes[to].engine.handle_commit(block.clone(), now) {
+                        Ok(()) => self.record_finalized(to, block),
+                        Err(_) => {
+                            self.report.messages_rejected += 1;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Propose if this node leads the current round and has not proposed yet: its
+    /// valid block with the proof of lock if it has one, otherwise a new block
+    fn maybe_propose(&mut self, idx: usize) {
+        let node = &self.nodes[idx];
+        let (height, round) = (node.engine.finality.height, node.engine.finality.round);
+        if node.engine.finality.step != RoundStep::Propose || node.proposed.contains(&(height, round)) {
+            return;
+        }
+        match node.engine.select_leader(height, round) {
+            Some(leader) if leader.address == node.address => {}
+            _ => return,
+        }
+
+        let now = self.now_ms;
+        let parent = node.engine.last_finalized.clone().unwrap_or_else(|| self.genesis.clone());
+        let address = node.address.clone();
+        let txs = format!("{}/{}/{}", address, height, round);
+        let (block, pol) = match node.engine.finality.valid_proposal() {
+            Some(valid) => valid,
+            None => match node.engine.propose_block(&address, &parent, txs.as_bytes(), &ExecutionSummary::default(), now) {
+                Ok(block) => (block, vec![]),
+                Err(_) => return,
+            },
+        };
+        let twin = match node.behaviour {
+            Behaviour::Equivocate => node.engine
+                .propose_block(&address, &parent, format!("{}/equivocation", txs).as_bytes(), &ExecutionSummary::default(), now)
+                .ok(),
+            _ => None,
+        };
+        self.nodes[idx].proposed.insert((height, round));
+        if let Some(twin) = &twin {
+            self.twins.insert(block.hash(), twin.hash());
+            self.twins.insert(twin.hash(), block.hash());
+        }
+
+        let (half_a, half_b) = self.split_peers(idx);
+        for peer in half_a {
+            self.send(idx, peer, SimMessage::Proposal { block: block.clone(), round, pol: pol.clone() });
+        }
+        let (other, other_pol) = match twin {
+            Some(twin) => (twin, vec![]),
+            None => (block.clone(), pol.clone()),
+        };
+        for peer in half_b {
+            self.send(idx, peer, SimMessage::Proposal { block: other.clone(), round, pol: other_pol.clone() });
+        }
+        let result = self.nodes[idx].engine.handle_proposal(&address, block, round, pol, now);
+        self.handle_result(idx, result);
+    }
+
+    /// Broadcast the node's votes and record finalization
+    fn handle_result(&mut self, idx: usize, result: Result<FinalityOutcome, ConsensusError>) {
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                self.report.messages_rejected += 1;
+                return;
+            }
+        };
+        for vote in outcome.votes {
+            let (half_a, half_b) = self.split_peers(idx);
+            for peer in half_a {
+                self.send(idx, peer, SimMessage::Vote(vote.clone()));
+            }
+            let conflicting = match self.nodes[idx].behaviour {
+                Behaviour::Equivocate => self.conflicting_vote(idx, &vote),
+                _ => None,
+            };
+            let other = conflicting.unwrap_or_else(|| vote.clone());
+            for peer in half_b {
+                self.send(idx, peer, SimMessage::Vote(other.clone()));
+            }
+        }
+        if let Some(block) = outcome.finalized {
+            self.record_finalized(idx, block);
+        }
+    }
+
+    /// A second, differently valued vote for the same height/round/type: for the
+    /// twin of the voted block when a Byzantine leader made one, otherwise nil
+    /// against a block vote and a made-up block against a nil vote
+    fn conflicting_vote(&mut self, idx: usize, vote: &Vote) -> Option<Vote> {
+        let other = match &vote.block_hash {
+            Some(hash) => self.twins.get(hash).cloned(),
+            None => Some(vec![0xBA; 32]),
+        };
+        let node = &self.nodes[idx];
+        let conflicting = node.engine
+            .sign_vote(&node.address, vote.vote_type, vote.height, vote.round, other)
+            .ok()?;
+        if vote.block_hash.is_some() && conflicting.block_hash.is_some() {
+            self.report.twin_votes_sent += 1;
+        }
+        Some(conflicting)
+    }
+
+    fn record_finalized(&mut self, idx: usize, block: Block) {
+        let now = self.now_ms;
+        let node = &mut self.nodes[idx];
+        if now - node.last_progress_ms > self.config.stall_threshold_ms && node.behaviour == Behaviour::Honest {
+            self.report.liveness_stalls.push(LivenessStall {
+                node: node.address.clone(),
+                height: block.header.height,
+                since_ms: node.last_progress_ms,
+                until_ms: now,
+            });
+        }
+        node.last_progress_ms = now;
+        node.finalized.push(block.clone());
+        // No ledger here: executing a block leaves the state root unchanged, so this cannot fail
+        let execution = ExecutionSummary {
+            gas_used: block.header.gas_used,
+            state_root: node.engine.state_root.clone(),
+            logs_bloom: block.header.logs_bloom.clone(),
+        };
+        let _ = node.engine.on_executed(&block.hash(), &execution);
+
+        if node.behaviour != Behaviour::Honest {
+            return;
+        }
+        let entry = (node.address.clone(), block.hash());
+        match self.first_finalized.get(&block.header.height) {
+            None => {
+                self.first_finalized.insert(block.header.height, entry);
+            }
+            Some(first) if first.1 != entry.1 => {
+                self.report.safety_violations.push(SafetyViolation {
+                    height: block.header.height,
+                    first: first.clone(),
+                    conflicting: entry,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Ask a peer for the finalized blocks this node is missing (rate limited)
+    fn request_sync(&mut self, idx: usize, peer: usize) {
+        let now = self.now_ms;
+        let node = &mut self.nodes[idx];
+        if let Some(last) = node.last_sync_request_ms {
+            if now < last + self.config.sync_retry_ms {
+                return;
+            }
+        }
+        node.last_sync_request_ms = Some(now);
+        let from_height = node.engine.finality.height;
+        self.send(idx, peer, SimMessage::SyncRequest { from_height });
+    }
+
+    /// All other nodes split into two halves (by index) for equivocation
+    fn split_peers(&self, idx: usize) -> (Vec<usize>, Vec<usize>) {
+        let peers: Vec<usize> = (0..self.nodes.len()).filter(|&i| i != idx).collect();
+        let (a, b) = peers.split_at(peers.len() / 2);
+        (a.to_vec(), b.to_vec())
+    }
+
+    /// Queue a message subject to behaviour, partitions, drops and latency
+    fn send(&mut self, from: usize, to: usize, msg: SimMessage) {
+        if self.nodes[from].behaviour == Behaviour::Withhold {
+            return;
+        }
+        self.report.messages_sent += 1;
+
+        let (from_addr, to_addr) = (&self.nodes[from].address, &self.nodes[to].address);
+        let partitioned = self.partitions.iter().any(|p| p.separates(from_addr, to_addr, self.now_ms));
+        let dropped = self.rng.range(0, BPS_DENOMINATOR - 1) < self.config.drop_rate_bps;
+        if partitioned || dropped {
+            self.report.messages_dropped += 1;
+            return;
+        }
+
+        let delay = self.rng.range(self.config.min_delay_ms.max(1), self.config.max_delay_ms);
+        self.seq += 1;
+        self.queue.insert((self.now_ms + delay, self.seq), Envelope { from, to, msg });
+    }
+}
+
@@ -1,98 +1,410 @@
-//! OCOS-Chain: Block & Header Definitions
+//! OCOS-Chain: Slashing & Misbehaviour Evidence
 //!
-//! Defines the canonical block and header structures, supporting both
-//! quantum-resistant and classical cryptography. Includes hashing, signature
-//! validation and metadata for full auditability.
+//! Collects verifiable evidence of validator misbehaviour (double-signed block
+//! headers, conflicting votes), tracks missed blocks over a sliding window, and
+//! applies configurable slash fractions and jailing. Evidence is gossiped between
+//! nodes and included in blocks, so any node can report a faulty validator.
+//!
+//! Evidence is checked against the offender's own record, so a validator that
+//! has since rotated out or exited is still accountable: its delegations and
+//! unbonding stake are slashed. Evidence older than the unbonding period (or
+//! `max_evidence_age`, if shorter) is refused, since that stake may be gone.
+//!
+//! Jailed validators stay out for at least `min_jail_blocks` and then return with
+//! a signed unjail transaction. Lifetime participation counters back the uptime
+//! statistics delegators use to compare validators.
 
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::core::consensus::{
+    block::BlockHeader,
+    finality::Vote,
+    signer::SignKind,
+    validator::{Validator, ValidatorSet, ValidatorStatus},
+    ConsensusError,
+};
+use crate::core::network::message::{GossipPayload, MessageType, NetworkMessage};
+
+/// Gossip tag for evidence messages
+pub const EVIDENCE_GOSSIP_TAG: &str = "consensus/evidence";
+
+/// Basis-point denominator for slash fractions (10_000 = 100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
 
-/// Canonical block header structure
+/// Slashing parameters
 #[derive(Debug, Clone)]
-pub struct BlockHeader {
-    pub parent_hash: Vec<u8>,
-    pub proposer_public_key: Vec<u8>,
-    pub state_root: Vec<u8>,
-    pub tx_root: Vec<u8>,
-    pub height: u64,
-    pub timestamp: u64,
-    pub metadata: Option<Vec<u8>>, // extensible: e.g., for consensus params, governance info
+pub struct SlashingConfig {
+    pub double_sign_slash_bps: u64,    // Fraction of stake burned for equivocation
+    pub downtime_slash_bps: u64,       // Fraction of stake burned for downtime
+    pub signed_blocks_window: usize,   // Sliding window length (blocks)
+    pub min_signed_per_window_bps: u64, // Minimum share of blocks a validator must sign
+    pub max_evidence_age: u64,         // Evidence older than this (in blocks) is ignored
+    pub max_evidence_per_block: usize,
+    pub min_jail_blocks: u64,          // Blocks a jailed validator must wait before unjailing
 }
 
-impl BlockHeader {
-    /// Create a new block header
-    pub fn new(parent_hash: Vec<u8>, proposer_public_key: Vec<u8>, txs: &[u8]) -> Self {
-        let now = Self::current_unix_timestamp();
-        BlockHeader {
-            parent_hash,
-            proposer_public_key,
-            state_root: Self::calc_state_root(txs),
-            tx_root: Self::calc_tx_root(txs),
-            height: 0, // to be set by consensus engine
-            timestamp: now,
-            metadata: None,
+impl Default for SlashingConfig {
+    fn default() -> Self {
+        SlashingConfig {
+            double_sign_slash_bps: 500, // 5%
+            downtime_slash_bps: 10,     // 0.1%
+            signed_blocks_window: 100,
+            min_signed_per_window_bps: 5_000,
+            max_evidence_age: 100_000,
+            max_evidence_per_block: 16,
+            min_jail_blocks: 600,
         }
     }
+}
+
+/// Two different headers at the same height, both signed by one proposer key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoubleSignEvidence {
+    pub proposer: String,
+    pub header_a: BlockHeader,
+    pub signature_a: Vec<u8>,
+    pub header_b: BlockHeader,
+    pub signature_b: Vec<u8>,
+}
+
+/// Verifiable proof of validator misbehaviour
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Evidence {
+    DoubleSign(DoubleSignEvidence),
+    DuplicateVote { vote_a: Vote, vote_b: Vote },
+}
 
-    /// Calculate block header hash (chain ID, height, roots, etc.)
+impl Evidence {
+    /// Offending validator address
+    pub fn offender(&self) -> &str {
+        match self {
+            Evidence::DoubleSign(ev) => &ev.proposer,
+            Evidence::DuplicateVote { vote_a, .. } => &vote_a.validator,
+        }
+    }
+
+    /// Height at which the misbehaviour happened
+    pub fn height(&self) -> u64 {
+        match self {
+            Evidence::DoubleSign(ev) => ev.header_a.height,
+            Evidence::DuplicateVote { vote_a, .. } => vote_a.height,
+        }
+    }
+
+    /// Unique identifier (order-independent for the two conflicting items)
     pub fn hash(&self) -> Vec<u8> {
-        let mut hasher = Sha3_256::new();
-        hasher.update(&self.parent_hash);
-        hasher.update(&self.proposer_public_key);
-        hasher.update(&self.state_root);
-        hasher.update(&self.tx_root);
-        hasher.update(&self.height.to_be_bytes());
-        hasher.update(&self.timestamp.to_be_bytes());
-        if let Some(meta) = &self.metadata {
-            hasher.update(meta);
+        let (mut a, mut b) = match self {
+            Evidence::DoubleSign(ev) => (ev.header_a.hash(), ev.header_b.hash()),
+            Evidence::DuplicateVote { vote_a, vote_b } => (vote_a.sign_bytes(), vote_b.sign_bytes()),
+        };
+        if a > b {
+            std::mem::swap(&mut a, &mut b);
         }
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.offender().as_bytes());
+        hasher.update(&a);
+        hasher.update(&b);
         hasher.finalize().to_vec()
     }
 
-    /// Simulated state root (placeholder)
-    fn calc_state_root(_txs: &[u8]) -> Vec<u8> {
-        // Real implementation: Merkle/Patricia trie root of state after txs applied
-        vec![0u8; 32]
+    /// Check that the evidence proves misbehaviour by `validator` (active or not)
+    pub fn validate(&self, validator: &Validator) -> Result<(), ConsensusError> {
+        if validator.address != self.offender() {
+            return Err(ConsensusError::InvalidEvidence);
+        }
+        let pk = &validator.public_key;
+
+        match self {
+            Evidence::DoubleSign(ev) => {
+                let (a, b) = (&ev.header_a, &ev.header_b);
+                if a.height != b.height
+                    || a.hash() == b.hash()
+                    || &a.proposer_public_key != pk
+                    || &b.proposer_public_key != pk
+                {
+                    return Err(ConsensusError::InvalidEvidence);
+                }
+                let signed = |header: &BlockHeader, signature: &[u8]| {
+                    validator.verify_signed(SignKind::Proposal, header.height, 0, &header.hash(), signature)
+                };
+                if !signed(a, &ev.signature_a) || !signed(b, &ev.signature_b) {
+                    return Err(ConsensusError::InvalidSignature);
+                }
+            }
+            Evidence::DuplicateVote { vote_a, vote_b } => {
+                if vote_a.validator != vote_b.validator
+                    || vote_a.vote_type != vote_b.vote_type
+                    || vote_a.height != vote_b.height
+                    || vote_a.round != vote_b.round
+                    || vote_a.block_hash == vote_b.block_hash
+                {
+                    return Err(ConsensusError::InvalidEvidence);
+                }
+                if !vote_a.is_signed_by(validator) || !vote_b.is_signed_by(validator) {
+                    return Err(ConsensusError::InvalidSignature);
+                }
+            }
+        }
+        Ok(())
     }
 
-    /// Simulated tx root (placeholder)
-    fn calc_tx_root(txs: &[u8]) -> Vec<u8> {
-        // Real implementation: Merkle root of all transactions
+    /// Merkle-style commitment over a block's evidence list (goes into the header)
+    pub fn root(evidence: &[Evidence]) -> Vec<u8> {
         let mut hasher = Sha3_256::new();
-        hasher.update(txs);
+        for ev in evidence {
+            hasher.update(ev.hash());
+        }
         hasher.finalize().to_vec()
     }
 
-    /// Helper: current UNIX timestamp (seconds)
-    fn current_unix_timestamp() -> u64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    /// Wrap as a gossip message for peer propagation
+    pub fn to_gossip(&self) -> Result<NetworkMessage, ConsensusError> {
+        let data = bincode::serialize(self).map_err(|_| ConsensusError::InvalidEvidence)?;
+        Ok(NetworkMessage::new(
+            MessageType::Gossip,
+            GossipPayload::Custom { tag: EVIDENCE_GOSSIP_TAG.to_string(), data },
+        ))
+    }
+
+    /// Extract evidence from a gossip payload (None for other payloads)
+    pub fn from_gossip(payload: &GossipPayload) -> Option<Evidence> {
+        match payload {
+            GossipPayload::Custom { tag, data } if tag == EVIDENCE_GOSSIP_TAG => bincode::deserialize(data).ok(),
+            _ => None,
+        }
     }
 }
 
-/// Canonical OCOS-Chain block structure
-#[derive(Debug, Clone)]
-pub struct Block {
-    pub header: BlockHeader,
-    pub signature: Vec<u8>,        // Block signature (quantum/classical)
-    pub proposer: String,          // Validator address
-    pub transactions: Vec<u8>,     // Transactions (batch, for simplicity)
+/// Outcome of a slash, mirrored into the staking ledger (delegations, unbonding)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlashRecord {
+    pub validator: String,
+    pub amount: u64,
+    pub fraction_bps: u64,
+    pub infraction_height: u64,
 }
 
-impl Block {
-    /// Block hash = header hash (chain standard)
-    pub fn hash(&self) -> Vec<u8> {
-        self.header.hash()
-    }
-
-    /// Validate block header signature (via quantum/classical handler)
-    pub fn validate_signature<F>(&self, verify_fn: F) -> bool
-    where
-        F: Fn(&[u8], &[u8], &[u8]) -> bool,
-    {
-        verify_fn(
-            &self.header.proposer_public_key,
-            &self.header.hash(),
-            &self.signature,
-        )
+/// Lifetime signing record of a validator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Participation {
+    pub signed: u64,
+    pub missed: u64,
+    pub times_jailed: u64,
+}
+
+/// Uptime summary of a validator, for delegators choosing by reliability
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UptimeStats {
+    pub validator: String,
+    pub window_signed: usize,
+    pub window_missed: usize,
+    pub uptime_bps: u64, // Signed share of all tracked blocks (10_000 before any block)
+    pub lifetime: Participation,
+    pub jailed_since: Option<u64>,
+    pub jailed_until: Option<u64>, // First height an unjail transaction is accepted
+}
+
+/// Slashing state: evidence pool, processed evidence and downtime windows
+#[derive(Debug, Clone, Default)]
+pub struct SlashingManager {
+    pub config: SlashingConfig,
+    /// Verified evidence waiting for inclusion in a block
+    pub pending: Vec<Evidence>,
+    /// Hashes of evidence already applied on-chain
+    pub committed: HashSet<Vec<u8>>,
+    /// Validator → signed (true) / missed (false) bitmap over the sliding window
+    pub signing_window: HashMap<String, VecDeque<bool>>,
+    /// Validator → lifetime participation counters
+    pub participation: HashMap<String, Participation>,
+}
+
+impl SlashingManager {
+    /// Create a slashing manager with custom parameters
+    pub fn new(config: SlashingConfig) -> Self {
+        SlashingManager {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Oldest evidence (in blocks) still accepted: `max_evidence_age`, capped at
+    /// the unbonding period after which the offender's stake may be released
+    pub fn max_evidence_age(&self, unbonding_period: u64) -> u64 {
+        self.config.max_evidence_age.min(unbonding_period)
+    }
+
+    /// Check evidence against the offender's record (active set or staking
+    /// candidacy) and the age limit at `current_height`
+    pub fn check_evidence(&self, evidence: &Evidence, offender: Option<&Validator>, current_height: u64, unbonding_period: u64) -> Result<(), ConsensusError> {
+        if current_height.saturating_sub(evidence.height()) > self.max_evidence_age(unbonding_period) {
+            return Err(ConsensusError::InvalidEvidence);
+        }
+        evidence.validate(offender.ok_or(ConsensusError::InvalidEvidence)?)
+    }
+
+    /// Verify and pool evidence from any node; returns false if already known
+    pub fn submit_evidence(&mut self, evidence: Evidence, offender: Option<&Validator>, current_height: u64, unbonding_period: u64) -> Result<bool, ConsensusError> {
+        let hash = evidence.hash();
+        if self.committed.contains(&hash) || self.pending.iter().any(|e| e.hash() == hash) {
+            return Ok(false);
+        }
+        self.check_evidence(&evidence, offender, current_height, unbonding_period)?;
+        self.pending.push(evidence);
+        Ok(true)
+    }
+
+    /// Evidence to include in the next proposed block
+    pub fn pending_evidence(&self) -> Vec<Evidence> {
+        self.pending.iter().take(self.config.max_evidence_per_block).cloned().collect()
+    }
+
+    /// Apply evidence included in a finalized block. An offender outside the
+    /// active set is not slashed here (`amount` is 0), but the record still
+    /// carries the fraction for its delegations and unbonding stake.
+    pub fn apply_evidence(&mut self, evidence: &Evidence, validators: &mut ValidatorSet, height: u64) -> Option<SlashRecord> {
+        let hash = evidence.hash();
+        if !self.committed.insert(hash.clone()) {
+            return None;
+        }
+        self.pending.retain(|e| e.hash() != hash);
+
+        let offender = evidence.offender().to_owned();
+        let fraction_bps = self.config.double_sign_slash_bps;
+        let amount = match validators.get_by_address_mut(&offender) {
+            Some(validator) => {
+                let amount = Self::fraction_of(validator.stake, fraction_bps);
+                self.slash(validator, amount, height);
+                validator.jail(height);
+                self.participation.entry(offender.clone()).or_default().times_jailed += 1;
+                amount
+            }
+            None => 0,
+        };
+        Some(SlashRecord {
+            validator: offender,
+            amount,
+            fraction_bps,
+            infraction_height: evidence.height(),
+        })
+    }
+
+    /// Record whether each active validator signed the block at `height`;
+    /// returns validators slashed and jailed for downtime
+    pub fn record_participation(&mut self, signers: &HashSet<String>, validators: &mut ValidatorSet, height: u64) -> Vec<SlashRecord> {
+        let window = self.config.signed_blocks_window;
+        let min_signed = (window as u64 * self.config.min_signed_per_window_bps / BPS_DENOMINATOR) as usize;
+        let mut punished = vec![];
+
+        let active: Vec<String> = validators.sorted_active().iter().map(|v| v.address.clone()).collect();
+        for address in active {
+            let signed_block = signers.contains(&address);
+            let counters = self.participation.entry(address.clone()).or_default();
+            if signed_block {
+                counters.signed += 1;
+            } else {
+                counters.missed += 1;
+            }
+            let bitmap = self.signing_window.entry(address.clone()).or_default();
+            bitmap.push_back(signed_block);
+            while bitmap.len() > window {
+                bitmap.pop_front();
+            }
+            let signed = bitmap.iter().filter(|&&s| s).count();
+            if bitmap.len() < window || signed >= min_signed {
+                continue;
+            }
+
+            bitmap.clear();
+            if let Some(validator) = validators.get_by_address_mut(&address) {
+                let fraction_bps = self.config.downtime_slash_bps;
+                let amount = Self::fraction_of(validator.stake, fraction_bps);
+                self.slash(validator, amount, height);
+                validator.jail(height);
+                self.participation.entry(address.clone()).or_default().times_jailed += 1;
+                punished.push(SlashRecord {
+                    validator: address,
+                    amount,
+                    fraction_bps,
+                    infraction_height: height,
+                });
+            }
+        }
+        punished
+    }
+
+    /// Change the signing window; bitmaps longer than the new window keep only
+    /// their most recent blocks
+    pub fn set_signed_blocks_window(&mut self, window: usize) {
+        self.config.signed_blocks_window = window;
+        for bitmap in self.signing_window.values_mut() {
+            let excess = bitmap.len().saturating_sub(window);
+            bitmap.drain(..excess);
+        }
+    }
+
+    /// Number of blocks missed by a validator in the current window
+    pub fn missed_blocks(&self, address: &str) -> usize {
+        self.signing_window
+            .get(address)
+            .map(|w| w.iter().filter(|&&s| !s).count())
+            .unwrap_or(0)
+    }
+
+    /// First height at which a jailed validator may unjail
+    pub fn jailed_until(&self, validator: &Validator) -> Option<u64> {
+        match validator.status {
+            ValidatorStatus::Jailed => validator.jailed_since.map(|since| since + self.config.min_jail_blocks),
+            _ => None,
+        }
+    }
+
+    /// Check that a validator is jailed and has served the minimum jail duration at `height`
+    pub fn check_unjail(&self, validator: &Validator, height: u64) -> Result<(), ConsensusError> {
+        let until = self.jailed_until(validator).ok_or(ConsensusError::ValidatorNotJailed)?;
+        if height < until {
+            return Err(ConsensusError::JailPeriodNotElapsed { until });
+        }
+        Ok(())
+    }
+
+    /// Message a jailed validator signs to unjail (bound to its jailing, so it cannot be replayed)
+    pub fn unjail_sign_bytes(address: &str, jailed_since: u64) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-UNJAIL");
+        hasher.update(address.as_bytes());
+        hasher.update(jailed_since.to_be_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    /// Lifetime uptime, the current window and jail status
+    pub fn uptime(&self, validator: &Validator) -> UptimeStats {
+        let window = self.signing_window.get(&validator.address);
+        let window_signed = window.map(|w| w.iter().filter(|&&s| s).count()).unwrap_or(0);
+        let lifetime = self.participation.get(&validator.address).copied().unwrap_or_default();
+        let uptime_bps = match lifetime.signed + lifetime.missed {
+            0 => BPS_DENOMINATOR,
+            total => lifetime.signed * BPS_DENOMINATOR / total,
+        };
+        UptimeStats {
+            validator: validator.address.clone(),
+            window_signed,
+            window_missed: self.missed_blocks(&validator.address),
+            uptime_bps,
+            lifetime,
+            jailed_since: validator.jailed_since,
+            jailed_until: self.jailed_until(validator),
+        }
+    }
+
+    /// Slash a validator's stake by an absolute amount
+    pub fn slash(&mut self, validator: &mut Validator, amount: u64, height: u64) {
+        validator.slash(amount, height);
+    }
+
+    /// `bps` basis points of `stake`
+    pub fn fraction_of(stake: u64, bps: u64) -> u64 {
+        (stake as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
     }
 }
@@ -0,0 +1,323 @@
+//! OCOS-Chain: Staking, Delegation & Epoch Rotation
+//!
+//! Source of truth for bonded stake. Candidates bond stake (self-delegation),
+//! token holders delegate to candidates, and at every epoch boundary the active
+//! validator set is recomputed as the top-N candidates by bonded stake. Joins and
+//! exits queue until the boundary; unbonding stake stays slashable until its
+//! unbonding period has elapsed.
+//!
+//! Bonds are escrowed: `request_join` and `delegate` debit the owner's ledger
+//! account, and unbonding released at an epoch boundary is credited back by
+//! `ConsensusEngine::take_matured_unbonding`, minus whatever was slashed.
+
+use std::collections::BTreeMap;
+
+use crate::core::ledger::{executor::Executor, state::State};
+use crate::core::consensus::{
+    slashing::{SlashRecord, SlashingManager, BPS_DENOMINATOR},
+    validator::{Validator, ValidatorSet, ValidatorStatus},
+    ConsensusError,
+};
+
+/// Staking and epoch parameters
+#[derive(Debug, Clone)]
+pub struct StakingConfig {
+    pub epoch_length: u64,     // Blocks per epoch
+    pub max_validators: usize, // Size of the active set (top-N by stake)
+    pub unbonding_period: u64, // Blocks before unbonded stake is released
+    pub min_self_bond: u64,    // Minimum self-delegation to stay a candidate
+}
+
+impl Default for StakingConfig {
+    fn default() -> Self {
+        StakingConfig {
+            epoch_length: 1_000,
+            max_validators: 100,
+            unbonding_period: 100_800,
+            min_self_bond: 1,
+        }
+    }
+}
+
+/// Commission charged by validators that never set one (10%)
+pub const DEFAULT_COMMISSION_BPS: u64 = 1_000;
+
+/// Stake on its way out: still slashable until `completion_height`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnbondingEntry {
+    pub delegator: String,
+    pub validator: String,
+    pub amount: u64,
+    pub creation_height: u64,
+    pub completion_height: u64,
+}
+
+/// Validator set change queued for the next epoch boundary
+#[derive(Debug, Clone)]
+pub enum PendingChange {
+    Join { validator: Validator, self_bond: u64 },
+    Exit { address: String },
+}
+
+/// Result of an epoch boundary
+#[derive(Debug, Clone)]
+pub struct EpochTransition {
+    pub epoch: u64,
+    pub active: Vec<Validator>,
+    /// Unbonding entries that matured and can be credited back to their owners
+    pub released: Vec<UnbondingEntry>,
+}
+
+/// Bonded stake, delegations, unbonding queue and commission rates
+#[derive(Debug, Clone, Default)]
+pub struct StakingLedger {
+    pub config: StakingConfig,
+    pub epoch: u64,
+    /// Height of the last epoch boundary (0 before the first)
+    pub epoch_start: u64,
+    /// Epoch length set by governance, applied at the next boundary
+    pub next_epoch_length: Option<u64>,
+    /// All bonded candidates (active or waiting), by address
+    pub candidates: BTreeMap<String, Validator>,
+    /// (validator, delegator) → bonded amount; self-bond uses the validator's own address
+    pub delegations: BTreeMap<(String, String), u64>,
+    pub unbonding: Vec<UnbondingEntry>,
+    pub pending: Vec<PendingChange>,
+    /// Validator → commission taken from its rewards before delegators are paid (bps)
+    pub commissions: BTreeMap<String, u64>,
+}
+
+impl StakingLedger {
+    /// Create a ledger seeded with genesis validators (their stake becomes self-bond)
+    pub fn new(config: StakingConfig, genesis: Vec<Validator>) -> Self {
+        let mut ledger = StakingLedger { config, ..Default::default() };
+        for validator in genesis {
+            let key = (validator.address.clone(), validator.address.clone());
+            ledger.delegations.insert(key, validator.stake);
+            ledger.candidates.insert(validator.address.clone(), validator);
+        }
+        ledger
+    }
+
+    /// True if `height` closes the current epoch (a multiple of `epoch_length`
+    /// blocks after the last boundary)
+    pub fn is_epoch_boundary(&self, height: u64) -> bool {
+        let length = self.config.epoch_length;
+        length > 0 && height >= self.epoch_start && (height - self.epoch_start) % length == 0
+    }
+
+    /// Change the epoch length from the next boundary on, so the running epoch
+    /// still ends where it was scheduled to (immediately if epochs are disabled)
+    pub fn set_epoch_length(&mut self, epoch_length: u64) {
+        if self.config.epoch_length == 0 {
+            self.config.epoch_length = epoch_length;
+            self.next_epoch_length = None;
+        } else {
+            self.next_epoch_length = (epoch_length != self.config.epoch_length).then_some(epoch_length);
+        }
+    }
+
+    /// Queue a new candidate, escrowing its self-bond; it becomes eligible at the next epoch boundary
+    pub fn request_join(&mut self, state: &mut State, validator: Validator, self_bond: u64) -> Result<(), ConsensusError> {
+        if self_bond < self.config.min_self_bond
+            || self.candidates.contains_key(&validator.address)
+            || self.is_joining(&validator.address)
+        {
+            return Err(ConsensusError::Unauthorized);
+        }
+        Self::escrow(state, &validator.address, self_bond)?;
+        self.pending.push(PendingChange::Join { validator, self_bond });
+        Ok(())
+    }
+
+    /// Queue a candidate exit; all of its stake starts unbonding at the next epoch boundary
+    pub fn request_exit(&mut self, address: &str) -> Result<(), ConsensusError> {
+        if !self.candidates.contains_key(address) {
+            return Err(ConsensusError::Unauthorized);
+        }
+        self.pending.push(PendingChange::Exit { address: address.to_owned() });
+        Ok(())
+    }
+
+    /// True if a join of `address` is queued for the next epoch boundary
+    pub fn is_joining(&self, address: &str) -> bool {
+        self.pending.iter().any(|change| matches!(change, PendingChange::Join { validator, .. } if validator.address == address))
+    }
+
+    /// Delegate stake escrowed from the delegator's ledger account to a candidate
+    /// (counts towards voting power from the next epoch)
+    pub fn delegate(&mut self, state: &mut State, delegator: &str, validator: &str, amount: u64) -> Result<(), ConsensusError> {
+        match self.candidates.get(validator) {
+            Some(v) if v.status != ValidatorStatus::Retired && amount > 0 => {}
+            _ => return Err(ConsensusError::Unauthorized),
+        }
+        Self::escrow(state, delegator, amount)?;
+        *self.delegations
+            .entry((validator.to_owned(), delegator.to_owned()))
+            .or_insert(0) += amount;
+        Ok(())
+    }
+
+    fn escrow(state: &mut State, owner: &str, amount: u64) -> Result<(), ConsensusError> {
+        Executor::escrow_bond(state, owner, amount).map_err(|_| ConsensusError::InsufficientBond {
+            account: owner.to_owned(),
+            amount,
+        })
+    }
+
+    /// Start unbonding part of a delegation
+    pub fn undelegate(&mut self, delegator: &str, validator: &str, amount: u64, height: u64) -> Result<UnbondingEntry, ConsensusError> {
+        let key = (validator.to_owned(), delegator.to_owned());
+        let bonded = self.delegations.get_mut(&key).ok_or(ConsensusError::Unauthorized)?;
+        if amount == 0 || amount > *bonded {
+            return Err(ConsensusError::Unauthorized);
+        }
+        *bonded -= amount;
+        if *bonded == 0 {
+            self.delegations.remove(&key);
+        }
+        let entry = UnbondingEntry {
+            delegator: delegator.to_owned(),
+            validator: validator.to_owned(),
+            amount,
+            creation_height: height,
+            completion_height: height + self.config.unbonding_period,
+        };
+        self.unbonding.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// Bonded stake of a candidate (sum of its delegations)
+    pub fn bonded_stake(&self, validator: &str) -> u64 {
+        self.delegations_of(validator).iter().map(|(_, amount)| amount).sum()
+    }
+
+    /// Delegations to a candidate as (delegator, amount), sorted by delegator
+    pub fn delegations_of(&self, validator: &str) -> Vec<(String, u64)> {
+        self.delegations
+            .iter()
+            .filter(|((v, _), _)| v == validator)
+            .map(|((_, d), amount)| (d.clone(), *amount))
+            .collect()
+    }
+
+    /// Set a candidate's commission rate (basis points of its rewards)
+    pub fn set_commission(&mut self, validator: &str, commission_bps: u64) -> Result<(), ConsensusError> {
+        if !self.candidates.contains_key(validator) || commission_bps > BPS_DENOMINATOR {
+            return Err(ConsensusError::Unauthorized);
+        }
+        self.commissions.insert(validator.to_owned(), commission_bps);
+        Ok(())
+    }
+
+    /// Commission rate of a validator
+    pub fn commission_of(&self, validator: &str) -> u64 {
+        self.commissions.get(validator).copied().unwrap_or(DEFAULT_COMMISSION_BPS)
+    }
+
+    /// Jail a candidate so the next epoch does not elect it (no effect once retired)
+    pub fn jail_candidate(&mut self, address: &str, height: u64) {
+        if let Some(candidate) = self.candidates.get_mut(address) {
+            candidate.jail(height);
+        }
+    }
+
+    /// Mirror a slash into delegations and into unbonding entries that were
+    /// created at or after the infraction and have not matured yet
+    pub fn apply_slash(&mut self, record: &SlashRecord, current_height: u64) -> u64 {
+        let mut burned = 0u64;
+        for ((validator, _), amount) in self.delegations.iter_mut() {
+            if validator == &record.validator {
+                let cut = SlashingManager::fraction_of(*amount, record.fraction_bps);
+                *amount -= cut;
+                burned += cut;
+            }
+        }
+        for entry in self.unbonding.iter_mut() {
+            if entry.validator == record.validator
+                && entry.creation_height >= record.infraction_height
+                && entry.completion_height > current_height
+            {
+                let cut = SlashingManager::fraction_of(entry.amount, record.fraction_bps);
+                entry.amount -= cut;
+                burned += cut;
+            }
+        }
+        burned
+    }
+
+    /// Close the epoch at `height`: carry over jail status from the live set, apply
+    /// queued joins/exits, release matured unbonding and pick the new top-N set
+    pub fn end_epoch(&mut self, height: u64, current: &ValidatorSet) -> EpochTransition {
+        for (address, live) in &current.validators {
+            if let Some(candidate) = self.candidates.get_mut(address) {
+                candidate.status = live.status.clone();
+                candidate.jailed_since = live.jailed_since;
+            }
+        }
+
+        for change in std::mem::take(&mut self.pending) {
+            match change {
+                PendingChange::Join { validator, self_bond } => {
+                    let address = validator.address.clone();
+                    self.delegations.insert((address.clone(), address.clone()), self_bond);
+                    self.candidates.insert(address, validator);
+                }
+                PendingChange::Exit { address } => {
+                    for (delegator, amount) in self.delegations_of(&address) {
+                        // Cannot fail: the delegation was just read from the ledger
+                        let _ = self.undelegate(&delegator, &address, amount, height);
+                    }
+                    if let Some(candidate) = self.candidates.get_mut(&address) {
+                        candidate.retire();
+                    }
+                }
+            }
+        }
+
+        let (released, still_unbonding): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unbonding)
+            .into_iter()
+            .partition(|e| e.completion_height <= height);
+        self.unbonding = still_unbonding;
+
+        let stakes: Vec<(String, u64)> = self.candidates
+            .keys()
+            .map(|address| (address.clone(), self.bonded_stake(address)))
+            .collect();
+        for (address, stake) in stakes {
+            if let Some(candidate) = self.candidates.get_mut(&address) {
+                candidate.stake = stake;
+            }
+        }
+
+        self.epoch += 1;
+        self.epoch_start = height;
+        if let Some(length) = self.next_epoch_length.take() {
+            self.config.epoch_length = length;
+        }
+        EpochTransition {
+            epoch: self.epoch,
+            active: self.active_set(),
+            released,
+        }
+    }
+
+    /// Top-N active candidates by bonded stake (ties broken by address)
+    pub fn active_set(&self) -> Vec<Validator> {
+        let min_self_bond = self.config.min_self_bond;
+        let mut eligible: Vec<&Validator> = self.candidates
+            .values()
+            .filter(|v| v.status == ValidatorStatus::Active)
+            .filter(|v| {
+                let key = (v.address.clone(), v.address.clone());
+                self.delegations.get(&key).copied().unwrap_or(0) >= min_self_bond
+            })
+            .collect();
+        eligible.sort_by(|a, b| b.stake.cmp(&a.stake).then_with(|| a.address.cmp(&b.address)));
+        eligible
+            .into_iter()
+            .take(self.config.max_validators)
+            .cloned()
+            .collect()
+    }
+}
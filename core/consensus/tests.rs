@@ -7,24 +7,44 @@ use super::*;
 use crate::core::consensus::{
     validator::{Validator, ValidatorSet},
     consensus_engine::{ConsensusEngine, ConsensusMode},
-    block::{Block, BlockHeader},
-    quantum_sig::QuantumSignature,
-    governance::GovernanceHook,
+    block::{Block, BlockHeader, BlockLimits, ExecutionSummary},
+    quantum_sig::{QuantumSignature, SignatureScheme},
+    governance::{GovernanceHook, ProposalStatus},
+    finality::{CommitCertificate, FinalityGadget, RoundStep, TimeoutConfig, Vote, VoteType},
+    vrf::VrfProof,
+    slashing::{DoubleSignEvidence, Evidence, Participation, SlashRecord, SlashingConfig, SlashingManager},
+    staking::{StakingConfig, StakingLedger},
+    events::{audit_codes, AuditLogSink, BroadcastSink, CompositeSink, JsonLinesSink, RingBufferSink},
+    simulator::{Behaviour, Partition, SimConfig, Simulator},
+    signer::{sign_bytes, BlockSigner, DoubleSignGuard, KeystoreSigner, LocalSigner, SignKind, SignRequest},
+    rewards::{ledger_account, IssuanceSchedule, RewardConfig},
+    light_client::{LightBlock, LightClient, LightClientConfig, LightClientError},
 };
+use crate::core::crypto::{sha3_256, DilithiumKeypair, Ed25519Keypair, XmssKeypair};
+use sha2::{Digest, Sha256};
+use crate::core::ledger::state::{AccountState, State};
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use crate::contracts::audit::{ActionType, AuditLog, LogLevel};
 
-    fn demo_keys() -> (Vec<u8>, Vec<u8>) {
-        // For demonstration; in real systems use cryptographic key generation
-        (vec![1; 32], vec![2; 32])
+    /// Deterministic Ed25519 key per address, so validators and signers can be built independently
+    fn demo_keys(addr: &str) -> (Vec<u8>, Vec<u8>) {
+        let keypair = Ed25519Keypair::from_private_key(&sha3_256(addr.as_bytes())).unwrap();
+        (keypair.public, keypair.private)
+    }
+
+    fn demo_signer(addr: &str) -> Arc<LocalSigner> {
+        let (pub_key, priv_key) = demo_keys(addr);
+        Arc::new(LocalSigner::new(SignatureScheme::Ed25519, pub_key, priv_key))
     }
 
     fn demo_validator(addr: &str, stake: u64) -> Validator {
-        let (pub_key, priv_key) = demo_keys();
-        Validator::new(addr.to_owned(), stake, pub_key, priv_key)
+        let (pub_key, _) = demo_keys(addr);
+        Validator::new(addr.to_owned(), stake, pub_key)
     }
 
     #[test]
@@ -51,67 +71,1158 @@ mod tests {
             demo_validator("val2", 2000),
         ];
         let vs = ValidatorSet::new(vals.clone());
-        let selected = vs.select_by_stake(1).unwrap();
+        let selected = vs.select_by_stake(&[7u8; 32], 1, 0).unwrap();
         assert!(selected.address == "val2" || selected.address == "val1");
+
+        // Same seed, same leader: independent of HashMap insertion order
+        let reversed = ValidatorSet::new(vals.into_iter().rev().collect());
+        assert_eq!(reversed.select_by_stake(&[7u8; 32], 1, 0).unwrap().address, selected.address);
+
+        // PoA round robin walks the address-sorted set
+        assert_eq!(vs.select_by_round_robin(0, 0).unwrap().address, "val1");
+        assert_eq!(vs.select_by_round_robin(0, 1).unwrap().address, "val2");
+    }
+
+    #[test]
+    fn test_stake_weighted_selection_follows_stake() {
+        let vs = ValidatorSet::new(vec![
+            demo_validator("whale", 9_000),
+            demo_validator("minnow", 1_000),
+        ]);
+        let whale_wins = (0..1_000u64)
+            .filter(|h| vs.select_by_stake(&[1u8; 32], *h, 0).unwrap().address == "whale")
+            .count();
+        assert!(whale_wins > 800 && whale_wins < 980);
+    }
+
+    #[test]
+    fn test_vrf_beacon_roundtrip() {
+        let (v, signer) = (demo_validator("val5", 100), demo_signer("val5"));
+        let input = VrfProof::input(&[0u8; 32], 5);
+        let vrf = VrfProof::prove(|msg| signer.sign(&SignRequest::new(SignKind::Beacon, 5, 0, msg.to_vec())), &input).unwrap();
+        let decoded = VrfProof::from_metadata(&vrf.to_metadata()).unwrap();
+        assert_eq!(decoded, vrf);
+        let verify = |pk: &[u8], msg: &[u8], sig: &[u8]| QuantumSignature::verify_ed25519(pk, &sign_bytes(SignKind::Beacon, 0, 0, msg), sig);
+        assert!(decoded.verify(verify, &v.public_key, &input));
+        assert!(!decoded.verify(verify, &v.public_key, &VrfProof::input(&[0u8; 32], 6)));
+        // The raw input was never signed: only its beacon-domain bytes were
+        assert!(!decoded.verify(QuantumSignature::verify_ed25519, &v.public_key, &input));
     }
 
     #[test]
     fn test_block_signature_classic_and_quantum() {
-        let v = demo_validator("val3", 1500);
+        let (v, (_, priv_key)) = (demo_validator("val3", 1500), demo_keys("val3"));
         let header = BlockHeader::new(vec![0u8;32], v.public_key.clone(), b"txs");
         let hash = header.hash();
 
-        // Ed25519 (classic, demo)
-        let sig = QuantumSignature::sign_ed25519(&v.private_key, &hash);
+        // Ed25519 (classic)
+        let sig = QuantumSignature::sign_ed25519(&priv_key, &hash).unwrap();
         assert!(QuantumSignature::verify_ed25519(&v.public_key, &hash, &sig));
+        assert!(!QuantumSignature::verify_ed25519(&v.public_key, b"other", &sig));
+
+        // Dilithium (quantum)
+        let pq = DilithiumKeypair::generate().unwrap();
+        let sigq = QuantumSignature::sign_dilithium(&pq.private, &hash).unwrap();
+        assert!(QuantumSignature::verify_dilithium(&pq.public, &hash, &sigq));
+        assert!(!QuantumSignature::verify_dilithium(&v.public_key, &hash, &sigq));
 
-        // Dilithium (quantum, demo)
-        let sigq = QuantumSignature::sign_dilithium(&v.private_key, &hash);
-        assert!(QuantumSignature::verify_dilithium(&v.public_key, &hash, &sigq));
+        // A key of the wrong scheme cannot sign
+        assert!(QuantumSignature::sign(SignatureScheme::Dilithium, &priv_key, &hash).is_err());
     }
 
     #[test]
     fn test_block_structure_and_validation() {
-        let v = demo_validator("valX", 3000);
+        let (v, (_, priv_key)) = (demo_validator("valX", 3000), demo_keys("valX"));
         let header = BlockHeader::new(vec![9u8;32], v.public_key.clone(), b"txbatch");
-        let sig = QuantumSignature::sign_ed25519(&v.private_key, &header.hash());
+        let sig = QuantumSignature::sign_ed25519(&priv_key, &sign_bytes(SignKind::Proposal, header.height, 0, &header.hash())).unwrap();
 
         let block = Block {
             header,
             signature: sig.clone(),
             proposer: v.address.clone(),
             transactions: b"txbatch".to_vec(),
+            commit: None,
+            evidence: vec![],
         };
 
         assert_eq!(block.hash(), block.header.hash());
         assert!(block.validate_signature(QuantumSignature::verify_ed25519));
+
+        // Shifting bytes across a field boundary or into metadata changes the hash
+        let mut shifted = block.header.clone();
+        let byte = shifted.parent_hash.pop().unwrap();
+        shifted.proposer_public_key.insert(0, byte);
+        assert_ne!(shifted.hash(), block.header.hash());
+        let mut with_empty_meta = block.header.clone();
+        with_empty_meta.metadata = Some(vec![]);
+        assert_ne!(with_empty_meta.hash(), block.header.hash());
     }
 
     #[test]
     fn test_governance_proposal_and_voting() {
+        let vs = ValidatorSet::new(vec![
+            demo_validator("val1", 600),
+            demo_validator("val2", 300),
+            demo_validator("val3", 100),
+        ]);
         let mut g = GovernanceHook::default();
-        let pid = g.submit_proposal(
-            "alice".to_string(),
+        let submit = |g: &mut GovernanceHook, proposer: &str| g.submit_proposal(
+            proposer.to_string(),
             "Change block time".to_string(),
-            "Update block_time param".to_string(),
-            "block_time".to_string(),
-            "2s".to_string(),
+            "Update block_time_ms param".to_string(),
+            "block_time_ms".to_string(),
+            "2000".to_string(),
             60,
+            1_000,
+            1,
+            &vs,
         );
-
+        assert!(matches!(submit(&mut g, "alice"), Err(ConsensusError::Unauthorized)));
+        let pid = submit(&mut g, "val1").unwrap();
         assert!(g.proposals.contains_key(&pid));
 
-        let status = g.vote(pid, true).unwrap();
-        assert_eq!(status, super::ProposalStatus::Pending);
+        let status = g.vote(pid, "val2", true, 1_010).unwrap();
+        assert_eq!(status, ProposalStatus::Pending);
+        assert!(matches!(g.vote(pid, "val2", false, 1_020), Err(ConsensusError::InvalidVote)));
+        assert!(matches!(g.vote(pid, "mallory", true, 1_020), Err(ConsensusError::Unauthorized)));
+
+        // Voting period still open
+        assert!(g.finalize_proposal(pid, 1_030).is_none());
+
+        // Only 30% of the snapshot stake voted: below the quorum
+        assert_eq!(g.finalize_proposal(pid, 1_060), Some(ProposalStatus::Rejected));
+        assert!(g.vote(pid, "val3", true, 1_061).is_err());
+
+        assert!(g.apply_update("block_time_ms", "2000"));
+        assert_eq!(g.get_config("block_time_ms").unwrap(), 2000);
+        assert!(!g.apply_update("block_time_ms", "2s"));
+        assert!(!g.validate_update("block_time", "2000"));
+    }
+
+    #[test]
+    fn test_governance_votes_are_signed_weighted_and_auto_finalized() {
+        let mut engine = demo_engine();
+        let pid = engine
+            .submit_governance_proposal("val1", "Bigger set", "", "validator_set_size", "50", 60, 1_000)
+            .unwrap();
+
+        // The signature binds the voter and the choice
+        let sig = engine.sign_governance_vote("val2", pid, true).unwrap();
+        assert!(matches!(engine.submit_governance_vote(pid, "val3", true, &sig, 1_001), Err(ConsensusError::InvalidSignature)));
+        assert!(matches!(engine.submit_governance_vote(pid, "val2", false, &sig, 1_001), Err(ConsensusError::InvalidSignature)));
+
+        for (addr, approve) in [("val1", true), ("val2", true), ("val3", true), ("val4", false)] {
+            let sig = engine.sign_governance_vote(addr, pid, approve).unwrap();
+            engine.submit_governance_vote(pid, addr, approve, &sig, 1_001).unwrap();
+        }
+        assert_eq!(engine.governance.proposals[&pid].votes_for, 300);
+
+        engine.finalize_governance(5, 1_059);
+        assert_eq!(engine.governance.proposals[&pid].status, ProposalStatus::Pending);
+        engine.finalize_governance(5, 1_060);
+        assert_eq!(engine.governance.proposals[&pid].status, ProposalStatus::Executed);
+        let pending = engine.governance.params.pending();
+        assert_eq!((pending[0].key.as_str(), pending[0].value, pending[0].activation_height), ("validator_set_size", 50, 15));
+    }
+
+    fn demo_engine() -> ConsensusEngine {
+        let addrs = ["val1", "val2", "val3", "val4"];
+        let vals = addrs.iter().map(|a| demo_validator(a, 100)).collect();
+        let mut engine = ConsensusEngine::new(vals, ConsensusMode::ProofOfStake);
+        // Tests drive every validator from one engine, so it hosts all signers
+        for addr in addrs {
+            engine.add_signer(addr, demo_signer(addr)).unwrap();
+        }
+        engine.set_local_validator("val1");
+        engine.start_height(1, 0);
+        engine
+    }
+
+    #[test]
+    fn test_governance_parameters_are_typed_and_scheduled() {
+        let mut engine = demo_engine();
+
+        assert!(matches!(
+            engine.apply_governance_update("validator_set_size", "0", 50),
+            Err(ConsensusError::InvalidParameter(ParamError::OutOfBounds { .. }))
+        ));
+        assert!(matches!(
+            engine.apply_governance_update("double_sign_slash_bps", "10001", 50),
+            Err(ConsensusError::InvalidParameter(ParamError::OutOfBounds { .. }))
+        ));
+        assert!(matches!(
+            engine.apply_governance_update("quorum", "1", 50),
+            Err(ConsensusError::InvalidParameter(ParamError::UnknownParameter(_)))
+        ));
+        assert!(matches!(
+            engine.apply_governance_update("validator_set_size", "3", 2),
+            Err(ConsensusError::InvalidParameter(ParamError::ActivationTooEarly { earliest: 11 }))
+        ));
+
+        let change = engine.apply_governance_update("validator_set_size", "3", 20).unwrap();
+        assert_eq!(change.value, 3);
+        engine.apply_governance_update("double_sign_slash_bps", "1000", 20).unwrap();
+        engine.apply_governance_update("block_time_ms", "2000", 20).unwrap();
+
+        // Nothing changes before the activation height
+        assert!(engine.activate_parameters(19).is_empty());
+        assert_eq!(engine.staking.config.max_validators, 100);
+        assert_eq!(engine.finality.timeouts.propose_ms, 3_000);
+
+        assert_eq!(engine.activate_parameters(20).len(), 3);
+        assert_eq!(engine.governance.get_config("validator_set_size"), Some(3));
+        assert_eq!(engine.staking.config.max_validators, 3);
+        assert_eq!(engine.slashing.config.double_sign_slash_bps, 1_000);
+        let timeouts = engine.finality.timeouts;
+        assert_eq!((timeouts.propose_ms, timeouts.prevote_ms, timeouts.precommit_ms, timeouts.delta_ms), (6_000, 2_000, 2_000, 1_000));
+        assert!(engine.governance.params.pending().is_empty());
+    }
+
+    fn genesis_block() -> Block {
+        Block {
+            header: BlockHeader::new(vec![0u8; 32], vec![], b""),
+            signature: vec![],
+            proposer: String::new(),
+            transactions: vec![],
+            commit: None,
+            evidence: vec![],
+        }
+    }
+
+    #[test]
+    fn test_bft_round_finalizes_with_commit_certificate() {
+        let mut engine = demo_engine();
+        let proposer = engine.select_leader(1, 0).unwrap().address.clone();
+        let block = engine.propose_block(&proposer, &genesis_block(), b"txs", &ExecutionSummary::default(), 0).unwrap();
+        let hash = block.hash();
+
+        assert!(matches!(
+            engine.handle_proposal("nobody", block.clone(), 0, vec![], 10),
+            Err(ConsensusError::Unauthorized)
+        ));
+        let out = engine.handle_proposal(&proposer, block, 0, vec![], 10).unwrap();
+        assert_eq!(out.votes.len(), 1);
+        assert_eq!(engine.finality.step, RoundStep::Prevote);
+
+        for addr in ["val2", "val3"] {
+            let vote = engine.sign_vote(addr, VoteType::Prevote, 1, 0, Some(hash.clone())).unwrap();
+            engine.handle_vote(vote, 20).unwrap();
+        }
+        assert_eq!(engine.finality.step, RoundStep::Precommit);
+
+        let mut finalized = None;
+        for addr in ["val2", "val3"] {
+            let vote = engine.sign_vote(addr, VoteType::Precommit, 1, 0, Some(hash.clone())).unwrap();
+            finalized = engine.handle_vote(vote, 30).unwrap().finalized.or(finalized);
+        }
+        let block = finalized.expect("block should be final");
+        assert!(block.is_final());
+        assert!(engine.verify_commit(&block).is_ok());
+        assert_eq!(engine.finality.height, 2);
+
+        // Height 2 waits until the ledger reports executing height 1, whose gas must match the header
+        let leader = engine.select_leader(2, 0).unwrap().address.clone();
+        let propose = |engine: &ConsensusEngine| engine.propose_block(&leader, &block, b"next", &ExecutionSummary::default(), 40);
+        assert!(matches!(propose(&engine), Err(ConsensusError::ExecutionPending)));
+        let executed = ExecutionSummary { gas_used: 0, state_root: vec![5u8; 32], logs_bloom: vec![] };
+        assert!(matches!(engine.on_executed(&[1u8; 32], &executed), Err(ConsensusError::UnexpectedExecution)));
+        let wrong_gas = ExecutionSummary { gas_used: 7, ..executed.clone() };
+        assert!(matches!(engine.on_executed(&hash, &wrong_gas), Err(ConsensusError::GasUsedMismatch { header: 0, executed: 7 })));
+        let wrong_bloom = ExecutionSummary { logs_bloom: vec![0u8; 256], ..executed.clone() };
+        assert!(matches!(engine.on_executed(&hash, &wrong_bloom), Err(ConsensusError::LogsBloomMismatch)));
+        engine.on_executed(&hash, &executed).unwrap();
+        let child = propose(&engine).unwrap();
+        assert_eq!(child.header.state_root, vec![5u8; 32]);
+        assert!(engine.verify_header(&child, 40).is_ok());
+    }
+
+    #[test]
+    fn test_bft_nil_round_moves_to_next_round() {
+        let mut engine = demo_engine();
+
+        // No proposal arrives: propose timeout yields a nil prevote
+        let out = engine.handle_timeout(3_000).unwrap();
+        assert_eq!(out.votes[0].block_hash, None);
+
+        for addr in ["val2", "val3"] {
+            let vote = engine.sign_vote(addr, VoteType::Prevote, 1, 0, None).unwrap();
+            engine.handle_vote(vote, 3_100).unwrap();
+        }
+        for addr in ["val2", "val3"] {
+            let vote = engine.sign_vote(addr, VoteType::Precommit, 1, 0, None).unwrap();
+            engine.handle_vote(vote, 3_200).unwrap();
+        }
+        assert_eq!(engine.finality.round, 1);
+        assert_eq!(engine.finality.step, RoundStep::Propose);
+    }
+
+    fn double_sign_evidence(v: &Validator) -> Evidence {
+        let signer = demo_signer(&v.address);
+        let sign = |hash: Vec<u8>| signer.sign(&SignRequest::new(SignKind::Proposal, 7, 0, hash)).unwrap();
+        let mut header_a = BlockHeader::new(vec![0u8; 32], v.public_key.clone(), b"a");
+        let mut header_b = BlockHeader::new(vec![0u8; 32], v.public_key.clone(), b"b");
+        header_a.height = 7;
+        header_b.height = 7;
+        Evidence::DoubleSign(DoubleSignEvidence {
+            proposer: v.address.clone(),
+            signature_a: sign(header_a.hash()),
+            signature_b: sign(header_b.hash()),
+            header_a,
+            header_b,
+        })
+    }
+
+    #[test]
+    fn test_double_sign_evidence_slashes_and_jails() {
+        let mut engine = demo_engine();
+        let offender = engine.validators.get_by_address("val2").unwrap().clone();
+        let evidence = double_sign_evidence(&offender);
+
+        // Evidence survives a gossip roundtrip
+        let msg = evidence.to_gossip().unwrap();
+        let received = Evidence::from_gossip(&msg.payload).unwrap();
+        assert_eq!(received, evidence);
+
+        assert!(engine.submit_evidence(received).unwrap());
+        assert!(!engine.submit_evidence(evidence.clone()).unwrap()); // duplicate
+        assert_eq!(engine.slashing.pending_evidence().len(), 1);
+
+        let mut validators = engine.validators.clone();
+        let record = engine.slashing.apply_evidence(&evidence, &mut validators, 8).unwrap();
+        assert_eq!(record.validator, "val2");
+        assert_eq!(record.amount, 5); // 5% of 100
+        assert_eq!(record.infraction_height, 7);
+        let v = validators.get_by_address("val2").unwrap();
+        assert_eq!(v.stake, 95);
+        assert_eq!(v.status, super::ValidatorStatus::Jailed);
+        assert_eq!(v.jailed_since, Some(8));
+        assert!(engine.slashing.pending_evidence().is_empty());
+    }
+
+    #[test]
+    fn test_evidence_rejects_same_header() {
+        let engine = demo_engine();
+        let v = engine.validators.get_by_address("val3").unwrap();
+        let mut evidence = double_sign_evidence(v);
+        if let Evidence::DoubleSign(ev) = &mut evidence {
+            ev.header_b = ev.header_a.clone();
+            ev.signature_b = ev.signature_a.clone();
+        }
+        assert!(evidence.validate(v).is_err());
+    }
+
+    #[test]
+    fn test_evidence_against_exited_validator_slashes_unbonding_stake() {
+        let mut engine = demo_engine();
+        engine.staking.config.unbonding_period = 20;
+        let offender = engine.validators.get_by_address("val2").unwrap().clone();
+        let late = double_sign_evidence(engine.validators.get_by_address("val3").unwrap());
+
+        // val2 double-signs at height 7, then exits at the epoch boundary at 10
+        engine.staking.request_exit("val2").unwrap();
+        let transition = engine.staking.end_epoch(10, &engine.validators);
+        engine.validators = ValidatorSet::new(transition.active);
+        assert!(engine.validators.get_by_address("val2").is_none());
+
+        let evidence = double_sign_evidence(&offender);
+        assert!(engine.submit_evidence(evidence.clone()).unwrap());
+        let mut validators = engine.validators.clone();
+        let record = engine.slashing.apply_evidence(&evidence, &mut validators, 12).unwrap();
+        assert_eq!((record.amount, record.fraction_bps), (0, 500));
+        assert_eq!(engine.staking.apply_slash(&record, 12), 5);
+        assert_eq!(engine.staking.unbonding[0].amount, 95);
+
+        // Past the unbonding period the stake may be gone, so evidence is refused
+        engine.finality.height = 7 + 21;
+        assert!(matches!(engine.submit_evidence(late), Err(ConsensusError::InvalidEvidence)));
+    }
+
+    #[test]
+    fn test_mixed_scheme_validators_verify_with_own_scheme() {
+        let pq = DilithiumKeypair::generate().unwrap();
+        let vals = vec![
+            demo_validator("val1", 100),
+            Validator::new("pq1".to_owned(), 100, pq.public.clone()).with_scheme(SignatureScheme::Dilithium).unwrap(),
+        ];
+        let mut engine = ConsensusEngine::new(vals, ConsensusMode::ProofOfStake);
+        engine.add_signer("val1", demo_signer("val1")).unwrap();
+        let pq_signer = Arc::new(LocalSigner::new(SignatureScheme::Dilithium, pq.public, pq.private));
+        // A signer must match the validator's registered key and scheme
+        assert!(engine.add_signer("val1", pq_signer.clone()).is_err());
+        engine.add_signer("pq1", pq_signer).unwrap();
+
+        for addr in ["val1", "pq1"] {
+            let block = engine.propose_block(addr, &genesis_block(), b"txs", &ExecutionSummary::default(), 0).unwrap();
+            assert!(engine.verify_block(&block, 0).is_ok());
+
+            let vote = engine.sign_vote(addr, VoteType::Prevote, 1, 0, Some(block.hash())).unwrap();
+            let signer = engine.validators.get_by_address(addr).unwrap();
+            assert!(vote.is_signed_by(signer));
+        }
+
+        // A block claimed by another validator fails against that validator's key
+        let mut forged = engine.propose_block("pq1", &genesis_block(), b"txs", &ExecutionSummary::default(), 0).unwrap();
+        forged.proposer = "val1".to_owned();
+        assert!(matches!(engine.verify_block(&forged, 0), Err(ConsensusError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_xmss_is_rejected_until_a_real_backend_exists() {
+        let mut keypair = XmssKeypair::generate().unwrap();
+        let msg: &[u8] = b"precommit";
+        // The old placeholder accepted sha256(pubkey || msg) from anyone
+        let forged = Sha256::digest([keypair.public.as_slice(), msg].concat()).to_vec();
+        assert!(!QuantumSignature::verify(SignatureScheme::XMSS, &keypair.public, msg, &forged));
+        let signed = QuantumSignature::sign_xmss(&mut keypair, msg);
+        assert!(!QuantumSignature::verify(SignatureScheme::XMSS, &keypair.public, msg, &signed));
+
+        let validator = Validator::new("xmss1".to_owned(), 100, keypair.public.clone());
+        assert!(matches!(validator.clone().with_scheme(SignatureScheme::XMSS), Err(ConsensusError::UnsupportedScheme(_))));
+
+        // Registering an XMSS signer is refused even for a validator declared with the scheme directly
+        let mut engine = ConsensusEngine::new(vec![validator.clone(), demo_validator("val1", 100)], ConsensusMode::ProofOfStake);
+        engine.validators.get_by_address_mut("xmss1").unwrap().scheme = SignatureScheme::XMSS;
+        let signer = Arc::new(LocalSigner::new(SignatureScheme::XMSS, keypair.public.clone(), keypair.private.clone()));
+        assert!(matches!(engine.add_signer("xmss1", signer), Err(ConsensusError::UnsupportedScheme(_))));
+    }
+
+    #[test]
+    fn test_block_validity_rules_reject_malformed_headers() {
+        let mut engine = demo_engine();
+        let now_ms = 100_000;
+        let block = engine.propose_block("val1", &genesis_block(), b"txs", &ExecutionSummary::default(), now_ms).unwrap();
+        assert_eq!(block.header.timestamp, 100);
+        assert!(engine.verify_block(&block, now_ms).is_ok());
+
+        let malformed = |mutate: fn(&mut Block)| {
+            let mut forged = block.clone();
+            mutate(&mut forged);
+            forged
+        };
+        let result = engine.verify_block(&malformed(|b| b.header.height = 2), now_ms);
+        assert!(matches!(result, Err(ConsensusError::InvalidHeight { expected: 1, found: 2 })));
+        let result = engine.verify_block(&malformed(|b| b.header.timestamp = 200), now_ms);
+        assert!(matches!(result, Err(ConsensusError::TimestampTooFarInFuture { max: 115 })));
+        let result = engine.verify_block(&malformed(|b| b.transactions = vec![0u8; 5_000_000]), now_ms);
+        assert!(matches!(result, Err(ConsensusError::BlockTooLarge { size: 5_000_000, .. })));
+        let result = engine.verify_block(&malformed(|b| b.header.gas_used = 30_000_001), now_ms);
+        assert!(matches!(result, Err(ConsensusError::BlockGasExceeded { gas: 30_000_001, .. })));
+        let result = engine.verify_block(&malformed(|b| b.transactions = b"other".to_vec()), now_ms);
+        assert!(matches!(result, Err(ConsensusError::InvalidTxRoot)));
+        let result = engine.verify_block(&malformed(|b| b.header.state_root = vec![1u8; 32]), now_ms);
+        assert!(matches!(result, Err(ConsensusError::InvalidStateRoot)));
+        assert!(matches!(
+            engine.propose_block("val1", &genesis_block(), &vec![0u8; 5_000_000], &ExecutionSummary::default(), now_ms),
+            Err(ConsensusError::BlockTooLarge { .. })
+        ));
+
+        // Gas used comes from the ledger's execution and is signed with the header
+        let executed = ExecutionSummary { gas_used: 21_000, logs_bloom: vec![0x80; 256], ..Default::default() };
+        let metered = engine.propose_block("val1", &genesis_block(), b"txs", &executed, now_ms).unwrap();
+        assert_eq!(metered.header.gas_used, 21_000);
+        assert_eq!(metered.header.logs_bloom, vec![0x80; 256]);
+        assert!(engine.verify_block(&metered, now_ms).is_ok());
+        let over_cap = ExecutionSummary { gas_used: 30_000_001, ..Default::default() };
+        assert!(matches!(
+            engine.propose_block("val1", &genesis_block(), b"txs", &over_cap, now_ms),
+            Err(ConsensusError::BlockGasExceeded { gas: 30_000_001, .. })
+        ));
+
+        // Children must extend the last finalized block and move past the median time
+        engine.last_finalized = Some(block.clone());
+        engine.recent_timestamps.extend([98, 99, 100]);
+        let child = engine.propose_block("val1", &block, b"next", &ExecutionSummary::default(), 90_000).unwrap();
+        assert_eq!(child.header.timestamp, 100);
+        assert!(engine.verify_header(&child, 90_000).is_ok());
 
-        // Force voting period expiration for test
-        let prop = g.proposals.get_mut(&pid).unwrap();
-        prop.end_time = 0;
+        // Base fee starts at the initial value and falls after an empty parent
+        assert_eq!(block.header.base_fee_per_gas, 1_000_000_000);
+        assert_eq!(child.header.base_fee_per_gas, 875_000_000);
+        let mut underpriced = child.clone();
+        underpriced.header.base_fee_per_gas = 1;
+        assert!(matches!(
+            engine.verify_header(&underpriced, 90_000),
+            Err(ConsensusError::InvalidBaseFee { expected: 875_000_000, found: 1 })
+        ));
+        let limits = BlockLimits::default();
+        let mut parent = block.header.clone();
+        parent.gas_used = limits.max_block_gas;
+        assert_eq!(limits.next_base_fee(&parent), 1_125_000_000);
+        parent.gas_used = limits.gas_target();
+        assert_eq!(limits.next_base_fee(&parent), 1_000_000_000);
+        parent.base_fee_per_gas = 1;
+        parent.gas_used = 0;
+        assert_eq!(limits.next_base_fee(&parent), 1);
 
-        let fin = g.finalize_proposal(pid).unwrap();
-        assert!(fin == super::ProposalStatus::Approved || fin == super::ProposalStatus::Rejected);
+        let mut stale = child.clone();
+        stale.header.timestamp = 99;
+        assert!(matches!(engine.verify_header(&stale, 90_000), Err(ConsensusError::TimestampTooOld { median: 99 })));
+        let mut orphan = child.clone();
+        orphan.header.parent_hash = vec![0u8; 32];
+        assert!(matches!(engine.verify_header(&orphan, 90_000), Err(ConsensusError::InvalidParentHash)));
+        let sibling = engine.propose_block("val1", &genesis_block(), b"txs", &ExecutionSummary::default(), now_ms).unwrap();
+        assert!(matches!(
+            engine.verify_header(&sibling, now_ms),
+            Err(ConsensusError::InvalidHeight { expected: 2, found: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_downtime_window_jails_absent_validator() {
+        let mut validators = ValidatorSet::new(vec![
+            demo_validator("val1", 10_000),
+            demo_validator("val2", 10_000),
+        ]);
+        let mut slashing = SlashingManager::new(SlashingConfig {
+            signed_blocks_window: 4,
+            min_signed_per_window_bps: 5_000,
+            ..Default::default()
+        });
+        let signers: std::collections::HashSet<String> = ["val1".to_string()].into_iter().collect();
+
+        for h in 1..4 {
+            assert!(slashing.record_participation(&signers, &mut validators, h).is_empty());
+        }
+        assert_eq!(slashing.missed_blocks("val2"), 3);
+        let punished = slashing.record_participation(&signers, &mut validators, 4);
+        assert_eq!(punished.len(), 1);
+        assert_eq!((punished[0].validator.as_str(), punished[0].amount), ("val2", 10));
+        assert_eq!(validators.get_by_address("val2").unwrap().status, super::ValidatorStatus::Jailed);
+        assert_eq!(validators.get_by_address("val1").unwrap().status, super::ValidatorStatus::Active);
+    }
+
+    #[test]
+    fn test_unjail_after_min_jail_duration_and_uptime_stats() {
+        let mut engine = demo_engine();
+        let audit_log = Arc::new(Mutex::new(AuditLog::new(16)));
+        engine.add_event_sink(Arc::new(AuditLogSink::new(audit_log.clone(), [7u8; 20])));
+        engine.slashing.config.signed_blocks_window = 4;
+        engine.slashing.config.min_jail_blocks = 10;
+        let signers: std::collections::HashSet<String> = ["val1", "val2", "val3"].iter().map(|s| s.to_string()).collect();
+        for h in 1..=4 {
+            engine.slashing.record_participation(&signers, &mut engine.validators, h);
+        }
+
+        let stats = engine.uptime("val4").unwrap();
+        assert_eq!(stats.uptime_bps, 0);
+        assert_eq!(stats.lifetime, Participation { signed: 0, missed: 4, times_jailed: 1 });
+        assert_eq!((stats.jailed_since, stats.jailed_until), (Some(4), Some(14)));
+        let ranking: Vec<String> = engine.uptime_stats().into_iter().map(|s| s.validator).collect();
+        assert_eq!(ranking, vec!["val1", "val2", "val3", "val4"]);
+        assert_eq!(engine.uptime("val1").unwrap().uptime_bps, 10_000);
+
+        // Too early, then a signature bound to another jailing, then a valid unjail
+        let signature = engine.sign_unjail("val4").unwrap();
+        assert!(matches!(engine.submit_unjail("val4", &signature), Err(ConsensusError::JailPeriodNotElapsed { until: 14 })));
+        engine.start_height(14, 0);
+        let replayed = demo_signer("val4")
+            .sign(&SignRequest::new(SignKind::Unjail, 14, 0, SlashingManager::unjail_sign_bytes("val4", 3)))
+            .unwrap();
+        assert!(matches!(engine.submit_unjail("val4", &replayed), Err(ConsensusError::InvalidSignature)));
+        engine.submit_unjail("val4", &signature).unwrap();
+        assert_eq!(engine.validators.get_by_address("val4").unwrap().status, super::ValidatorStatus::Active);
+        assert!(engine.validators.voting_power("val4") > 0);
+        assert!(matches!(engine.submit_unjail("val4", &signature), Err(ConsensusError::ValidatorNotJailed)));
+        assert!(matches!(engine.sign_unjail("val1"), Err(ConsensusError::ValidatorNotJailed)));
+
+        // The unjailing reaches audit sinks as its own action
+        let audit_log = audit_log.lock().unwrap();
+        let unjailed = audit_log.by_action(ActionType::Custom(audit_codes::VALIDATOR_UNJAILED));
+        assert_eq!(unjailed.len(), 1);
+        assert_eq!(unjailed[0].level, LogLevel::Info);
+        assert!(unjailed[0].details.contains("val4"));
+    }
+
+    fn demo_staking(max_validators: usize) -> StakingLedger {
+        let config = StakingConfig {
+            epoch_length: 10,
+            max_validators,
+            unbonding_period: 20,
+            min_self_bond: 1,
+        };
+        StakingLedger::new(config, vec![
+            demo_validator("val1", 300),
+            demo_validator("val2", 200),
+            demo_validator("val3", 100),
+        ])
+    }
 
-        g.apply_update("block_time", "2s");
-        assert_eq!(g.get_config("block_time").unwrap(), "2s");
+    /// Ledger state where each named owner's account holds `balance`
+    fn funded_state(owners: &[&str], balance: u128) -> State {
+        let mut state = State::new();
+        for owner in owners {
+            state.update_account(ledger_account(owner), AccountState::new(balance));
+        }
+        state
+    }
+
+    fn balance_of(state: &State, owner: &str) -> u128 {
+        state.get_account(&ledger_account(owner)).map_or(0, |account| account.balance)
+    }
+
+    #[test]
+    fn test_epoch_rotation_picks_top_n_by_bonded_stake() {
+        let mut staking = demo_staking(2);
+        let current = ValidatorSet::new(ValidatorSet::load_all(&staking));
+        assert!(current.get_by_address("val3").is_none());
+
+        // Delegation and a new candidate only count from the next epoch
+        let mut state = funded_state(&["alice", "val4"], 1_000);
+        staking.delegate(&mut state, "alice", "val3", 250).unwrap();
+        staking.request_join(&mut state, demo_validator("val4", 0), 500).unwrap();
+        staking.request_exit("val1").unwrap();
+        assert!(staking.is_epoch_boundary(10));
+
+        let transition = staking.end_epoch(10, &current);
+        let active: Vec<&str> = transition.active.iter().map(|v| v.address.as_str()).collect();
+        assert_eq!(active, vec!["val4", "val3"]);
+        assert_eq!(transition.epoch, 1);
+        assert_eq!(staking.unbonding.len(), 1); // val1's self-bond
+    }
+
+    #[test]
+    fn test_epoch_length_change_waits_for_the_boundary() {
+        let mut staking = demo_staking(2);
+        let current = ValidatorSet::new(ValidatorSet::load_all(&staking));
+
+        // Shortening mid-epoch keeps the running epoch's end at 10
+        staking.set_epoch_length(4);
+        assert!(!staking.is_epoch_boundary(4) && !staking.is_epoch_boundary(8));
+        assert!(staking.is_epoch_boundary(10));
+        staking.end_epoch(10, &current);
+        assert_eq!(staking.config.epoch_length, 4);
+        assert!(!staking.is_epoch_boundary(12));
+        assert!(staking.is_epoch_boundary(14) && staking.is_epoch_boundary(18));
+    }
+
+    #[test]
+    fn test_shrinking_signing_window_truncates_bitmaps() {
+        let mut validators = ValidatorSet::new(vec![demo_validator("val1", 10_000), demo_validator("val2", 10_000)]);
+        let mut slashing = SlashingManager::new(SlashingConfig {
+            signed_blocks_window: 8,
+            min_signed_per_window_bps: 5_000,
+            ..Default::default()
+        });
+        let signers: std::collections::HashSet<String> = ["val1".to_string()].into_iter().collect();
+        let everyone: std::collections::HashSet<String> = ["val1", "val2"].iter().map(|s| s.to_string()).collect();
+        for h in 1..=3 {
+            slashing.record_participation(&signers, &mut validators, h);
+        }
+        for h in 4..=6 {
+            slashing.record_participation(&everyone, &mut validators, h);
+        }
+        assert_eq!(slashing.missed_blocks("val2"), 3);
+
+        // Only the last 4 blocks count once the window shrinks to 4
+        slashing.set_signed_blocks_window(4);
+        assert_eq!(slashing.signing_window["val2"].len(), 4);
+        assert_eq!(slashing.missed_blocks("val2"), 1);
+        assert!(slashing.record_participation(&everyone, &mut validators, 7).is_empty());
+        assert_eq!(slashing.signing_window["val2"].len(), 4);
+    }
+
+    #[test]
+    fn test_unbonding_stays_slashable_until_released() {
+        let mut staking = demo_staking(3);
+        let mut state = funded_state(&["bob"], 1_000);
+        staking.delegate(&mut state, "bob", "val2", 1_000).unwrap();
+        let entry = staking.undelegate("bob", "val2", 400, 12).unwrap();
+        assert_eq!(entry.completion_height, 32);
+
+        // Infraction at height 11 catches stake that started unbonding afterwards
+        let record = SlashRecord { validator: "val2".into(), amount: 0, fraction_bps: 1_000, infraction_height: 11 };
+        staking.apply_slash(&record, 15);
+        assert_eq!(staking.unbonding[0].amount, 360);
+        assert_eq!(staking.bonded_stake("val2"), 180 + 540);
+
+        let current = ValidatorSet::new(ValidatorSet::load_all(&staking));
+        assert!(staking.end_epoch(20, &current).released.is_empty());
+        let released = staking.end_epoch(40, &current).released;
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].amount, 360);
+    }
+
+    #[test]
+    fn test_bonds_are_escrowed_and_released_at_maturity() {
+        let mut engine = demo_engine();
+        engine.staking.config.epoch_length = 1;
+        engine.staking.config.unbonding_period = 0;
+        let mut state = funded_state(&["dave", "val5"], 1_000);
+
+        engine.staking.delegate(&mut state, "dave", "val2", 400).unwrap();
+        assert_eq!(balance_of(&state, "dave"), 600);
+        let err = engine.staking.delegate(&mut state, "dave", "val2", 601).unwrap_err();
+        assert!(matches!(err, ConsensusError::InsufficientBond { amount: 601, .. }));
+        assert!(engine.staking.delegate(&mut state, "erin", "val2", 1).is_err());
+        assert_eq!(balance_of(&state, "dave"), 600);
+
+        // A queued join escrows its self-bond once; a second request is refused
+        engine.staking.request_join(&mut state, demo_validator("val5", 0), 300).unwrap();
+        assert!(engine.staking.request_join(&mut state, demo_validator("val5", 0), 300).is_err());
+        assert_eq!(balance_of(&state, "val5"), 700);
+        assert_eq!(engine.staking.pending.len(), 1);
+
+        // Nothing is credited before the unbonding is released
+        engine.staking.undelegate("dave", "val2", 400, 0).unwrap();
+        assert!(engine.take_matured_unbonding(&mut state).unwrap().is_empty());
+        let validators = engine.validators.clone();
+        let transition = engine.staking.end_epoch(1, &validators);
+        engine.matured_unbonding.extend(transition.released);
+
+        let released = engine.take_matured_unbonding(&mut state).unwrap();
+        assert_eq!(released.len(), 1);
+        assert_eq!(balance_of(&state, "dave"), 1_000);
+        assert!(engine.matured_unbonding.is_empty());
+    }
+
+    #[test]
+    fn test_issuance_schedule_decays_to_tail() {
+        let schedule = IssuanceSchedule {
+            initial_block_reward: 1_000,
+            reduction_interval: 10,
+            reduction_bps: 5_000,
+            tail_block_reward: 200,
+        };
+        assert_eq!(schedule.block_reward(0), 0);
+        assert_eq!(schedule.block_reward(10), 1_000);
+        assert_eq!(schedule.block_reward(11), 500);
+        assert_eq!(schedule.block_reward(21), 250);
+        assert_eq!(schedule.block_reward(31), 200);
+        assert_eq!(schedule.total_issued(20), 15_000);
+    }
+
+    #[test]
+    fn test_block_rewards_split_by_stake_commission_and_delegation() {
+        let mut engine = demo_engine();
+        engine.rewards = RewardConfig {
+            issuance: IssuanceSchedule { initial_block_reward: 10_000, reduction_interval: 0, reduction_bps: 0, tail_block_reward: 0 },
+            ..RewardConfig::default()
+        };
+        let mut state = funded_state(&["carol"], 100);
+        engine.staking.delegate(&mut state, "carol", "val1", 100).unwrap();
+        engine.staking.set_commission("val1", 2_000).unwrap();
+        assert!(engine.staking.set_commission("val1", 10_001).is_err());
+
+        let proposer_key = engine.validators.get_by_address("val1").unwrap().public_key.clone();
+        let dist = engine.reward_distributor().distribute(1, &proposer_key);
+        assert!(dist.is_balanced());
+        assert_eq!(dist.proposer.as_deref(), Some("val1"));
+        assert_eq!(dist.minted, 10_000);
+        // 2% of issuance, plus 2 units of stake-rounding dust
+        assert_eq!(dist.treasury, 202);
+        assert_eq!(dist.proposer_bonus, 490);
+
+        let val1 = dist.validators.iter().find(|v| v.validator == "val1").unwrap();
+        assert_eq!((val1.amount, val1.commission), (2_327 + 490, 563));
+        assert_eq!(val1.delegator_shares, vec![("carol".to_string(), 1_127), ("val1".to_string(), 1_127)]);
+
+        let payouts = dist.payouts();
+        assert_eq!(payouts["carol"], 1_127);
+        assert_eq!(payouts["val2"], 2_327); // default 10% commission + its own self-bond share
+        assert_eq!(payouts["treasury"], 202);
+    }
+
+    #[test]
+    fn test_event_sinks_receive_engine_events() {
+        let ring = Arc::new(RingBufferSink::new(2));
+        let channel = Arc::new(BroadcastSink::new(16));
+        let mut rx = channel.subscribe();
+        let path = std::env::temp_dir().join(format!("ocos-consensus-events-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let file = Arc::new(JsonLinesSink::create(&path).unwrap());
+        let audit_log = Arc::new(Mutex::new(AuditLog::new(16)));
+        let audit = Arc::new(AuditLogSink::new(audit_log.clone(), [7u8; 20]));
+
+        let mut engine = demo_engine();
+        engine.add_event_sink(ring.clone());
+        engine.add_event_sink(Arc::new(CompositeSink::new().with(channel.clone()).with(file.clone()).with(audit)));
+
+        let offender = engine.validators.get_by_address("val2").unwrap().clone();
+        engine.submit_evidence(double_sign_evidence(&offender)).unwrap();
+        for _ in 0..2 {
+            engine.propose_block("val1", &genesis_block(), b"txs", &ExecutionSummary::default(), 0).unwrap();
+        }
+
+        // Ring buffer keeps only the newest two
+        let expected = ConsensusEvent::BlockProposed { proposer: "val1".into(), height: 1 };
+        assert_eq!(ring.snapshot(), vec![expected.clone(), expected.clone()]);
+
+        assert_eq!(rx.try_recv().unwrap(), ConsensusEvent::EvidenceSubmitted { validator: "val2".into(), height: 7 });
+        assert_eq!(rx.try_recv().unwrap(), expected);
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 3);
+        assert!(lines.lines().next().unwrap().contains("EvidenceSubmitted"));
+        assert_eq!(file.error_count(), 0);
+        let _ = std::fs::remove_file(&path);
+
+        let audit_log = audit_log.lock().unwrap();
+        assert_eq!(audit_log.entries.len(), 3);
+        assert_eq!(audit_log.by_level(LogLevel::Warn).len(), 1);
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ocos-consensus-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_keystore_signer_roundtrip() {
+        let path = temp_path("keystore.json");
+        let (pub_key, priv_key) = demo_keys("val1");
+        KeystoreSigner::create(&path, b"correct horse", SignatureScheme::Ed25519, &pub_key, &priv_key).unwrap();
+
+        // The private key never appears in the file
+        let file = std::fs::read_to_string(&path).unwrap();
+        assert!(!file.contains(&hex::encode(&priv_key)));
+        assert!(matches!(KeystoreSigner::open(&path, b"wrong"), Err(ConsensusError::Keystore(_))));
+
+        let signer = KeystoreSigner::open(&path, b"correct horse").unwrap();
+        assert_eq!(signer.public_key(), pub_key);
+        let request = SignRequest::new(SignKind::Prevote, 1, 0, b"vote".to_vec());
+        let sig = signer.sign(&request).unwrap();
+        assert!(demo_validator("val1", 100).verify_signed(SignKind::Prevote, 1, 0, b"vote", &sig));
+        assert!(!demo_validator("val1", 100).verify_signature(b"vote", &sig));
+
+        // Scheme and public key are authenticated: swapping in another key breaks decryption
+        let mut json: serde_json::Value = serde_json::from_str(&file).unwrap();
+        json["public_key"] = serde_json::Value::String(hex::encode(demo_keys("val2").0));
+        std::fs::write(&path, serde_json::to_vec(&json).unwrap()).unwrap();
+        assert!(matches!(KeystoreSigner::open(&path, b"correct horse"), Err(ConsensusError::Keystore(_))));
+
+        // A file encrypted for a key that is not the stated public key is refused on open
+        let (_, other_priv) = demo_keys("val2");
+        KeystoreSigner::create(&path, b"correct horse", SignatureScheme::Ed25519, &pub_key, &other_priv).unwrap();
+        assert!(matches!(KeystoreSigner::open(&path, b"correct horse"), Err(ConsensusError::Keystore(_))));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_double_sign_guard_persists_last_signed_position() {
+        let path = temp_path("sign-state.json");
+        let _ = std::fs::remove_file(&path);
+        let request = |kind, height, round, payload: &[u8]| SignRequest::new(kind, height, round, payload.to_vec());
+
+        let mut guard = DoubleSignGuard::with_state_file(&path).unwrap();
+        guard.check_and_record(&request(SignKind::Prevote, 5, 1, b"block-a")).unwrap();
+        guard.check_and_record(&request(SignKind::Prevote, 5, 1, b"block-a")).unwrap(); // identical re-sign
+        assert!(matches!(
+            guard.check_and_record(&request(SignKind::Prevote, 5, 1, b"block-b")),
+            Err(ConsensusError::DoubleSignPrevented { height: 5, round: 1 })
+        ));
+        guard.check_and_record(&request(SignKind::Precommit, 5, 1, b"block-a")).unwrap();
+
+        // A restarted signer still refuses to go backwards
+        let mut reloaded = DoubleSignGuard::with_state_file(&path).unwrap();
+        assert_eq!(reloaded.last.as_ref().map(|s| (s.height, s.round, s.step)), Some((5, 1, 3)));
+        assert!(reloaded.check_and_record(&request(SignKind::Prevote, 5, 1, b"block-a")).is_err());
+        assert!(reloaded.check_and_record(&request(SignKind::Proposal, 4, 9, b"old")).is_err());
+        reloaded.check_and_record(&request(SignKind::Beacon, 1, 0, b"seed")).unwrap(); // not guarded
+        reloaded.check_and_record(&request(SignKind::Proposal, 6, 0, b"next")).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_signer_over_unix_socket() {
+        use crate::core::consensus::signer::{RemoteSigner, RemoteSignerServer};
+
+        let socket = temp_path("signer.sock");
+        let (pub_key, priv_key) = demo_keys("val1");
+        let listener = RemoteSignerServer::bind(&socket).unwrap();
+        let server = RemoteSignerServer::new(
+            Box::new(LocalSigner::new(SignatureScheme::Ed25519, pub_key.clone(), priv_key)),
+            DoubleSignGuard::in_memory(),
+        );
+        std::thread::spawn(move || server.serve(listener));
+
+        let remote = RemoteSigner::connect(&socket).unwrap();
+        assert_eq!(remote.public_key(), pub_key);
+
+        // The engine signs through the remote signer
+        let mut engine = ConsensusEngine::new(vec![demo_validator("val1", 100)], ConsensusMode::ProofOfStake);
+        engine.add_signer("val1", Arc::new(remote.clone())).unwrap();
+        let vote = engine.sign_vote("val1", VoteType::Prevote, 1, 0, Some(vec![1u8; 32])).unwrap();
+        assert!(vote.is_signed_by(engine.validators.get_by_address("val1").unwrap()));
+
+        // A conflicting prevote for the same height/round is refused by the service
+        assert!(matches!(
+            engine.sign_vote("val1", VoteType::Prevote, 1, 0, Some(vec![2u8; 32])),
+            Err(ConsensusError::DoubleSignPrevented { height: 1, round: 0 })
+        ));
+
+        // Relabelling the conflicting prevote as an unguarded kind, or claiming another
+        // position for it, yields a signature that never verifies as that prevote
+        let mut conflicting = Vote::unsigned(VoteType::Prevote, 1, 0, Some(vec![2u8; 32]));
+        conflicting.validator = "val1".to_owned();
+        let validator = engine.validators.get_by_address("val1").unwrap().clone();
+        for (kind, height) in [(SignKind::Governance, 1), (SignKind::Beacon, 1), (SignKind::Unjail, 1), (SignKind::Prevote, 9)] {
+            conflicting.signature = remote.sign(&SignRequest::new(kind, height, 0, conflicting.sign_bytes())).unwrap();
+            assert!(!conflicting.is_signed_by(&validator));
+        }
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    fn demo_set(members: &[&str]) -> Vec<Validator> {
+        members.iter().map(|a| demo_validator(a, 100)).collect()
+    }
+
+    fn next_header(parent: &BlockHeader, set: &[&str], next: &[&str]) -> BlockHeader {
+        BlockHeader {
+            parent_hash: parent.hash(),
+            height: parent.height + 1,
+            timestamp: 1_000 + parent.height + 1,
+            validators_hash: ValidatorSet::new(demo_set(set)).hash(),
+            next_validators_hash: ValidatorSet::new(demo_set(next)).hash(),
+            ..Default::default()
+        }
+    }
+
+    /// Light block whose commit carries precommits from `signers`
+    fn certify(header: BlockHeader, set: &[&str], signers: &[&str]) -> LightBlock {
+        let hash = header.hash();
+        let precommits = signers.iter().map(|addr| {
+            let mut vote = Vote::unsigned(VoteType::Precommit, header.height, 0, Some(hash.clone()));
+            vote.validator = addr.to_string();
+            let request = SignRequest::new(SignKind::Precommit, header.height, 0, vote.sign_bytes());
+            vote.signature = demo_signer(addr).sign(&request).unwrap();
+            vote
+        }).collect();
+        LightBlock {
+            commit: CommitCertificate { height: header.height, round: 0, block_hash: hash, precommits },
+            header,
+            validators: demo_set(set),
+        }
+    }
+
+    const OLD_SET: [&str; 4] = ["val1", "val2", "val3", "val4"];
+    const LIGHT_NOW: u64 = 2_000;
+
+    #[test]
+    fn test_light_client_follows_validator_set_transition() {
+        // Engine headers commit to the epoch set
+        let engine = demo_engine();
+        let block = engine.propose_block("val1", &genesis_block(), b"txs", &ExecutionSummary::default(), 0).unwrap();
+        assert_eq!(block.header.validators_hash, engine.epoch_validators.hash());
+        assert_eq!(block.header.next_validators_hash, engine.epoch_validators.hash());
+
+        let new_set = ["val1", "val2", "val5", "val6"];
+        let h1 = certify(next_header(&BlockHeader::default(), &OLD_SET, &OLD_SET), &OLD_SET, &["val1", "val2", "val3"]);
+        let mut client = LightClient::new(LightClientConfig::default(), h1.clone()).unwrap();
+
+        // h2 announces the new set; two of four signatures are not a commit
+        let h2_header = next_header(&h1.header, &OLD_SET, &new_set);
+        let weak = certify(h2_header.clone(), &OLD_SET, &["val1", "val2"]);
+        assert_eq!(client.update(weak, LIGHT_NOW), Err(LightClientError::InvalidCommit));
+        let h2 = certify(h2_header, &OLD_SET, &["val2", "val3", "val4"]);
+        client.update(h2.clone(), LIGHT_NOW).unwrap();
+
+        // The successor must be signed by the announced set, not the old one
+        let stale = certify(next_header(&h2.header, &OLD_SET, &OLD_SET), &OLD_SET, &["val1", "val2", "val3"]);
+        assert_eq!(client.update(stale, LIGHT_NOW), Err(LightClientError::ValidatorSetMismatch));
+        let mut h3 = certify(next_header(&h2.header, &new_set, &new_set), &new_set, &["val1", "val5", "val6"]);
+        h3.validators[0].stake = 1_000; // a full node cannot inflate stake
+        assert_eq!(client.update(h3.clone(), LIGHT_NOW), Err(LightClientError::ValidatorSetMismatch));
+        h3.validators[0].stake = 100;
+        client.update(h3, LIGHT_NOW).unwrap();
+        assert_eq!(client.trusted_height(), 3);
+        assert!(client.verified_header(2).is_some());
+    }
+
+    #[test]
+    fn test_light_client_skips_with_trusted_third_and_bisects() {
+        let rotated = ["val7", "val8", "val9"];
+        let mut headers = vec![certify(next_header(&BlockHeader::default(), &OLD_SET, &OLD_SET), &OLD_SET, &OLD_SET)];
+        for height in 2..=6u64 {
+            let parent = &headers.last().unwrap().header;
+            let (set, next): (&[&str], &[&str]) = match height {
+                2 => (&OLD_SET, &OLD_SET),
+                3 => (&OLD_SET, &rotated),
+                _ => (&rotated, &rotated),
+            };
+            headers.push(certify(next_header(parent, set, next), set, set));
+        }
+        let fetch = |h: u64| headers.get(h as usize - 1).cloned();
+
+        // Skipping across a full rotation has no trusted signers left
+        let mut client = LightClient::new(LightClientConfig::default(), headers[0].clone()).unwrap();
+        assert_eq!(client.verify_skipping(&headers[5], LIGHT_NOW), Err(LightClientError::InsufficientTrust));
+        assert!(client.verify_skipping(&headers[2], LIGHT_NOW).is_ok());
+        assert_eq!(
+            client.verify_skipping(&headers[2], 1_003 + 15 * 24 * 3_600),
+            Err(LightClientError::TrustExpired)
+        );
+
+        // Bisection walks 1 → 3 (skip) → 4 (transition) → 6 (skip)
+        client.verify_to(6, fetch, LIGHT_NOW).unwrap();
+        assert_eq!(client.trusted_height(), 6);
+        assert!(client.verified_header(4).is_some());
+        assert!(client.verified_header(5).is_none());
+    }
+
+    #[test]
+    fn test_light_client_verifies_account_balance_proof() {
+        let mut state = State::new();
+        for i in 1u8..=5 {
+            state.update_account(vec![i; 20], AccountState::new(i as u128 * 1_000));
+        }
+        let mut header = next_header(&BlockHeader::default(), &OLD_SET, &OLD_SET);
+        header.state_root = state.commit();
+        let client = LightClient::new(LightClientConfig::default(), certify(header, &OLD_SET, &OLD_SET)).unwrap();
+
+        for i in 1u8..=5 {
+            let proof = state.prove_account(&[i; 20]);
+            assert_eq!(client.verify_account(1, &proof).unwrap(), i as u128 * 1_000);
+        }
+        // Unknown accounts are proven absent, i.e. hold nothing
+        let absent = state.prove_account(&[9u8; 20]);
+        assert!(absent.account.is_none());
+        assert_eq!(client.verify_account(1, &absent).unwrap(), 0);
+
+        let mut forged = state.prove_account(&[3u8; 20]);
+        forged.account.as_mut().unwrap().balance = 1_000_000;
+        assert_eq!(client.verify_account(1, &forged), Err(LightClientError::InvalidStateProof));
+        assert_eq!(client.verify_account(2, &forged), Err(LightClientError::UnknownHeight(2)));
+    }
+
+    fn demo_simulator(seed: u64) -> Simulator {
+        let genesis = ["val1", "val2", "val3", "val4"]
+            .iter()
+            .map(|a| (demo_validator(a, 100), demo_signer(a) as Arc<dyn BlockSigner>))
+            .collect();
+        Simulator::new(SimConfig { seed, ..Default::default() }, genesis)
+    }
+
+    #[test]
+    fn test_simulator_honest_network_is_safe_and_live() {
+        let mut sim = demo_simulator(1);
+        let report = sim.run_until(5_000);
+        assert!(report.is_safe());
+        assert!(report.liveness_stalls.is_empty());
+        assert!(report.min_height(&[]) >= 5);
+        assert_eq!(sim.finalized_chain("val1")[0].hash(), sim.finalized_chain("val4")[0].hash());
+    }
+
+    #[test]
+    fn test_simulator_partition_stalls_then_recovers() {
+        let mut sim = demo_simulator(2);
+        sim.config.stall_threshold_ms = 5_000;
+        sim.add_partition(Partition {
+            start_ms: 1_000,
+            end_ms: 12_000,
+            groups: vec![vec!["val1".into(), "val2".into()], vec!["val3".into(), "val4".into()]],
+        });
+        let during = sim.run_until(11_000);
+        assert!(during.messages_dropped > 0);
+
+        let after = sim.run_until(40_000);
+        assert!(after.is_safe());
+        assert!(!after.liveness_stalls.is_empty()); // neither half holds 2/3 of the stake
+        assert!(after.min_height(&[]) > during.finalized_heights.values().copied().max().unwrap());
+    }
+
+    #[test]
+    fn test_simulator_byzantine_minority_cannot_break_safety() {
+        for behaviour in [Behaviour::Equivocate, Behaviour::Withhold] {
+            let mut sim = demo_simulator(3);
+            sim.config.drop_rate_bps = 500;
+            sim.set_behaviour("val4", behaviour);
+            let report = sim.run_until(20_000);
+            assert!(report.is_safe(), "{:?}", behaviour);
+            assert!(report.min_height(&["val1", "val2", "val3"]) >= 3, "{:?}", behaviour);
+            // An equivocating leader backs its twin block with votes, not only nil
+            assert_eq!(report.twin_votes_sent > 0, behaviour == Behaviour::Equivocate, "{:?}", behaviour);
+        }
+    }
+
+    #[test]
+    fn test_simulator_validators_locked_on_different_blocks_converge() {
+        let mut sim = demo_simulator(4);
+        let leader = |sim: &Simulator, round| sim.engine("val1").unwrap().select_leader(1, round).unwrap().address.clone();
+        let (l0, l1) = (leader(&sim, 0), leader(&sim, 1));
+        let a = sim.engine(&l0).unwrap().propose_block(&l0, &genesis_block(), b"A", &ExecutionSummary::default(), 0).unwrap();
+        let b = sim.engine(&l1).unwrap().propose_block(&l1, &genesis_block(), b"B", &ExecutionSummary::default(), 0).unwrap();
+        let l2 = leader(&sim, 2);
+        let other = ["val1", "val2", "val3", "val4"].into_iter().find(|v| *v != l2).unwrap();
+        let c = sim.engine(other).unwrap().propose_block(other, &genesis_block(), b"C", &ExecutionSummary::default(), 0).unwrap();
+
+        // Scripted rounds: engines only see the messages handed to them here
+        let propose = |sim: &mut Simulator, addr: &str, block: &Block, round, now| {
+            sim.engine_mut(addr).unwrap().handle_proposal(&block.proposer, block.clone(), round, vec![], now).unwrap().votes
+        };
+        let deliver = |sim: &mut Simulator, addr: &str, votes: &[Vote], now| {
+            for vote in votes {
+                sim.engine_mut(addr).unwrap().handle_vote(vote.clone(), now).unwrap();
+            }
+        };
+        let timeout = |sim: &mut Simulator, addr: &str, now| {
+            sim.engine_mut(addr).unwrap().handle_timeout(now).unwrap();
+        };
+
+        // Round 0: val1 sees 2/3+ prevotes for A and locks on it; nobody else does
+        propose(&mut sim, "val1", &a, 0, 0);
+        let a_prevotes: Vec<Vote> = ["val3", "val4"].iter().flat_map(|v| propose(&mut sim, v, &a, 0, 0)).collect();
+        deliver(&mut sim, "val1", &a_prevotes, 0);
+        timeout(&mut sim, "val1", 1_000);
+        for addr in ["val3", "val4"] {
+            timeout(&mut sim, addr, 1_000);
+            timeout(&mut sim, addr, 2_000);
+        }
+
+        // Round 1: locked val1 prevotes nil on B; val3 and val4 prevote B, which val2 locks on
+        assert_eq!(propose(&mut sim, "val1", &b, 1, 2_000)[0].block_hash, None);
+        let b_prevotes: Vec<Vote> = ["val3", "val4"].iter().flat_map(|v| propose(&mut sim, v, &b, 1, 2_000)).collect();
+        deliver(&mut sim, "val2", &b_prevotes, 2_000);
+        propose(&mut sim, "val2", &b, 1, 2_000);
+        let locked = |sim: &Simulator, addr: &str| sim.engine(addr).unwrap().finality.locked.clone();
+        assert_eq!(locked(&sim, "val1"), Some((0, a.hash())));
+        assert_eq!(locked(&sim, "val2"), Some((1, b.hash())));
+        let (valid, b_pol) = sim.engine("val2").unwrap().finality.valid_proposal().unwrap();
+        assert_eq!((valid.hash(), b_pol.len()), (b.hash(), 3));
+        let (_, a_pol) = sim.engine("val1").unwrap().finality.valid_proposal().unwrap();
+
+        // A proof of lock from a round at or after the lock unlocks; an older one does not
+        let validators = sim.engine("val1").unwrap().validators.clone();
+        let prevote_after = |lock: (u32, Vec<u8>), pol: &[Vote], block: &Block| {
+            let mut gadget = FinalityGadget::new(1, TimeoutConfig::default());
+            gadget.locked = Some(lock);
+            gadget.start_round(2, 2_000);
+            for vote in pol {
+                gadget.on_vote(vote.clone(), 100, &validators, 2_000).unwrap();
+            }
+            gadget.on_proposal(block.clone(), 2, &validators, 2_000).votes[0].block_hash.clone()
+        };
+        assert_eq!(prevote_after((0, a.hash()), &b_pol, &b), Some(b.hash()));
+        assert_eq!(prevote_after((1, b.hash()), &a_pol, &a), None);
+
+        // Only the round's leader may relay another validator's block, and only with its own proof of lock
+        assert!(matches!(
+            sim.engine_mut("val3").unwrap().handle_proposal(&l2, c.clone(), 2, a_pol.clone(), 2_000),
+            Err(ConsensusError::InvalidProofOfLock)
+        ));
+        assert!(matches!(
+            sim.engine_mut("val3").unwrap().handle_proposal(other, c.clone(), 2, vec![], 2_000),
+            Err(ConsensusError::Unauthorized)
+        ));
+
+        // Back on the network, leaders re-propose their valid block with its proof of lock
+        let report = sim.run_until(60_000);
+        assert!(report.is_safe());
+        assert!(report.min_height(&[]) >= 1);
+        let first = sim.finalized_chain("val1")[0].hash();
+        assert!(first == a.hash() || first == b.hash());
+        for addr in ["val2", "val3", "val4"] {
+            assert_eq!(sim.finalized_chain(addr)[0].hash(), first);
+        }
+    }
+
+    #[test]
+    fn test_simulator_is_deterministic_for_a_seed() {
+        let run = |seed| {
+            let mut sim = demo_simulator(seed);
+            sim.config.drop_rate_bps = 1_000;
+            let report = sim.run_until(5_000);
+            (report.finalized_heights, report.messages_sent, report.messages_dropped)
+        };
+        assert_eq!(run(7), run(7));
     }
 }
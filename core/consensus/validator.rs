@@ -1,26 +1,36 @@
 //! OCOS-Chain: Validator Management Module
 //!
 //! Provides structures and functions for validator identity, stake management,
-//! slashing, jail status, and quantum-safe key storage. Designed for secure,
-//! auditable and flexible consensus operation.
+//! slashing and jail status. A validator only carries public key material;
+//! signing happens through a `BlockSigner` (see `signer.rs`). Designed for
+//! secure, auditable and flexible consensus operation.
 
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
 use std::collections::HashMap;
 
+use crate::core::consensus::{
+    quantum_sig::{QuantumSignature, SignatureScheme},
+    signer::{sign_bytes, SignKind},
+    staking::StakingLedger,
+    ConsensusError,
+};
+
 /// Validator status (Active, Jailed, Retired)
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidatorStatus {
     Active,
     Jailed,
     Retired,
 }
 
-/// Validator main struct
-#[derive(Debug, Clone)]
+/// Validator main struct (public material only; keys to sign with live in a `BlockSigner`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Validator {
     pub address: String,
     pub stake: u64,
     pub public_key: Vec<u8>,
-    pub private_key: Vec<u8>, // For demonstration; in real, secure enclave/HSM!
+    pub scheme: SignatureScheme, // Algorithm of `public_key`
     pub status: ValidatorStatus,
     pub jailed_since: Option<u64>,
     pub metadata: Option<HashMap<String, String>>, // e.g., node info, geo, governance info
@@ -28,18 +38,39 @@ pub struct Validator {
 
 impl Validator {
     /// Create a new validator
-    pub fn new(address: String, stake: u64, public_key: Vec<u8>, private_key: Vec<u8>) -> Self {
+    pub fn new(address: String, stake: u64, public_key: Vec<u8>) -> Self {
         Validator {
             address,
             stake,
             public_key,
-            private_key,
+            scheme: SignatureScheme::default(),
             status: ValidatorStatus::Active,
             jailed_since: None,
             metadata: None,
         }
     }
 
+    /// Set the signature scheme of the validator's key (default: Ed25519);
+    /// schemes without a verified backend are refused
+    pub fn with_scheme(mut self, scheme: SignatureScheme) -> Result<Self, ConsensusError> {
+        if !scheme.is_supported() {
+            return Err(ConsensusError::UnsupportedScheme(scheme));
+        }
+        self.scheme = scheme;
+        Ok(self)
+    }
+
+    /// Verify a signature by this validator over raw bytes using its declared scheme
+    pub fn verify_signature(&self, msg: &[u8], signature: &[u8]) -> bool {
+        QuantumSignature::verify(self.scheme, &self.public_key, msg, signature)
+    }
+
+    /// Verify a consensus signature made through a `BlockSigner` for `payload`
+    /// as `kind` at (`height`, `round`)
+    pub fn verify_signed(&self, kind: SignKind, height: u64, round: u32, payload: &[u8], signature: &[u8]) -> bool {
+        self.verify_signature(&sign_bytes(kind, height, round, payload), signature)
+    }
+
     /// Slash the validator (reduce stake & set jail if needed)
     pub fn slash(&mut self, amount: u64, block_height: u64) {
         if amount >= self.stake {
@@ -51,6 +82,14 @@ impl Validator {
         }
     }
 
+    /// Jail the validator at `block_height` (removes it from the active set)
+    pub fn jail(&mut self, block_height: u64) {
+        if self.status == ValidatorStatus::Active {
+            self.status = ValidatorStatus::Jailed;
+            self.jailed_since = Some(block_height);
+        }
+    }
+
     /// Release (unjail) validator after penalty or governance decision
     pub fn unjail(&mut self) {
         if self.status == ValidatorStatus::Jailed {
@@ -95,25 +134,94 @@ impl ValidatorSet {
         self.validators.get_mut(address)
     }
 
-    /// Select validator by stake (PoS): weighted random or deterministic (simple version: round robin)
-    pub fn select_by_stake(&self, height: u64) -> Option<&Validator> {
-        // For demo: simple round robin
-        let keys: Vec<&String> = self.validators.keys().collect();
-        if keys.is_empty() {
+    /// Total stake of all active validators
+    pub fn total_stake(&self) -> u64 {
+        self.validators
+            .values()
+            .filter(|v| v.status == ValidatorStatus::Active)
+            .map(|v| v.stake)
+            .sum()
+    }
+
+    /// Voting power of a validator (zero unless active)
+    pub fn voting_power(&self, address: &str) -> u64 {
+        match self.validators.get(address) {
+            Some(v) if v.status == ValidatorStatus::Active => v.stake,
+            _ => 0,
+        }
+    }
+
+    /// Minimum stake strictly greater than 2/3 of the total active stake
+    pub fn quorum_threshold(&self) -> u64 {
+        (self.total_stake() as u128 * 2 / 3) as u64 + 1
+    }
+
+    /// Active validators in a stable order (sorted by address), identical on every node
+    pub fn sorted_active(&self) -> Vec<&Validator> {
+        let mut active: Vec<&Validator> = self.validators
+            .values()
+            .filter(|v| v.status == ValidatorStatus::Active)
+            .collect();
+        active.sort_by(|a, b| a.address.cmp(&b.address));
+        active
+    }
+
+    /// Commitment to the set's membership, keys, schemes and stake (order-independent);
+    /// headers carry it so light clients can check the validator set they are given
+    pub fn hash(&self) -> Vec<u8> {
+        let mut members: Vec<&Validator> = self.validators.values().collect();
+        members.sort_by(|a, b| a.address.cmp(&b.address));
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-VALSET");
+        for v in members {
+            hasher.update((v.address.len() as u32).to_be_bytes());
+            hasher.update(v.address.as_bytes());
+            hasher.update((v.public_key.len() as u32).to_be_bytes());
+            hasher.update(&v.public_key);
+            hasher.update([v.scheme as u8]);
+            hasher.update(v.stake.to_be_bytes());
+        }
+        hasher.finalize().to_vec()
+    }
+
+    /// Select validator by stake (PoS): stake-weighted draw seeded by the random beacon
+    pub fn select_by_stake(&self, seed: &[u8], height: u64, round: u32) -> Option<&Validator> {
+        let active = self.sorted_active();
+        let total: u64 = active.iter().map(|v| v.stake).sum();
+        if total == 0 {
             return None;
         }
-        let idx = (height as usize) % keys.len();
-        self.validators.get(keys[idx])
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(seed);
+        hasher.update(height.to_be_bytes());
+        hasher.update(round.to_be_bytes());
+        let digest = hasher.finalize();
+        let mut draw_bytes = [0u8; 16];
+        draw_bytes.copy_from_slice(&digest[..16]);
+        let mut target = (u128::from_be_bytes(draw_bytes) % total as u128) as u64;
+
+        for validator in active {
+            if target < validator.stake {
+                return Some(validator);
+            }
+            target -= validator.stake;
+        }
+        None
     }
 
-    /// Select validator by round robin (PoA)
-    pub fn select_by_round_robin(&self, height: u64) -> Option<&Validator> {
-        self.select_by_stake(height)
+    /// Select validator by round robin (PoA) over the sorted active set
+    pub fn select_by_round_robin(&self, height: u64, round: u32) -> Option<&Validator> {
+        let active = self.sorted_active();
+        if active.is_empty() {
+            return None;
+        }
+        let idx = ((height + round as u64) % active.len() as u64) as usize;
+        Some(active[idx])
     }
 
-    /// Load all validators (mock/demo)
-    pub fn load_all() -> Vec<Validator> {
-        // Placeholder: In real system, load from persistent storage or state
-        vec![]
+    /// Load the active validator set from bonded stake (top-N candidates)
+    pub fn load_all(staking: &StakingLedger) -> Vec<Validator> {
+        staking.active_set()
     }
 }
@@ -0,0 +1,89 @@
+//! OCOS-Chain: Verifiable Random Beacon
+//!
+//! Signature-based VRF used to seed leader election. The proposer of each block
+//! signs the previous beacon seed together with the block height; the hash of that
+//! signature is the next seed. Anyone holding the proposer's public key can check
+//! the proof.
+//!
+//! This is not a unique-output VRF: verification only checks that the signature is
+//! valid, and each validator picks its own scheme, so a proposer can make several
+//! valid signatures over the same input and publish the one whose output elects it.
+//! Proposers can therefore bias the beacon; it is not unbiased randomness.
+//!
+//! The proof travels in `BlockHeader::metadata`, so every node derives the same seed
+//! from the same chain.
+
+use sha3::{Digest, Sha3_256};
+
+use crate::core::consensus::ConsensusError;
+
+/// Metadata tag identifying an encoded VRF proof
+const VRF_TAG: &[u8; 4] = b"VRF1";
+const VRF_OUTPUT_LEN: usize = 32;
+
+/// VRF output and the proof it was derived from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VrfProof {
+    pub output: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+impl VrfProof {
+    /// Message signed by the proposer for a given parent seed and height
+    pub fn input(prev_seed: &[u8], height: u64) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-VRF");
+        hasher.update(prev_seed);
+        hasher.update(height.to_be_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    /// Produce a proof using the proposer's signing function (e.g. a `BlockSigner`);
+    /// any valid signature is accepted, so the output is not unique per key and input
+    pub fn prove<F>(sign_fn: F, input: &[u8]) -> Result<Self, ConsensusError>
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, ConsensusError>,
+    {
+        let proof = sign_fn(input)?;
+        Ok(VrfProof {
+            output: Self::output_from_proof(&proof),
+            proof,
+        })
+    }
+
+    /// Check the proof against the proposer's key and the output it claims
+    pub fn verify<F>(&self, verify_fn: F, public_key: &[u8], input: &[u8]) -> bool
+    where
+        F: Fn(&[u8], &[u8], &[u8]) -> bool,
+    {
+        verify_fn(public_key, input, &self.proof) && self.output == Self::output_from_proof(&self.proof)
+    }
+
+    /// Encode as header metadata: tag || output || proof
+    pub fn to_metadata(&self) -> Vec<u8> {
+        let mut meta = Vec::with_capacity(VRF_TAG.len() + self.output.len() + self.proof.len());
+        meta.extend_from_slice(VRF_TAG);
+        meta.extend_from_slice(&self.output);
+        meta.extend_from_slice(&self.proof);
+        meta
+    }
+
+    /// Decode from header metadata (None if absent or malformed)
+    pub fn from_metadata(meta: &[u8]) -> Option<Self> {
+        if meta.len() < VRF_TAG.len() + VRF_OUTPUT_LEN || &meta[..VRF_TAG.len()] != VRF_TAG {
+            return None;
+        }
+        let body = &meta[VRF_TAG.len()..];
+        Some(VrfProof {
+            output: body[..VRF_OUTPUT_LEN].to_vec(),
+            proof: body[VRF_OUTPUT_LEN..].to_vec(),
+        })
+    }
+
+    fn output_from_proof(proof: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-VRF-OUT");
+        hasher.update(proof);
+        hasher.finalize().to_vec()
+    }
+}
@@ -26,6 +26,16 @@ pub enum AesError {
 
 /// Encrypt data using AES-256-GCM
 pub fn aes_encrypt(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+    aes_encrypt_with_aad(key, nonce, plaintext, b"")
+}
+
+/// Decrypt data using AES-256-GCM
+pub fn aes_decrypt(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    aes_decrypt_with_aad(key, nonce, ciphertext, b"")
+}
+
+/// Encrypt data using AES-256-GCM, authenticating `aad` alongside it (not encrypted)
+pub fn aes_encrypt_with_aad(key: &[u8], nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AesError> {
     if key.len() != AES_KEY_LEN || nonce.len() != AES_NONCE_LEN {
         return Err(AesError::InvalidInput);
     }
@@ -34,12 +44,12 @@ pub fn aes_encrypt(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>
     let nonce = Nonce::from_slice(nonce);
 
     cipher
-        .encrypt(nonce, Payload { msg: plaintext, aad: b"" })
+        .encrypt(nonce, Payload { msg: plaintext, aad })
         .map_err(|_| AesError::EncryptError)
 }
 
-/// Decrypt data using AES-256-GCM
-pub fn aes_decrypt(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+/// Decrypt data using AES-256-GCM; fails unless `aad` matches the one used to encrypt
+pub fn aes_decrypt_with_aad(key: &[u8], nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AesError> {
     if key.len() != AES_KEY_LEN || nonce.len() != AES_NONCE_LEN {
         return Err(AesError::InvalidInput);
     }
@@ -48,6 +58,6 @@ pub fn aes_decrypt(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8
     let nonce = Nonce::from_slice(nonce);
 
     cipher
-        .decrypt(nonce, Payload { msg: ciphertext, aad: b"" })
+        .decrypt(nonce, Payload { msg: ciphertext, aad })
         .map_err(|_| AesError::DecryptError)
 }
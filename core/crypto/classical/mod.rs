@@ -14,4 +14,4 @@ pub mod aes_gcm;
 
 pub use ed25519::{Ed25519Keypair, ed25519_sign, ed25519_verify};
 pub use secp256k1::{Secp256k1Keypair, secp_sign, secp_verify};
-pub use aes_gcm::{aes_encrypt, aes_decrypt};
+pub use aes_gcm::{aes_encrypt, aes_decrypt, aes_encrypt_with_aad, aes_decrypt_with_aad};
@@ -15,7 +15,7 @@ pub mod utils;
 pub use classical::{
     ed25519::{Ed25519Keypair, ed25519_sign, ed25519_verify},
     secp256k1::{Secp256k1Keypair, secp_sign, secp_verify},
-    aes_gcm::{aes_encrypt, aes_decrypt},
+    aes_gcm::{aes_encrypt, aes_decrypt, aes_encrypt_with_aad, aes_decrypt_with_aad},
 };
 
 pub use quantum::{
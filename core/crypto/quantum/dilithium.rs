@@ -4,11 +4,10 @@
 //! digital signature scheme (NIST PQC finalist). Designed to support both
 //! stateless secure signing and quantum-safe verification across DAO and ledger layers.
 //!
-//! Placeholder implementation – production use requires binding to a PQ-safe library
-//! such as `pqcrypto-dilithium`, `liboqs`, or `OpenQuantumSafe`.
+//! Backed by `pqcrypto-dilithium` (Dilithium3 parameter set, detached signatures).
 
-use sha3::{Sha3_512, Digest};
-use rand_core::{OsRng, RngCore};
+use pqcrypto_dilithium::dilithium3;
+use pqcrypto_traits::sign::{DetachedSignature, PublicKey, SecretKey};
 use crate::crypto::quantum::QuantumCryptoError;
 
 /// Keypair structure for Dilithium
@@ -19,37 +18,35 @@ pub struct DilithiumKeypair {
 }
 
 impl DilithiumKeypair {
-    /// Generate a new Dilithium keypair (placeholder logic)
+    /// Generate a new Dilithium3 keypair
     pub fn generate() -> Result<Self, QuantumCryptoError> {
-        // Simulated key lengths (real impl: ~1.5 KB pub, ~3 KB priv)
-        let mut public = vec![0u8; 1472];  // Example: Dilithium3 public key size
-        let mut private = vec![0u8; 3504]; // Example: Dilithium3 private key size
-        OsRng.fill_bytes(&mut public);
-        OsRng.fill_bytes(&mut private);
-        Ok(Self { public, private })
+        let (pk, sk) = dilithium3::keypair();
+        Ok(Self {
+            public: pk.as_bytes().to_vec(),
+            private: sk.as_bytes().to_vec(),
+        })
     }
 
-    /// Derive public key from private (not implemented in placeholder)
+    /// Derive public key from private (not supported by the underlying library)
     pub fn from_private(_sk: &[u8]) -> Result<Self, QuantumCryptoError> {
         Err(QuantumCryptoError::UnsupportedAlgorithm)
     }
 }
 
-/// Sign a message using Dilithium (placeholder, not secure)
-pub fn dilithium_sign(private_key: &[u8], message: &[u8]) -> Vec<u8> {
-    // ⚠️ Placeholder: Use real Dilithium sign() in production
-    let mut hasher = Sha3_512::new();
-    hasher.update(private_key);
-    hasher.update(message);
-    hasher.finalize()[..256].to_vec() // Simulated signature
+/// Sign a message using Dilithium3 (detached signature)
+pub fn dilithium_sign(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>, QuantumCryptoError> {
+    let sk = dilithium3::SecretKey::from_bytes(private_key).map_err(|_| QuantumCryptoError::InvalidKey)?;
+    Ok(dilithium3::detached_sign(message, &sk).as_bytes().to_vec())
 }
 
-/// Verify a Dilithium signature (placeholder logic)
+/// Verify a Dilithium3 detached signature
 pub fn dilithium_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
-    // ⚠️ Placeholder: Use real Dilithium verify() in production
-    let mut hasher = Sha3_512::new();
-    hasher.update(public_key);
-    hasher.update(message);
-    let expected = &hasher.finalize()[..256];
-    &signature[..] == expected
+    let (pk, sig) = match (
+        dilithium3::PublicKey::from_bytes(public_key),
+        dilithium3::DetachedSignature::from_bytes(signature),
+    ) {
+        (Ok(pk), Ok(sig)) => (pk, sig),
+        _ => return false,
+    };
+    dilithium3::verify_detached_signature(&sig, message, &pk).is_ok()
 }
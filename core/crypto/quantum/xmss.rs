@@ -5,6 +5,9 @@
 //!
 //! Suitable for logging, DAO voting trails, validator election proofs, and long-lived identities.
 //! Note: XMSS is stateful – must track signature usage count (OTS index) securely.
+//!
+//! ⚠️ Signing is simulated and verification is not implemented: `xmss_verify`
+//! rejects every signature until a real WOTS+ / Merkle tree backend is wired in.
 
 use sha2::{Sha256, Digest};
 use rand_core::{OsRng, RngCore};
@@ -46,14 +49,12 @@ pub fn xmss_sign(kp: &mut XmssKeypair, message: &[u8]) -> Vec<u8> {
     hasher.update(&message);
     hasher.update(&kp.ots_index.to_be_bytes());
     kp.ots_index += 1; // move forward in the tree
-    hasher.finalize()[..48].to_vec()
+    hasher.finalize().to_vec()
 }
 
-/// Simulated XMSS verification
-pub fn xmss_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
-    // ⚠️ Placeholder logic – in real XMSS, full Merkle tree path verification is needed
-    let mut hasher = Sha256::new();
-    hasher.update(public_key);
-    hasher.update(message);
-    hasher.finalize()[..48].to_vec() == signature
+/// XMSS verification (not implemented: rejects every signature)
+pub fn xmss_verify(_public_key: &[u8], _message: &[u8], _signature: &[u8]) -> bool {
+    // ⚠️ Real XMSS needs WOTS+ and Merkle auth path verification; accepting anything
+    // less would let anyone forge signatures for an XMSS key.
+    false
 }
@@ -0,0 +1,34 @@
+//! OCOS-Chain: Ledger Block Module
+//!
+//! Ledger-side view of a block: the canonical consensus header together with
+//! the decoded, signed transactions executed against ledger state.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::transaction::SignedTransaction;
+
+pub use crate::consensus::block::BlockHeader;
+
+/// Block as stored and executed by the ledger
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<SignedTransaction>,
+}
+
+impl Block {
+    /// Block hash = header hash (chain standard)
+    pub fn hash(&self) -> Vec<u8> {
+        self.header.hash()
+    }
+
+    /// Hash of the parent block
+    pub fn parent_hash(&self) -> &[u8] {
+        &self.header.parent_hash
+    }
+
+    /// Block height
+    pub fn height(&self) -> u64 {
+        self.header.height
+    }
+}
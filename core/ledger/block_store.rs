@@ -2,93 +2,315 @@
 //!
 //! Responsible for efficient and auditable storage and retrieval of blocks,
 //! supports persistence, indexing, rollback, and chain reorg operations.
+//!
+//! Blocks are kept as a tree indexed by hash with parent links, so competing
+//! blocks at the same height coexist until fork choice picks one. A separate
+//! height index tracks the canonical chain.
+//!
+//! Finalized blocks are written to the key-value store (`write_block`) and read
+//! back on startup (`load`); unfinalized branches live in memory only.
+//!
+//! The store remembers its finalized tip. Ancestry walks go lazily from the
+//! newest block and stop as soon as they reach what they look for, so fork
+//! choice only visits the blocks above the tip; finalizing a block drops the
+//! side branches between the old tip and the new one.
 
 use crate::ledger::block::Block;
-use std::collections::HashMap;
+use crate::ledger::kv_store::{Column, KvError, KvStore, WriteBatch};
+use std::collections::{BTreeMap, HashMap};
+
+/// Block with its position in the block tree
+#[derive(Debug, Clone)]
+pub struct StoredBlock {
+    pub block: Block,
+    pub hash: Vec<u8>,
+    pub parent_hash: Vec<u8>,
+    pub height: u64,
+    pub finalized: bool,
+}
 
 /// BlockStore struct: main persistent storage for all blocks
 #[derive(Debug, Default)]
 pub struct BlockStore {
-    /// Block height → Block
-    pub blocks_by_height: HashMap<u64, Block>,
-    /// Block hash → Block height
-    pub height_by_hash: HashMap<Vec<u8>, u64>,
+    /// Block hash → stored block (all known branches)
+    pub blocks: HashMap<Vec<u8>, StoredBlock>,
+    /// Parent hash → child block hashes
+    pub children: HashMap<Vec<u8>, Vec<Vec<u8>>>,
+    /// Block height → canonical block hash
+    pub canonical: BTreeMap<u64, Vec<u8>>,
+    /// Hash of the highest finalized block
+    pub finalized_tip: Option<Vec<u8>>,
 }
 
 impl BlockStore {
     /// Create a new, empty block store
     pub fn new() -> Self {
         Self {
-            blocks_by_height: HashMap::new(),
-            height_by_hash: HashMap::new(),
+            blocks: HashMap::new(),
+            children: HashMap::new(),
+            canonical: BTreeMap::new(),
+            finalized_tip: None,
+        }
+    }
+
+    /// Rebuild the finalized canonical chain from the store
+    pub fn load(store: &dyn KvStore) -> Result<Self, KvError> {
+        let mut blocks = Self::new();
+        let mut last = None;
+        for (height, hash) in store.iter(Column::Canonical)? {
+            let height = u64::from_be_bytes(height.as_slice().try_into()
+                .map_err(|_| KvError::corrupted(Column::Canonical, "bad height key"))?);
+            let bytes = store.get(Column::Blocks, &hash)?
+                .ok_or_else(|| KvError::corrupted(Column::Blocks, "missing canonical block"))?;
+            let block: Block = bincode::deserialize(&bytes)
+                .map_err(|e| KvError::corrupted(Column::Blocks, e))?;
+            blocks.insert_block(block, hash.clone(), height);
+            last = Some(hash);
+        }
+        if let Some(hash) = last {
+            blocks.mark_finalized(&hash);
         }
+        Ok(blocks)
+    }
+
+    /// Queue a stored block and its canonical index entry for the store
+    pub fn write_block(&self, hash: &[u8], batch: &mut WriteBatch) -> Result<(), KvError> {
+        let stored = self.blocks.get(hash)
+            .ok_or_else(|| KvError::corrupted(Column::Blocks, "unknown block"))?;
+        let bytes = bincode::serialize(&stored.block).map_err(|e| KvError::corrupted(Column::Blocks, e))?;
+        batch.put(Column::Blocks, hash.to_vec(), bytes);
+        batch.put(Column::Canonical, stored.height.to_be_bytes().to_vec(), hash.to_vec());
+        Ok(())
     }
 
-    /// Insert a block (returns true if new, false if replaced)
+    /// Insert a block (returns true if new, false if already known).
+    /// The block joins the canonical chain only if it extends the canonical tip.
     pub fn insert_block(&mut self, block: Block, block_hash: Vec<u8>, height: u64) -> bool {
-        let is_new = !self.blocks_by_height.contains_key(&height);
-        self.blocks_by_height.insert(height, block);
-        self.height_by_hash.insert(block_hash, height);
-        is_new
+        if self.blocks.contains_key(&block_hash) {
+            return false;
+        }
+        let parent_hash = block.header.parent_hash.clone();
+        let extends_tip = match self.canonical.iter().next_back() {
+            None => true,
+            Some((&tip_height, tip_hash)) => tip_height + 1 == height && tip_hash == &parent_hash,
+        };
+
+        self.children.entry(parent_hash.clone()).or_default().push(block_hash.clone());
+        if extends_tip {
+            self.canonical.insert(height, block_hash.clone());
+        }
+        self.blocks.insert(block_hash.clone(), StoredBlock {
+            block,
+            hash: block_hash,
+            parent_hash,
+            height,
+            finalized: false,
+        });
+        true
     }
 
-    /// Get block by height
+    /// Get canonical block by height
     pub fn get_block_by_height(&self, height: u64) -> Option<&Block> {
-        self.blocks_by_height.get(&height)
+        self.canonical
+            .get(&height)
+            .and_then(|hash| self.get_block_by_hash(hash))
     }
 
-    /// Get block by hash
+    /// Get block by hash (any branch)
     pub fn get_block_by_hash(&self, hash: &[u8]) -> Option<&Block> {
-        if let Some(height) = self.height_by_hash.get(hash) {
-            self.blocks_by_height.get(height)
-        } else {
-            None
+        self.blocks.get(hash).map(|stored| &stored.block)
+    }
+
+    /// Get block with tree metadata by hash
+    pub fn get_stored(&self, hash: &[u8]) -> Option<&StoredBlock> {
+        self.blocks.get(hash)
+    }
+
+    /// All known blocks at a height, across branches
+    pub fn blocks_at_height(&self, height: u64) -> Vec<&StoredBlock> {
+        let mut at_height: Vec<&StoredBlock> = self.blocks.values().filter(|b| b.height == height).collect();
+        at_height.sort_by(|a, b| a.hash.cmp(&b.hash));
+        at_height
+    }
+
+    /// Child block hashes of a block
+    pub fn children_of(&self, hash: &[u8]) -> &[Vec<u8>] {
+        self.children.get(hash).map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    /// Blocks without children (heads of every branch); with a finalized tip,
+    /// only the blocks built on it are visited
+    pub fn leaves(&self) -> Vec<&StoredBlock> {
+        let Some(tip) = &self.finalized_tip else {
+            return self.blocks
+                .values()
+                .filter(|b| self.children_of(&b.hash).is_empty())
+                .collect();
+        };
+        let mut leaves = vec![];
+        let mut frontier = vec![tip.as_slice()];
+        while let Some(hash) = frontier.pop() {
+            match self.children_of(hash) {
+                [] => leaves.extend(self.blocks.get(hash)),
+                children => frontier.extend(children.iter().map(|c| c.as_slice())),
+            }
         }
+        leaves
     }
 
-    /// Remove block by height
-    pub fn remove_block_by_height(&mut self, height: u64) -> Option<Block> {
-        if let Some(block) = self.blocks_by_height.remove(&height) {
-            // Remove from hash index as well
-            let hash_to_remove = self.height_by_hash.iter()
-                .find(|(_, &h)| h == height)
-                .map(|(k, _)| k.clone());
-            if let Some(hash) = hash_to_remove {
-                self.height_by_hash.remove(&hash);
+    /// Stored blocks from `hash` back towards the oldest stored ancestor
+    /// (inclusive, newest first), read one parent at a time
+    pub fn ancestors<'a>(&'a self, hash: &[u8]) -> impl Iterator<Item = &'a StoredBlock> + 'a {
+        std::iter::successors(self.blocks.get(hash), move |stored| self.blocks.get(&stored.parent_hash))
+    }
+
+    /// Hashes from `hash` back to the oldest stored ancestor (inclusive, newest first)
+    pub fn ancestry(&self, hash: &[u8]) -> Vec<Vec<u8>> {
+        self.ancestors(hash).map(|stored| stored.hash.clone()).collect()
+    }
+
+    /// Deepest block that is an ancestor of (or equal to) both `a` and `b`;
+    /// both paths are walked back only down to that block
+    pub fn common_ancestor(&self, a: &[u8], b: &[u8]) -> Option<Vec<u8>> {
+        let (mut a, mut b) = (self.blocks.get(a)?, self.blocks.get(b)?);
+        while a.hash != b.hash {
+            if a.height >= b.height {
+                a = self.blocks.get(&a.parent_hash)?;
+            } else {
+                b = self.blocks.get(&b.parent_hash)?;
+            }
+        }
+        Some(a.hash.clone())
+    }
+
+    /// True if `descendant` is `ancestor` or builds on it (walks no lower than `ancestor`)
+    pub fn is_descendant(&self, descendant: &[u8], ancestor: &[u8]) -> bool {
+        let Some(target) = self.blocks.get(ancestor) else {
+            return false;
+        };
+        self.ancestors(descendant)
+            .take_while(|b| b.height >= target.height)
+            .any(|b| b.hash == ancestor)
+    }
+
+    /// Make the branch ending at `head` canonical
+    pub fn set_canonical_head(&mut self, head: &[u8]) -> bool {
+        let Some(head_height) = self.blocks.get(head).map(|b| b.height) else {
+            return false;
+        };
+        self.canonical.split_off(&(head_height + 1));
+        let branch: Vec<(u64, Vec<u8>)> = self.ancestors(head)
+            .take_while(|b| self.canonical.get(&b.height) != Some(&b.hash))
+            .map(|b| (b.height, b.hash.clone()))
+            .collect();
+        self.canonical.extend(branch);
+        true
+    }
+
+    /// Mark a block and its not yet final ancestors final, and drop the side
+    /// branches that fork off between them (everything conflicting with it)
+    pub fn mark_finalized(&mut self, hash: &[u8]) -> bool {
+        let Some(height) = self.blocks.get(hash).map(|b| b.height) else {
+            return false;
+        };
+        let newly_final: Vec<Vec<u8>> = self.ancestors(hash)
+            .take_while(|b| !b.finalized)
+            .map(|b| b.hash.clone())
+            .collect();
+        for h in &newly_final {
+            let Some(stored) = self.blocks.get_mut(h) else {
+                continue;
+            };
+            stored.finalized = true;
+            let parent_hash = stored.parent_hash.clone();
+            let siblings: Vec<Vec<u8>> = self.children_of(&parent_hash)
+                .iter()
+                .filter(|c| *c != h)
+                .cloned()
+                .collect();
+            for sibling in siblings {
+                self.remove_branch(&sibling);
             }
-            Some(block)
-        } else {
-            None
         }
+        if !matches!(self.latest_finalized(), Some(tip) if tip.height >= height) {
+            self.finalized_tip = Some(hash.to_vec());
+        }
+        true
+    }
+
+    /// Highest finalized block, if any
+    pub fn latest_finalized(&self) -> Option<&StoredBlock> {
+        self.finalized_tip.as_ref().and_then(|hash| self.blocks.get(hash))
+    }
+
+    /// Remove a block and every block built on it
+    pub fn remove_branch(&mut self, hash: &[u8]) {
+        let mut branch = vec![hash.to_vec()];
+        let mut next = 0;
+        while next < branch.len() {
+            let children = self.children_of(&branch[next]).to_vec();
+            branch.extend(children);
+            next += 1;
+        }
+        for h in branch {
+            self.remove_block_by_hash(&h);
+        }
+    }
+
+    /// Remove a single block from every index
+    pub fn remove_block_by_hash(&mut self, hash: &[u8]) -> Option<Block> {
+        let stored = self.blocks.remove(hash)?;
+        if let Some(siblings) = self.children.get_mut(&stored.parent_hash) {
+            siblings.retain(|h| h != hash);
+            if siblings.is_empty() {
+                self.children.remove(&stored.parent_hash);
+            }
+        }
+        if self.canonical.get(&stored.height).map(|h| h.as_slice()) == Some(hash) {
+            self.canonical.remove(&stored.height);
+        }
+        if self.finalized_tip.as_deref() == Some(hash) {
+            self.finalized_tip = self.blocks
+                .get(&stored.parent_hash)
+                .filter(|parent| parent.finalized)
+                .map(|parent| parent.hash.clone());
+        }
+        Some(stored.block)
+    }
+
+    /// Remove canonical block by height
+    pub fn remove_block_by_height(&mut self, height: u64) -> Option<Block> {
+        let hash = self.canonical.get(&height)?.clone();
+        self.remove_block_by_hash(&hash)
     }
 
-    /// Get the latest block (by highest height)
+    /// Get the latest canonical block (by highest height)
     pub fn get_latest_block(&self) -> Option<&Block> {
-        self.blocks_by_height
-            .keys()
-            .max()
-            .and_then(|max_height| self.blocks_by_height.get(max_height))
+        self.canonical
+            .values()
+            .next_back()
+            .and_then(|hash| self.get_block_by_hash(hash))
     }
 
-    /// Rollback to a given block height (removes all blocks above height)
+    /// Rollback to a given block height (removes all blocks above height, on every branch)
     pub fn rollback_to_height(&mut self, height: u64) {
-        let heights_to_remove: Vec<u64> = self.blocks_by_height
-            .keys()
-            .cloned()
-            .filter(|&h| h > height)
+        let hashes_to_remove: Vec<Vec<u8>> = self.blocks
+            .values()
+            .filter(|b| b.height > height)
+            .map(|b| b.hash.clone())
             .collect();
-        for h in heights_to_remove {
-            self.remove_block_by_height(h);
+        for hash in hashes_to_remove {
+            self.remove_block_by_hash(&hash);
         }
     }
 
     /// Returns the number of stored blocks
     pub fn len(&self) -> usize {
-        self.blocks_by_height.len()
+        self.blocks.len()
     }
 
     /// Checks if the store is empty
     pub fn is_empty(&self) -> bool {
-        self.blocks_by_height.is_empty()
+        self.blocks.is_empty()
     }
 }
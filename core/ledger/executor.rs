@@ -2,48 +2,166 @@
 //!
 //! Responsible for deterministic execution of blocks and transactions,
 //! state transition application, gas accounting, and receipt generation.
+//! Every transaction is verified before it touches state and runs in its own
+//! checkpoint; large blocks run in parallel (see `parallel`) with the same result.
 
-use crate::ledger::{block::Block, transaction::SignedTransaction, state::{State, AccountState, StateUpdate}, receipt::{Receipt, EventLog}};
-use std::collections::VecDeque;
+use crate::consensus::block::{BlockLimits, ExecutionSummary};
+use crate::consensus::rewards::{ledger_account, RewardDistribution, RewardDistributor};
+use crate::consensus::staking::UnbondingEntry;
+use crate::ledger::{block::Block, transaction::{address_of, SignedTransaction, TransactionError}, state::{State, AccountState, StateUpdate}, receipt::{Bloom, Receipt, EventLog}};
+use crate::ledger::parallel::{self, TxOutcome};
 
+/// Outcome of a block. `state_update` is read from the state journal, so it lists the
+/// net change of every account and storage key; `state_root` is the authenticated root
+/// of the post-block state, committed when the block ends.
 #[derive(Debug)]
 pub struct ExecutionResult {
     pub state_update: StateUpdate,
     pub receipts: Vec<Receipt>,
+    pub logs_bloom: Bloom,                     // Bloom over the logs of all receipts
     pub gas_used: u64,
+    pub fees_collected: u128,                  // Gas charged to senders in this block (burned + tips)
+    pub fees_burned: u128,                     // Base-fee portion, removed from supply
+    pub tips: u128,                            // Priority fees, paid to the proposer's key account
+    pub rewards: Option<RewardDistribution>,   // Payout record (when a distributor was used)
+    pub state_root: Vec<u8>,                   // State trie root after the block
     pub errors: Vec<ExecutionError>,
 }
 
+impl ExecutionResult {
+    /// What consensus needs from the execution: gas for the header, root for the next one.
+    /// A proposer hands it to the consensus engine before signing, so the header's
+    /// `gas_used` and `logs_bloom` match the execution; `Chain::import_block` re-executes
+    /// each block and rejects a header with either field different.
+    pub fn summary(&self) -> ExecutionSummary {
+        ExecutionSummary {
+            gas_used: self.gas_used,
+            state_root: self.state_root.clone(),
+            logs_bloom: self.logs_bloom.as_bytes().to_vec(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ExecutionError {
     InvalidSignature,
+    WrongChainId,
+    UnknownAccount,
     InsufficientBalance,
     InvalidNonce,
+    FeeCapBelowBaseFee,
+    BlockGasLimitReached,
+    BalanceOverflow,
     ContractError(String),
     Unknown,
 }
 
+impl From<TransactionError> for ExecutionError {
+    fn from(err: TransactionError) -> Self {
+        match err {
+            TransactionError::WrongChainId { .. } => ExecutionError::WrongChainId,
+            _ => ExecutionError::InvalidSignature,
+        }
+    }
+}
+
+/// Account reads and writes made by transaction logic; implemented by `State`
+/// and by the speculative views of parallel execution
+pub trait AccountAccess {
+    fn account(&mut self, address: &[u8]) -> Option<AccountState>;
+    fn set_account(&mut self, address: &[u8], account: AccountState);
+}
+
+impl AccountAccess for State {
+    fn account(&mut self, address: &[u8]) -> Option<AccountState> {
+        self.get_account(address).cloned()
+    }
+
+    fn set_account(&mut self, address: &[u8], account: AccountState) {
+        self.update_account(address.to_vec(), account);
+    }
+}
+
 /// Executor: Processes blocks & txs, applies results to state
 pub struct Executor;
 
 impl Executor {
-    /// Execute a block (returns cumulative result)
+    /// Execute a block for chain `chain_id` under the default gas cap (returns cumulative result)
     pub fn execute_block(
         state: &mut State,
         block: &Block,
+        chain_id: u64,
+    ) -> ExecutionResult {
+        Self::run_block(state, block, chain_id, BlockLimits::default().max_block_gas, None)
+    }
+
+    /// Execute a block, then pay its fees and issuance out according to `distributor`
+    pub fn execute_block_with_rewards(
+        state: &mut State,
+        block: &Block,
+        chain_id: u64,
+        distributor: &RewardDistributor,
+    ) -> ExecutionResult {
+        Self::run_block(state, block, chain_id, BlockLimits::default().max_block_gas, Some(distributor))
+    }
+
+    /// Execute a block under the chain's current `max_block_gas`, paying rewards when a distributor is set.
+    /// Transactions may reserve at most `max_block_gas` between them (by gas limit, in block
+    /// order); the ones past the cap are not executed and are reported as `BlockGasLimitReached`.
+    pub fn execute_block_capped(
+        state: &mut State,
+        block: &Block,
+        chain_id: u64,
+        max_block_gas: u64,
+        distributor: Option<&RewardDistributor>,
+    ) -> ExecutionResult {
+        Self::run_block(state, block, chain_id, max_block_gas, distributor)
+    }
+
+    /// Run every transaction that fits the gas cap in its own checkpoint (reverted
+    /// on failure), pay rewards, then commit the whole block at once. Gas is charged at
+    /// the EIP-1559 effective price: the base-fee portion is burned and the tips are
+    /// credited in full to `address_of(proposer_public_key)`; a distributor additionally
+    /// pays the block's issuance to the proposer, treasury, validators and delegators.
+    fn run_block(
+        state: &mut State,
+        block: &Block,
+        chain_id: u64,
+        max_block_gas: u64,
+        distributor: Option<&RewardDistributor>,
     ) -> ExecutionResult {
-        let mut state_update = StateUpdate { account_deltas: vec![], storage_deltas: vec![] };
+        let block_checkpoint = state.checkpoint();
         let mut receipts = vec![];
         let mut gas_used = 0;
+        let (mut fees_burned, mut tips) = (0u128, 0u128);
         let mut errors = vec![];
+        let base_fee = block.header.base_fee_per_gas;
 
-        // Example: iterate all transactions in the block
-        for tx in &block.transactions {
-            match Self::execute_transaction(state, tx) {
-                Ok((delta, receipt, gas)) => {
-                    state_update.account_deltas.push((tx.sender.clone(), delta));
+        let mut reserved = 0u64;
+        let fitting = block.transactions
+            .iter()
+            .take_while(|tx| {
+                reserved = reserved.saturating_add(tx.tx.gas_limit);
+                reserved <= max_block_gas
+            })
+            .count();
+        let (included, excluded) = block.transactions.split_at(fitting);
+
+        let outcomes: Vec<TxOutcome> = match parallel::worker_count(included.len()) {
+            1 => included
+                .iter()
+                .map(|tx| Self::execute_transaction(state, tx, chain_id, base_fee).map(|(_, receipt, gas)| (receipt, gas)))
+                .collect(),
+            workers => parallel::execute_transactions(state, included, chain_id, base_fee, workers),
+        };
+
+        for (tx, outcome) in included.iter().zip(outcomes) {
+            match outcome {
+                Ok((receipt, gas)) => {
                     receipts.push(receipt);
                     gas_used += gas;
+                    fees_burned += gas as u128 * base_fee;
+                    tips += gas as u128 * tx.tx.priority_fee(base_fee).unwrap_or(0);
                 }
                 Err(err) => {
                     errors.push(err);
@@ -51,53 +169,152 @@ impl Executor {
             }
         }
 
+        errors.extend(excluded.iter().map(|_| ExecutionError::BlockGasLimitReached));
+
+        // Tips belong to the proposer alone, with or without a distributor
+        if tips > 0 {
+            if let Err(err) = Self::credit(state, &address_of(&block.header.proposer_public_key), tips) {
+                errors.push(err);
+            }
+        }
+        let rewards = match distributor {
+            Some(distributor) => {
+                let distribution = distributor.distribute(block.height(), &block.header.proposer_public_key);
+                if let Err(err) = Self::apply_rewards(state, &distribution) {
+                    errors.push(err);
+                }
+                Some(distribution)
+            }
+            None => None,
+        };
+
+        let state_update = state.changes_since(block_checkpoint);
+        state.release(block_checkpoint);
         ExecutionResult {
             state_update,
+            logs_bloom: Bloom::from_receipts(&receipts),
             receipts,
             gas_used,
+            fees_collected: fees_burned + tips,
+            fees_burned,
+            tips,
+            rewards,
+            state_root: state.commit(),
             errors,
         }
     }
 
-    /// Execute a single transaction (returns account delta, receipt, gas used)
+    /// Credit every payout of a distribution to ledger accounts (created on demand);
+    /// all or none of the payouts are applied
+    pub fn apply_rewards(state: &mut State, distribution: &RewardDistribution) -> Result<(), ExecutionError> {
+        let checkpoint = state.checkpoint();
+        for (recipient, amount) in distribution.payouts() {
+            if let Err(err) = Self::credit(state, &ledger_account(&recipient), amount) {
+                state.revert_to(checkpoint);
+                return Err(err);
+            }
+        }
+        state.release(checkpoint);
+        Ok(())
+    }
+
+    /// Move a staking bond out of the owner's ledger account into escrow
+    pub fn escrow_bond(state: &mut State, owner: &str, amount: u64) -> Result<(), ExecutionError> {
+        let address = ledger_account(owner);
+        let mut account = state.account(&address).ok_or(ExecutionError::UnknownAccount)?;
+        account.balance = account.balance
+            .checked_sub(amount as u128)
+            .ok_or(ExecutionError::InsufficientBalance)?;
+        state.set_account(&address, account);
+        Ok(())
+    }
+
+    /// Credit matured unbonding back to the delegators' ledger accounts;
+    /// all or none of the entries are applied
+    pub fn release_unbonding(state: &mut State, entries: &[UnbondingEntry]) -> Result<(), ExecutionError> {
+        let checkpoint = state.checkpoint();
+        for entry in entries {
+            if let Err(err) = Self::credit(state, &ledger_account(&entry.delegator), entry.amount as u128) {
+                state.revert_to(checkpoint);
+                return Err(err);
+            }
+        }
+        state.release(checkpoint);
+        Ok(())
+    }
+
+    fn credit<S: AccountAccess>(state: &mut S, address: &[u8], amount: u128) -> Result<(), ExecutionError> {
+        let account = match state.account(address) {
+            Some(mut account) => {
+                account.balance = account.balance.checked_add(amount).ok_or(ExecutionError::BalanceOverflow)?;
+                account
+            }
+            None => AccountState::new(amount),
+        };
+        state.set_account(address, account);
+        Ok(())
+    }
+
+    /// Execute a single transaction in a block with `base_fee` (returns its state
+    /// changes, receipt, gas used). A failed transaction leaves the state untouched.
     pub fn execute_transaction(
         state: &mut State,
         tx: &SignedTransaction,
-    ) -> Result<(AccountDelta, Receipt, u64), ExecutionError> {
+        chain_id: u64,
+        base_fee: u128,
+    ) -> Result<(StateUpdate, Receipt, u64), ExecutionError> {
+        // Chain id, sender key and signature must check out before any state access
+        tx.verify(chain_id)?;
+
+        let checkpoint = state.checkpoint();
+        match Self::apply_transaction(state, tx, base_fee) {
+            Ok((receipt, gas)) => {
+                let changes = state.changes_since(checkpoint);
+                state.release(checkpoint);
+                Ok((changes, receipt, gas))
+            }
+            Err(err) => {
+                state.revert_to(checkpoint);
+                Err(err)
+            }
+        }
+    }
+
+    /// Transfer logic of a verified transaction; may fail after partial writes
+    pub(crate) fn apply_transaction<S: AccountAccess>(state: &mut S, tx: &SignedTransaction, base_fee: u128) -> Result<(Receipt, u64), ExecutionError> {
         // Simple transfer logic for demo
         let sender_addr = &tx.sender;
-        let recipient_addr = &tx.recipient;
-        let amount = tx.amount;
-        let gas = tx.gas_limit;
-
-        // Check signature (pseudo)
-        // if !verify_sig(tx) { return Err(ExecutionError::InvalidSignature); }
+        let recipient_addr = &tx.tx.to;
+        let amount = tx.tx.value;
+        let gas = tx.tx.gas_limit;
+        let price = tx.tx.effective_gas_price(base_fee).ok_or(ExecutionError::FeeCapBelowBaseFee)?;
+        // The sender must afford the full fee cap; only the effective price is charged
+        let max_cost = tx.tx.max_fee()
+            .and_then(|fee| fee.checked_add(amount))
+            .ok_or(ExecutionError::InsufficientBalance)?;
+        let cost = gas as u128 * price + amount;
 
         // Check sender account
-        let sender_account = state.get_account_mut(sender_addr)
-            .ok_or(ExecutionError::InvalidSignature)?;
+        let mut sender_account = state.account(sender_addr)
+            .ok_or(ExecutionError::UnknownAccount)?;
 
-        if sender_account.balance < amount + gas as u128 {
+        if sender_account.balance < max_cost {
             return Err(ExecutionError::InsufficientBalance);
         }
 
-        if tx.nonce != sender_account.nonce + 1 {
+        if tx.nonce() != sender_account.nonce + 1 {
             return Err(ExecutionError::InvalidNonce);
         }
 
         // Update sender
-        sender_account.balance -= amount + gas as u128;
+        sender_account.balance -= cost;
         sender_account.nonce += 1;
+        state.set_account(sender_addr, sender_account);
 
         // Update recipient
-        let recipient_account = state.get_account_mut(recipient_addr)
-            .unwrap_or_else(|| {
-                state.update_account(recipient_addr.clone(), AccountState::new(0));
-                state.get_account_mut(recipient_addr).unwrap()
-            });
-        recipient_account.balance += amount;
+        Self::credit(state, recipient_addr, amount)?;
 
-        // (Smart contract calls, event logs, fee burning, etc. can be added here.)
+        // Base fee is burned and tips are paid out by `execute_block` (smart contract calls, event logs, etc. can be added here)
 
         // Prepare receipt
         let receipt = Receipt {
@@ -111,9 +328,6 @@ impl Executor {
             }],
         };
 
-        // Delta for audit/state trace
-        let delta = AccountDelta::BalanceChange(amount as i128);
-
-        Ok((delta, receipt, gas))
+        Ok((receipt, gas))
     }
 }
@@ -0,0 +1,518 @@
+//! OCOS-Chain: Fork Choice & Chain Reorganization
+//!
+//! Picks the canonical head of the block tree (highest finalized block, then
+//! height, then smallest hash) and keeps the ledger state of that head. Blocks
+//! are executed on import, finalized by consensus (`Chain::finalize`) and, on a
+//! `KvStore`, written in one atomic batch per finalized block.
+
+use crate::consensus::block::{BlockLimits, ExecutionSummary};
+use crate::consensus::rewards::RewardDistributor;
+use crate::ledger::{
+    block::Block,
+    block_store::{BlockStore, StoredBlock},
+    executor::{ExecutionResult, Executor},
+    history::History,
+    kv_store::{Column, KvError, KvStore, WriteBatch},
+    log_index::{IndexedBlock, LogFilter, LogIndex, MatchedLog},
+    snapshot::{Snapshot, SnapshotError, SnapshotTrust},
+    state::{AccountState, State, StateDiff},
+    state_history::{HistoryError, StateHistory, StorageMode},
+    transaction::MAINNET_CHAIN_ID,
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const META_HEAD: &[u8] = b"head";
+const META_HEIGHT: &[u8] = b"height";
+
+/// Fork-choice rule over the block tree
+pub struct ForkChoice;
+
+impl ForkChoice {
+    /// Hash of the best head: highest finalized ancestor, then height, then smallest hash
+    pub fn best_head(store: &BlockStore) -> Option<Vec<u8>> {
+        store
+            .leaves()
+            .into_iter()
+            .max_by_key(|leaf| {
+                (
+                    Self::finalized_height(store, leaf),
+                    leaf.height,
+                    Reverse(leaf.hash.clone()),
+                )
+            })
+            .map(|leaf| leaf.hash.clone())
+    }
+
+    /// Height of the highest finalized block on the path to `leaf` (the walk
+    /// stops at the first finalized ancestor)
+    fn finalized_height(store: &BlockStore, leaf: &StoredBlock) -> u64 {
+        store
+            .ancestors(&leaf.hash)
+            .find(|b| b.finalized)
+            .map(|b| b.height)
+            .unwrap_or(0)
+    }
+}
+
+/// Chain import/finalization errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    UnknownParent,
+    /// Header height is not its parent's height plus one
+    InvalidHeight { expected: u64, found: u64 },
+    ConflictsWithFinalized,
+    MissingCheckpoint,
+    UnknownBlock,
+    /// Header `gas_used` differs from executing the block
+    GasUsedMismatch { expected: u64, found: u64 },
+    /// Header `logs_bloom` differs from the bloom of the block's receipts
+    LogsBloomMismatch,
+    Storage(KvError),
+    Snapshot(SnapshotError),
+    History(HistoryError),
+}
+
+impl From<KvError> for ChainError {
+    fn from(err: KvError) -> Self {
+        ChainError::Storage(err)
+    }
+}
+
+impl From<SnapshotError> for ChainError {
+    fn from(err: SnapshotError) -> Self {
+        ChainError::Snapshot(err)
+    }
+}
+
+impl From<HistoryError> for ChainError {
+    fn from(err: HistoryError) -> Self {
+        match err {
+            HistoryError::Storage(err) => ChainError::Storage(err),
+            err => ChainError::History(err),
+        }
+    }
+}
+
+/// What importing a block did to the canonical chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    Duplicate,
+    /// Block stored on a non-canonical branch
+    SideBranch,
+    /// Block extended the canonical head
+    Extended,
+    /// Canonical head moved to another branch
+    Reorg {
+        common_ancestor: Vec<u8>,
+        reverted: Vec<Vec<u8>>,
+        applied: Vec<Vec<u8>>,
+    },
+}
+
+/// State changes of one executed block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDiff {
+    pub changes: StateDiff,
+    /// Root of the block's post-state
+    pub state_root: Vec<u8>,
+}
+
+impl BlockDiff {
+    /// Diff of a root block whose state is loaded whole (genesis, snapshot, restart)
+    fn root(state: &State) -> Self {
+        BlockDiff { changes: StateDiff::default(), state_root: state.state_root() }
+    }
+}
+
+/// Block tree plus the ledger state of its canonical head. Only the head's state
+/// is kept: every block above the finalized tip keeps its diff, and switching
+/// branches undoes and replays diffs. Finalized state also goes to `state_history`
+/// for historical queries, and finalized receipts to `logs`.
+pub struct Chain {
+    pub store: BlockStore,
+    /// Post-state of `head`; moved between branches with `diffs`
+    pub state: State,
+    pub head: Vec<u8>,
+    /// Diffs of the finalized tip and every block imported after it
+    pub diffs: HashMap<Vec<u8>, BlockDiff>,
+    /// Pays block fees and issuance during execution (None = fees are only deducted)
+    pub rewards: Option<RewardDistributor>,
+    /// Transactions signed for another chain are rejected
+    pub chain_id: u64,
+    /// Gas the transactions of one block may reserve (follows the consensus `max_block_gas`)
+    pub max_block_gas: u64,
+    /// Finalized blocks and their transactions
+    pub history: History,
+    /// Persistent backend (None = in-memory only)
+    pub db: Option<Arc<dyn KvStore>>,
+    /// Height of the last finalized block recorded in history (and written to `db`)
+    pub persisted_height: u64,
+    /// Per-height versions of finalized state (pruned or archive)
+    pub state_history: StateHistory,
+    /// Log blooms of finalized blocks over their stored receipts
+    pub logs: LogIndex,
+    /// Receipts of every non-finalized block, indexed on finalization
+    pending_logs: HashMap<Vec<u8>, IndexedBlock>,
+}
+
+impl Chain {
+    /// Start a chain from a finalized genesis block and its state
+    pub fn new(genesis: Block, genesis_state: State) -> Self {
+        let hash = genesis.hash();
+        let height = genesis.height();
+        let mut history = History::new();
+        history.add_block(height, hash.clone(), genesis.transactions.clone());
+        let mut store = BlockStore::new();
+        store.insert_block(genesis, hash.clone(), height);
+        store.mark_finalized(&hash);
+
+        let mut diffs = HashMap::new();
+        diffs.insert(hash.clone(), BlockDiff::root(&genesis_state));
+        let state_history = StateHistory::in_memory(StorageMode::default(), height, &genesis_state);
+        Chain {
+            store,
+            state: genesis_state,
+            head: hash,
+            diffs,
+            rewards: None,
+            chain_id: MAINNET_CHAIN_ID,
+            max_block_gas: BlockLimits::default().max_block_gas,
+            history,
+            db: None,
+            persisted_height: height,
+            state_history,
+            logs: LogIndex::new(),
+            pending_logs: HashMap::new(),
+        }
+    }
+
+    /// Open a persistent chain: resume from the last finalized block in `db`
+    /// (unfinalized blocks are re-imported), or write `genesis` and its state if
+    /// the store is empty
+    pub fn open(db: Arc<dyn KvStore>, genesis: Block, mut genesis_state: State) -> Result<Self, ChainError> {
+        let Some(head) = db.get(Column::Meta, META_HEAD)? else {
+            genesis_state.commit();
+            let mut batch = genesis_state.take_write_batch();
+            let mut chain = Chain::new(genesis, genesis_state);
+            chain.state_history = StateHistory::new(db.clone(), StorageMode::default(), chain.persisted_height, &chain.state, &mut batch);
+            chain.queue_finalized(&chain.head, &mut batch)?;
+            db.write(batch)?;
+            chain.logs = LogIndex::load(db.clone())?; // Receipts and versions go to `db` from now on
+            chain.db = Some(db);
+            return Ok(chain);
+        };
+
+        let store = BlockStore::load(db.as_ref())?;
+        let height = store.get_stored(&head)
+            .map(|b| b.height)
+            .ok_or_else(|| KvError::corrupted(Column::Meta, "head block missing"))?;
+        let state = State::load(db.as_ref())?;
+        let mut diffs = HashMap::new();
+        diffs.insert(head.clone(), BlockDiff::root(&state));
+        Ok(Chain {
+            store,
+            state,
+            head,
+            diffs,
+            rewards: None,
+            chain_id: MAINNET_CHAIN_ID,
+            max_block_gas: BlockLimits::default().max_block_gas,
+            history: History::load(db.as_ref())?,
+            state_history: StateHistory::load(db.clone(), StorageMode::default())?,
+            logs: LogIndex::load(db.clone())?,
+            db: Some(db),
+            persisted_height: height,
+            pending_logs: HashMap::new(),
+        })
+    }
+
+    /// Start from a snapshot instead of genesis: its block becomes the finalized
+    /// root once the manifest matches `trust` and every chunk and the state root
+    /// check out. With `db`, an empty store is initialized from it; an
+    /// initialized one resumes from its own head.
+    pub fn from_snapshot(snapshot: &Snapshot, trust: &SnapshotTrust, db: Option<Arc<dyn KvStore>>) -> Result<Self, ChainError> {
+        let state = snapshot.restore_trusted(trust)?;
+        let block = snapshot.manifest.block.clone();
+        match db {
+            Some(db) => Self::open(db, block, state),
+            None => Ok(Chain::new(block, state)),
+        }
+    }
+
+    /// Keep every historical version (archive) or only recent ones (pruned);
+    /// applies from the next finalized block on
+    pub fn with_storage_mode(mut self, mode: StorageMode) -> Self {
+        self.state_history.mode = mode;
+        self
+    }
+
+    /// Account as of the end of canonical block `height`: non-finalized heights
+    /// are read through the diffs above them, finalized ones from state history
+    pub fn account_at(&self, address: &[u8], height: u64) -> Result<Option<AccountState>, ChainError> {
+        if height <= self.persisted_height {
+            return Ok(self.state_history.account_at(address, height)?);
+        }
+        self.store.get_block_by_height(height).ok_or(ChainError::UnknownBlock)?;
+        // The lowest block above `height` that wrote the account holds its value at `height`
+        let mut account = self.state.get_account(address).cloned();
+        for block in self.store.ancestors(&self.head).take_while(|b| b.height > height) {
+            let diff = self.diffs.get(&block.hash).ok_or(ChainError::MissingCheckpoint)?;
+            if let Some(before) = diff.changes.account_before(address) {
+                account = before.clone();
+            }
+        }
+        Ok(account)
+    }
+
+    /// Logs matching `filter` on the canonical chain, oldest first
+    pub fn logs(&self, filter: &LogFilter) -> Result<Vec<MatchedLog>, ChainError> {
+        let mut logs = self.logs.query(filter)?;
+        for height in filter.from_height.max(self.persisted_height + 1)..=filter.to_height {
+            let Some(block) = self.store.get_block_by_height(height) else {
+                break;
+            };
+            if let Some(indexed) = self.pending_logs.get(&block.hash()) {
+                logs.extend(filter.scan(height, indexed));
+            }
+        }
+        Ok(logs)
+    }
+
+    /// Snapshot of the state after the latest finalized block; unless it is the
+    /// head, the state is copied once and the blocks above it undone on the copy
+    pub fn snapshot(&self) -> Result<Snapshot, ChainError> {
+        let finalized = self.store.latest_finalized().ok_or(ChainError::UnknownBlock)?;
+        if finalized.hash == self.head {
+            return Ok(Snapshot::from_state(finalized.block.clone(), &self.state)?);
+        }
+        let mut state = self.state.clone();
+        for block in self.store.ancestors(&self.head).take_while(|b| b.height > finalized.height) {
+            let diff = self.diffs.get(&block.hash).ok_or(ChainError::MissingCheckpoint)?;
+            state.apply_diff(&diff.changes, false);
+        }
+        state.commit();
+        Ok(Snapshot::from_state(finalized.block.clone(), &state)?)
+    }
+
+    /// Execute `block` on its parent's post-state and write the resulting gas and
+    /// logs bloom into its header; producers call this before the block is signed
+    pub fn seal_block(&mut self, block: &mut Block) -> Result<ExecutionResult, ChainError> {
+        let (result, _) = self.execute_on_parent(block)?;
+        block.header.gas_used = result.gas_used;
+        block.header.logs_bloom = result.logs_bloom.as_bytes().to_vec();
+        Ok(result)
+    }
+
+    /// Execution results of an imported block, for `ConsensusEngine::on_executed`
+    /// once consensus finalizes it
+    pub fn execution_summary(&self, hash: &[u8]) -> Result<ExecutionSummary, ChainError> {
+        let block = self.store.get_block_by_hash(hash).ok_or(ChainError::UnknownBlock)?;
+        let diff = self.diffs.get(hash).ok_or(ChainError::MissingCheckpoint)?;
+        Ok(ExecutionSummary {
+            gas_used: block.header.gas_used, // Both checked against execution on import
+            state_root: diff.state_root.clone(),
+            logs_bloom: block.header.logs_bloom.clone(),
+        })
+    }
+
+    /// Import a block, run fork choice, and reorganize if the best head changed.
+    /// The block is executed first and rejected if its height is not its parent's
+    /// plus one or its header `gas_used` or `logs_bloom` disagrees with the result.
+    pub fn import_block(&mut self, block: Block) -> Result<ImportOutcome, ChainError> {
+        let hash = block.hash();
+        if self.store.get_stored(&hash).is_some() {
+            return Ok(ImportOutcome::Duplicate);
+        }
+        let parent_height = self.store
+            .get_stored(block.parent_hash())
+            .map(|p| p.height)
+            .ok_or(ChainError::UnknownParent)?;
+        if block.height() != parent_height + 1 {
+            return Err(ChainError::InvalidHeight { expected: parent_height + 1, found: block.height() });
+        }
+        if let Some(finalized) = self.store.latest_finalized() {
+            if !self.store.is_descendant(block.parent_hash(), &finalized.hash) {
+                return Err(ChainError::ConflictsWithFinalized);
+            }
+        }
+        let (result, changes) = self.execute_on_parent(&block)?;
+        if block.header.gas_used != result.gas_used {
+            return Err(ChainError::GasUsedMismatch { expected: result.gas_used, found: block.header.gas_used });
+        }
+        if block.header.logs_bloom != result.logs_bloom.as_bytes() {
+            return Err(ChainError::LogsBloomMismatch);
+        }
+        let height = block.height();
+        self.store.insert_block(block, hash.clone(), height);
+        let indexed = IndexedBlock { hash: hash.clone(), bloom: result.logs_bloom, receipts: result.receipts };
+        self.pending_logs.insert(hash.clone(), indexed);
+        self.diffs.insert(hash, BlockDiff { changes, state_root: result.state_root });
+
+        let best = ForkChoice::best_head(&self.store).ok_or(ChainError::UnknownBlock)?;
+        if best == self.head {
+            return Ok(ImportOutcome::SideBranch);
+        }
+        let best_parent = self.store.get_stored(&best).map(|b| b.parent_hash.clone());
+        if best_parent.as_deref() == Some(self.head.as_slice()) {
+            self.set_head(best)?;
+            return Ok(ImportOutcome::Extended);
+        }
+        self.reorg_to(best)
+    }
+
+    /// Switch state and the canonical index to the branch ending at `new_head`
+    pub fn reorg_to(&mut self, new_head: Vec<u8>) -> Result<ImportOutcome, ChainError> {
+        let ancestor = self.store
+            .common_ancestor(&self.head, &new_head)
+            .ok_or(ChainError::UnknownBlock)?;
+        let reverted: Vec<Vec<u8>> = self.branch_from(&ancestor, &self.head);
+        let applied: Vec<Vec<u8>> = self.branch_from(&ancestor, &new_head);
+        self.set_head(new_head)?;
+
+        Ok(ImportOutcome::Reorg {
+            common_ancestor: ancestor,
+            reverted,
+            applied,
+        })
+    }
+
+    /// Mark a block final (BFT commit) and drop conflicting branches and stale
+    /// diffs; fork choice and import then only walk the blocks above it
+    pub fn finalize(&mut self, hash: &[u8]) -> Result<(), ChainError> {
+        let height = self.store.get_stored(hash).map(|b| b.height).ok_or(ChainError::UnknownBlock)?;
+
+        // Consensus finalized a block off our canonical branch: switch before pruning
+        if !self.store.is_descendant(&self.head, hash) {
+            self.reorg_to(hash.to_vec())?;
+        }
+        self.store.mark_finalized(hash);
+        let best = ForkChoice::best_head(&self.store).ok_or(ChainError::UnknownBlock)?;
+        if best != self.head {
+            self.reorg_to(best)?;
+        }
+        self.persist_finalized(hash)?;
+
+        // Diffs of pruned branches go with their blocks
+        let store = &self.store;
+        let keep = |h: &Vec<u8>| h.as_slice() == hash || store.get_stored(h).map(|b| b.height > height).unwrap_or(false);
+        self.diffs.retain(|h, _| keep(h));
+        self.pending_logs.retain(|h, _| keep(h));
+        Ok(())
+    }
+
+    /// Record newly finalized blocks up to `hash` in history and write each one,
+    /// with its state changes, to the store in its own atomic batch. In-memory
+    /// records only move once their block is written, so a failed write leaves
+    /// the block pending for the next finalization.
+    fn persist_finalized(&mut self, hash: &[u8]) -> Result<(), ChainError> {
+        let persisted = self.persisted_height;
+        let mut newly_final: Vec<Vec<u8>> = self.store
+            .ancestors(hash)
+            .take_while(|b| b.height > persisted)
+            .map(|b| b.hash.clone())
+            .collect();
+        newly_final.reverse();
+
+        for h in newly_final {
+            let stored = self.store.get_stored(&h).ok_or(ChainError::UnknownBlock)?;
+            let writes = self.diffs.get(&h).ok_or(ChainError::MissingCheckpoint)?.changes.write_batch();
+            let indexed = self.pending_logs.get(&h).ok_or(ChainError::MissingCheckpoint)?;
+            match self.db.clone() {
+                Some(db) => {
+                    let mut batch = WriteBatch::new();
+                    let bounds = self.state_history.stage_block(stored.height, &writes, &mut batch)?;
+                    batch.ops.extend(writes.ops);
+                    let bloom = self.logs.stage_block(stored.height, indexed, &mut batch)?;
+                    self.queue_finalized(&h, &mut batch)?;
+                    db.write(batch)?;
+                    self.state_history.advance(bounds);
+                    self.logs.add_bloom(stored.height, bloom);
+                }
+                None => {
+                    self.state_history.record_block(stored.height, &writes)?;
+                    self.logs.add_block(stored.height, indexed.clone())?;
+                }
+            }
+            self.history.add_block(stored.height, h.clone(), stored.block.transactions.clone());
+            self.persisted_height = stored.height;
+            self.pending_logs.remove(&h);
+        }
+        Ok(())
+    }
+
+    /// Queue a finalized block, its history entry and the new head for the store
+    fn queue_finalized(&self, hash: &[u8], batch: &mut WriteBatch) -> Result<(), ChainError> {
+        let stored = self.store.get_stored(hash).ok_or(ChainError::UnknownBlock)?;
+        let height = stored.height;
+        self.store.write_block(hash, batch)?;
+        History::write_block(height, &stored.block.transactions, batch)?;
+        batch.put(Column::Meta, META_HEAD.to_vec(), hash.to_vec());
+        batch.put(Column::Meta, META_HEIGHT.to_vec(), height.to_be_bytes().to_vec());
+        Ok(())
+    }
+
+    /// Hashes on the path after `ancestor` up to `head`, oldest first
+    fn branch_from(&self, ancestor: &[u8], head: &[u8]) -> Vec<Vec<u8>> {
+        let mut branch: Vec<Vec<u8>> = self.store
+            .ancestors(head)
+            .take_while(|b| b.hash != ancestor)
+            .map(|b| b.hash.clone())
+            .collect();
+        branch.reverse();
+        branch
+    }
+
+    /// Execute a block on its parent's post-state; returns the result and the
+    /// block's diff, and leaves `state` at the head
+    fn execute_on_parent(&mut self, block: &Block) -> Result<(ExecutionResult, StateDiff), ChainError> {
+        let head = self.head.clone();
+        self.move_state(&head, block.parent_hash())?;
+        let checkpoint = self.state.checkpoint();
+        let result = Executor::execute_block_capped(&mut self.state, block, self.chain_id, self.max_block_gas, self.rewards.as_ref());
+        let changes = self.state.diff_since(checkpoint);
+        self.state.revert_to(checkpoint);
+        self.settle_state();
+        self.move_state(block.parent_hash(), &head)?;
+        Ok((result, changes))
+    }
+
+    /// Make an executed block the canonical head and its post-state the current state
+    fn set_head(&mut self, hash: Vec<u8>) -> Result<(), ChainError> {
+        let head = self.head.clone();
+        self.move_state(&head, &hash)?;
+        self.store.set_canonical_head(&hash);
+        self.head = hash;
+        Ok(())
+    }
+
+    /// Move `state` from the post-state of `from` to that of `to`: undo the diffs
+    /// of `from`'s branch down to the common ancestor, then replay `to`'s
+    fn move_state(&mut self, from: &[u8], to: &[u8]) -> Result<(), ChainError> {
+        if from == to {
+            return Ok(());
+        }
+        let ancestor = self.store.common_ancestor(from, to).ok_or(ChainError::UnknownBlock)?;
+        let undo = self.branch_from(&ancestor, from);
+        let redo = self.branch_from(&ancestor, to);
+        if !undo.iter().chain(&redo).all(|h| self.diffs.contains_key(h)) {
+            return Err(ChainError::MissingCheckpoint);
+        }
+        for hash in undo.iter().rev() {
+            self.state.apply_diff(&self.diffs[hash].changes, false);
+        }
+        for hash in &redo {
+            self.state.apply_diff(&self.diffs[hash].changes, true);
+        }
+        self.settle_state();
+        Ok(())
+    }
+
+    /// Fold state moves into the trie; they are never written to the store, as
+    /// finalized blocks write their own diffs
+    fn settle_state(&mut self) {
+        self.state.commit();
+        self.state.take_write_batch();
+    }
+}
@@ -2,8 +2,9 @@
 //!
 //! Stores and enables iteration over block and transaction history.
 //! Supports audit, rewind, chain analysis, and explorer queries.
+//! Persisted alongside finalized blocks through the key-value store.
 
-use crate::ledger::block::Block;
+use crate::ledger::kv_store::{Column, KvError, KvStore, WriteBatch};
 use crate::ledger::transaction::SignedTransaction;
 use std::collections::BTreeMap;
 
@@ -21,6 +22,46 @@ impl History {
         Self::default()
     }
 
+    /// Tarixçəni store-dan bərpa et (blok hash-ları kanonik indeksdən)
+    pub fn load(store: &dyn KvStore) -> Result<Self, KvError> {
+        let mut history = Self::new();
+        for (height, hash) in store.iter(Column::Canonical)? {
+            let height = Self::decode_height(Column::Canonical, &height)?;
+            history.block_hashes.insert(height, hash);
+        }
+        for (height, bytes) in store.iter(Column::BlockTxs)? {
+            let height = Self::decode_height(Column::BlockTxs, &height)?;
+            let tx_hashes: Vec<Vec<u8>> = bincode::deserialize(&bytes)
+                .map_err(|e| KvError::corrupted(Column::BlockTxs, e))?;
+            history.txs_by_block.insert(height, tx_hashes);
+        }
+        for (hash, bytes) in store.iter(Column::Transactions)? {
+            let tx = bincode::deserialize(&bytes).map_err(|e| KvError::corrupted(Column::Transactions, e))?;
+            history.tx_details.insert(hash, tx);
+        }
+        Ok(history)
+    }
+
+    /// Blokun əməliyyatlarını store yazı paketinə əlavə et (yaddaşdakı tarixçəyə
+    /// toxunmadan: `add_block` yalnız paket yazıldıqdan sonra çağırılır)
+    pub fn write_block(height: u64, txs: &[SignedTransaction], batch: &mut WriteBatch) -> Result<(), KvError> {
+        let mut tx_hashes = vec![];
+        for tx in txs {
+            let bytes = bincode::serialize(tx).map_err(|e| KvError::corrupted(Column::Transactions, e))?;
+            batch.put(Column::Transactions, tx.hash.clone(), bytes);
+            tx_hashes.push(tx.hash.clone());
+        }
+        let bytes = bincode::serialize(&tx_hashes).map_err(|e| KvError::corrupted(Column::BlockTxs, e))?;
+        batch.put(Column::BlockTxs, height.to_be_bytes().to_vec(), bytes);
+        Ok(())
+    }
+
+    fn decode_height(column: Column, key: &[u8]) -> Result<u64, KvError> {
+        key.try_into()
+            .map(u64::from_be_bytes)
+            .map_err(|_| KvError::corrupted(column, "bad height key"))
+    }
+
     /// Yeni blok əlavə edin
     pub fn add_block(&mut self, height: u64, block_hash: Vec<u8>, txs: Vec<SignedTransaction>) {
         self.block_hashes.insert(height, block_hash);
@@ -0,0 +1,261 @@
+//! OCOS-Chain: Key-Value Storage Backend
+//!
+//! Persistence layer under the block store, state and history. Data lives in
+//! column families (one keyspace per kind of record) and is written through
+//! `WriteBatch`es that are applied atomically, so a crash leaves either all or
+//! none of a block's writes on disk.
+//!
+//! Two backends implement `KvStore`: `SledStore`, an embedded on-disk database,
+//! and `MemoryStore` for tests and ephemeral nodes.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Column families
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Column {
+    Blocks,       // Block hash → block
+    Canonical,    // Height (big-endian) → block hash
+    Accounts,     // Address → account state
+    Storage,      // Storage key → value
+    BlockTxs,     // Height (big-endian) → tx hashes
+    Transactions, // Tx hash → signed transaction
+    Meta,         // Chain metadata (last committed height, head)
+    History,      // (column, key, height) → account/storage version (see `state_history`)
+    HistoryIndex, // (height, column, key) → nothing: keys written at a height, for pruning
+    Logs,         // Height (big-endian) → receipts (see `log_index`)
+    LogBlooms,    // Height (big-endian) → block hash and log bloom
+}
+
+impl Column {
+    /// Key prefix separating the column inside a single keyspace
+    pub fn id(&self) -> u8 {
+        match self {
+            Column::Blocks => 0,
+            Column::Canonical => 1,
+            Column::Accounts => 2,
+            Column::Storage => 3,
+            Column::BlockTxs => 4,
+            Column::Transactions => 5,
+            Column::Meta => 6,
+            Column::History => 7,
+            Column::Logs => 8,
+            Column::LogBlooms => 9,
+            Column::HistoryIndex => 10,
+        }
+    }
+
+    fn key(&self, key: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(key.len() + 1);
+        out.push(self.id());
+        out.extend_from_slice(key);
+        out
+    }
+}
+
+/// Storage backend errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KvError {
+    #[error("Storage I/O error: {0}")]
+    Io(String),
+    #[error("Corrupted record in {column:?}: {reason}")]
+    Corrupted { column: Column, reason: String },
+}
+
+impl KvError {
+    pub fn corrupted(column: Column, reason: impl ToString) -> Self {
+        KvError::Corrupted { column, reason: reason.to_string() }
+    }
+}
+
+/// Puts and deletes applied together or not at all
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    pub ops: Vec<(Column, Vec<u8>, Option<Vec<u8>>)>, // None = delete
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, column: Column, key: Vec<u8>, value: Vec<u8>) {
+        self.ops.push((column, key, Some(value)));
+    }
+
+    pub fn delete(&mut self, column: Column, key: Vec<u8>) {
+        self.ops.push((column, key, None));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Column-family key-value store with atomic batches
+pub trait KvStore: Send + Sync {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, KvError>;
+
+    /// Apply every operation of the batch atomically (and durably, for disk backends)
+    fn write(&self, batch: WriteBatch) -> Result<(), KvError>;
+
+    /// All entries of a column, ordered by key
+    fn iter(&self, column: Column) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError>;
+
+    /// Entries of a column with keys in `from..=to`, ordered by key
+    fn range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError>;
+
+    /// Entry with the greatest key in `from..=to`, found by a reverse scan
+    fn last_in_range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, KvError>;
+
+    fn put(&self, column: Column, key: Vec<u8>, value: Vec<u8>) -> Result<(), KvError> {
+        let mut batch = WriteBatch::new();
+        batch.put(column, key, value);
+        self.write(batch)
+    }
+
+    fn delete(&self, column: Column, key: Vec<u8>) -> Result<(), KvError> {
+        let mut batch = WriteBatch::new();
+        batch.delete(column, key);
+        self.write(batch)
+    }
+}
+
+/// In-memory backend (tests, ephemeral nodes)
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, KvError> {
+        let entries = self.entries.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(entries.get(&column.key(key)).cloned())
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), KvError> {
+        // One write lock for the whole batch: readers never see it half-applied
+        let mut entries = self.entries.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (column, key, value) in batch.ops {
+            match value {
+                Some(value) => entries.insert(column.key(&key), value),
+                None => entries.remove(&column.key(&key)),
+            };
+        }
+        Ok(())
+    }
+
+    fn iter(&self, column: Column) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+        let entries = self.entries.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(entries
+            .range(vec![column.id()]..)
+            .take_while(|(k, _)| k[0] == column.id())
+            .map(|(k, v)| (k[1..].to_vec(), v.clone()))
+            .collect())
+    }
+
+    fn range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+        if from > to {
+            return Ok(vec![]);
+        }
+        let entries = self.entries.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(entries
+            .range(column.key(from)..=column.key(to))
+            .map(|(k, v)| (k[1..].to_vec(), v.clone()))
+            .collect())
+    }
+
+    fn last_in_range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, KvError> {
+        if from > to {
+            return Ok(None);
+        }
+        let entries = self.entries.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(entries
+            .range(column.key(from)..=column.key(to))
+            .next_back()
+            .map(|(k, v)| (k[1..].to_vec(), v.clone())))
+    }
+}
+
+/// Embedded on-disk backend (sled); every batch is flushed before `write` returns
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl SledStore {
+    /// Open (or create) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, KvError> {
+        let db = sled::open(path).map_err(|e| KvError::Io(e.to_string()))?;
+        Ok(SledStore { db })
+    }
+}
+
+impl KvStore for SledStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, KvError> {
+        self.db
+            .get(column.key(key))
+            .map(|value| value.map(|v| v.to_vec()))
+            .map_err(|e| KvError::Io(e.to_string()))
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), KvError> {
+        let mut sled_batch = sled::Batch::default();
+        for (column, key, value) in batch.ops {
+            match value {
+                Some(value) => sled_batch.insert(column.key(&key), value),
+                None => sled_batch.remove(column.key(&key)),
+            }
+        }
+        self.db.apply_batch(sled_batch).map_err(|e| KvError::Io(e.to_string()))?;
+        self.db.flush().map_err(|e| KvError::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    fn iter(&self, column: Column) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+        self.db
+            .scan_prefix([column.id()])
+            .map(|entry| {
+                entry
+                    .map(|(k, v)| (k[1..].to_vec(), v.to_vec()))
+                    .map_err(|e| KvError::Io(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+        if from > to {
+            return Ok(vec![]);
+        }
+        self.db
+            .range(column.key(from)..=column.key(to))
+            .map(|entry| {
+                entry
+                    .map(|(k, v)| (k[1..].to_vec(), v.to_vec()))
+                    .map_err(|e| KvError::Io(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn last_in_range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, KvError> {
+        if from > to {
+            return Ok(None);
+        }
+        self.db
+            .range(column.key(from)..=column.key(to))
+            .next_back()
+            .transpose()
+            .map(|entry| entry.map(|(k, v)| (k[1..].to_vec(), v.to_vec())))
+            .map_err(|e| KvError::Io(e.to_string()))
+    }
+}
@@ -0,0 +1,223 @@
+//! OCOS-Chain: Event Log Index & Queries
+//!
+//! Indexes the receipts of finalized blocks by height and answers `LogFilter`
+//! queries such as "events of contract X with topic T between heights A and B".
+//! Only each block's hash and log bloom stay in memory; receipts live in the
+//! `Logs` column and are read back only for blocks whose bloom may match the
+//! filter, where every log is checked exactly to drop bloom false positives.
+//!
+//! A chain with a `KvStore` writes both records in the same batch as the
+//! finalized block itself (`LogIndex::stage_block`), and `LogIndex::load`
+//! reloads only the blooms on restart. An in-memory chain keeps them in its own
+//! `MemoryStore`.
+
+use crate::ledger::kv_store::{Column, KvError, KvStore, MemoryStore, WriteBatch};
+use crate::ledger::receipt::{Bloom, EventLog, Receipt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Most blocks a single API log query may span
+pub const MAX_QUERY_RANGE: u64 = 10_000;
+
+/// Log query over an inclusive height range. An empty address list or topic
+/// position matches anything; otherwise one of the listed values must be present.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub from_height: u64,
+    pub to_height: u64,
+    pub addresses: Vec<Vec<u8>>,   // Emitting address is one of these
+    pub topics: Vec<Vec<Vec<u8>>>, // Topic i is one of `topics[i]`
+}
+
+impl LogFilter {
+    /// Every log between two heights (inclusive)
+    pub fn new(from_height: u64, to_height: u64) -> Self {
+        LogFilter { from_height, to_height, ..Default::default() }
+    }
+
+    /// Also accept logs emitted by `address`
+    pub fn with_address(mut self, address: Vec<u8>) -> Self {
+        self.addresses.push(address);
+        self
+    }
+
+    /// Require topic `position` to be one of `alternatives`
+    pub fn with_topic(mut self, position: usize, alternatives: Vec<Vec<u8>>) -> Self {
+        if self.topics.len() <= position {
+            self.topics.resize(position + 1, vec![]);
+        }
+        self.topics[position] = alternatives;
+        self
+    }
+
+    /// False when no log summarized by `bloom` can match
+    pub fn may_match(&self, bloom: &Bloom) -> bool {
+        let any_in = |values: &[Vec<u8>]| values.is_empty() || values.iter().any(|v| bloom.contains(v));
+        any_in(&self.addresses) && self.topics.iter().all(|alternatives| any_in(alternatives))
+    }
+
+    /// Exact check of a single log
+    pub fn matches(&self, log: &EventLog) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false;
+        }
+        self.topics.iter().enumerate().all(|(position, alternatives)| {
+            alternatives.is_empty() || log.topics.get(position).is_some_and(|topic| alternatives.contains(topic))
+        })
+    }
+
+    /// Matching logs of one block, in receipt order
+    pub fn scan(&self, height: u64, block: &IndexedBlock) -> Vec<MatchedLog> {
+        if !self.may_match(&block.bloom) {
+            return vec![];
+        }
+        let logs = block.receipts.iter().enumerate().flat_map(|(tx_index, receipt)| {
+            receipt.logs.iter().map(move |log| (tx_index, &receipt.tx_hash, log))
+        });
+        logs.enumerate()
+            .filter(|(_, (_, _, log))| self.matches(log))
+            .map(|(log_index, (tx_index, tx_hash, log))| MatchedLog {
+                height,
+                block_hash: block.hash.clone(),
+                tx_hash: tx_hash.clone(),
+                tx_index,
+                log_index,
+                log: log.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A log returned by a query, with its position in the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedLog {
+    pub height: u64,
+    pub block_hash: Vec<u8>,
+    pub tx_hash: Vec<u8>,
+    pub tx_index: usize,  // Receipt position in the block
+    pub log_index: usize, // Log position across the whole block
+    pub log: EventLog,
+}
+
+/// Receipts of one block and the bloom over their logs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedBlock {
+    pub hash: Vec<u8>,
+    pub bloom: Bloom,
+    pub receipts: Vec<Receipt>,
+}
+
+impl IndexedBlock {
+    pub fn new(hash: Vec<u8>, receipts: Vec<Receipt>) -> Self {
+        IndexedBlock { hash, bloom: Bloom::from_receipts(&receipts), receipts }
+    }
+}
+
+/// Hash and log bloom of an indexed block, the part kept in memory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockBloom {
+    pub hash: Vec<u8>,
+    pub bloom: Bloom,
+}
+
+/// Height-ordered blooms of finalized blocks over receipts kept in a store
+pub struct LogIndex {
+    store: Arc<dyn KvStore>,
+    blooms: BTreeMap<u64, BlockBloom>,
+}
+
+impl Default for LogIndex {
+    fn default() -> Self {
+        LogIndex { store: Arc::new(MemoryStore::new()), blooms: BTreeMap::new() }
+    }
+}
+
+impl LogIndex {
+    /// Empty index over its own in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reload the blooms from `store`; receipts stay there until a query needs them
+    pub fn load(store: Arc<dyn KvStore>) -> Result<Self, KvError> {
+        let mut blooms = BTreeMap::new();
+        for (height, bytes) in store.iter(Column::LogBlooms)? {
+            let bloom = bincode::deserialize(&bytes).map_err(|e| KvError::corrupted(Column::LogBlooms, e))?;
+            blooms.insert(decode_height(&height, Column::LogBlooms)?, bloom);
+        }
+        Ok(LogIndex { store, blooms })
+    }
+
+    /// Index a finalized block, writing its receipts to the index's store
+    pub fn add_block(&mut self, height: u64, block: IndexedBlock) -> Result<(), KvError> {
+        let mut batch = WriteBatch::new();
+        let entry = self.stage_block(height, &block, &mut batch)?;
+        self.store.write(batch)?;
+        self.add_bloom(height, entry);
+        Ok(())
+    }
+
+    /// Queue a finalized block's receipts and bloom for the store with `batch`;
+    /// the returned bloom goes to `add_bloom` once the batch is written
+    pub fn stage_block(&self, height: u64, block: &IndexedBlock, batch: &mut WriteBatch) -> Result<BlockBloom, KvError> {
+        let entry = BlockBloom { hash: block.hash.clone(), bloom: block.bloom.clone() };
+        let key = height.to_be_bytes().to_vec();
+        let receipts = bincode::serialize(&block.receipts).map_err(|e| KvError::corrupted(Column::Logs, e))?;
+        let bloom = bincode::serialize(&entry).map_err(|e| KvError::corrupted(Column::LogBlooms, e))?;
+        batch.put(Column::Logs, key.clone(), receipts);
+        batch.put(Column::LogBlooms, key, bloom);
+        Ok(entry)
+    }
+
+    /// Make a block staged with `stage_block` queryable
+    pub fn add_bloom(&mut self, height: u64, entry: BlockBloom) {
+        self.blooms.insert(height, entry);
+    }
+
+    /// Hash and bloom of the block indexed at `height`
+    pub fn bloom(&self, height: u64) -> Option<&BlockBloom> {
+        self.blooms.get(&height)
+    }
+
+    /// The block indexed at `height`, with its receipts read from the store
+    pub fn get(&self, height: u64) -> Result<Option<IndexedBlock>, KvError> {
+        let Some(entry) = self.blooms.get(&height) else {
+            return Ok(None);
+        };
+        let bytes = self.store
+            .get(Column::Logs, &height.to_be_bytes())?
+            .ok_or_else(|| KvError::corrupted(Column::Logs, format!("receipts of height {} missing", height)))?;
+        let receipts = bincode::deserialize(&bytes).map_err(|e| KvError::corrupted(Column::Logs, e))?;
+        Ok(Some(IndexedBlock { hash: entry.hash.clone(), bloom: entry.bloom.clone(), receipts }))
+    }
+
+    /// Indexed heights in the filter's range whose bloom may match
+    pub fn candidates(&self, filter: &LogFilter) -> Vec<u64> {
+        let range = (filter.from_height <= filter.to_height).then(|| self.blooms.range(filter.from_height..=filter.to_height));
+        range
+            .into_iter()
+            .flatten()
+            .filter(|(_, entry)| filter.may_match(&entry.bloom))
+            .map(|(height, _)| *height)
+            .collect()
+    }
+
+    /// Every matching log in the filter's range, oldest first; only candidate
+    /// blocks have their receipts read
+    pub fn query(&self, filter: &LogFilter) -> Result<Vec<MatchedLog>, KvError> {
+        let mut logs = vec![];
+        for height in self.candidates(filter) {
+            if let Some(block) = self.get(height)? {
+                logs.extend(filter.scan(height, &block));
+            }
+        }
+        Ok(logs)
+    }
+}
+
+fn decode_height(key: &[u8], column: Column) -> Result<u64, KvError> {
+    key.try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| KvError::corrupted(column, "bad height key"))
+}
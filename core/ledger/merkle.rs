@@ -0,0 +1,196 @@
+//! OCOS-Chain: Sparse Merkle Tree
+//!
+//! Authenticated key-value map over 256-bit key paths. Only non-empty nodes are
+//! stored; an empty subtree hashes to 32 zero bytes at every depth, so the root
+//! of an empty tree is `EMPTY_ROOT`. Updating a key rehashes its path only,
+//! which keeps state roots incremental. Proofs carry the non-empty siblings plus
+//! a bitmap of which ones are present, and prove inclusion (a value) or
+//! non-inclusion (an empty slot) against nothing but the root.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::HashMap;
+
+/// Root of a tree with no keys
+pub const EMPTY_ROOT: [u8; 32] = [0u8; 32];
+
+const DEPTH: usize = 256;
+
+type Hash = [u8; 32];
+
+/// Sparse Merkle tree with incremental root updates
+#[derive(Debug, Clone, Default)]
+pub struct MerkleTree {
+    /// (depth, path prefix) → hash of every non-empty node; leaves live at depth 256
+    nodes: HashMap<(u16, Hash), Hash>,
+    /// Path → value of every leaf
+    values: HashMap<Hash, Vec<u8>>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key path of `key` within a domain (e.g. accounts vs storage)
+    pub fn path_of(domain: &[u8], key: &[u8]) -> Hash {
+        let mut hasher = Sha3_256::new();
+        hasher.update(domain);
+        hasher.update(key);
+        hasher.finalize().into()
+    }
+
+    /// Current root hash
+    pub fn root(&self) -> Vec<u8> {
+        self.node(0, &EMPTY_ROOT).to_vec()
+    }
+
+    pub fn get(&self, path: &Hash) -> Option<&Vec<u8>> {
+        self.values.get(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Set (Some) or delete (None) the value at `path`, rehashing the path up to the root
+    pub fn update(&mut self, path: Hash, value: Option<Vec<u8>>) {
+        let mut hash = match &value {
+            Some(v) => leaf_hash(&path, v),
+            None => EMPTY_ROOT,
+        };
+        match value {
+            Some(v) => self.values.insert(path, v),
+            None => self.values.remove(&path),
+        };
+        self.store(DEPTH, prefix(&path, DEPTH), hash);
+
+        for depth in (0..DEPTH).rev() {
+            let sibling = self.node(depth + 1, &sibling_prefix(&path, depth));
+            hash = match bit(&path, depth) {
+                true => node_hash(&sibling, &hash),
+                false => node_hash(&hash, &sibling),
+            };
+            self.store(depth, prefix(&path, depth), hash);
+        }
+    }
+
+    /// Inclusion proof for a present key, non-inclusion proof otherwise
+    pub fn prove(&self, path: &Hash) -> MerkleProof {
+        let mut bitmap = vec![0u8; DEPTH / 8];
+        let mut siblings = vec![];
+        for depth in (0..DEPTH).rev() {
+            let sibling = self.node(depth + 1, &sibling_prefix(path, depth));
+            if sibling != EMPTY_ROOT {
+                bitmap[depth / 8] |= 0x80 >> (depth % 8);
+                siblings.push(sibling.to_vec());
+            }
+        }
+        MerkleProof {
+            path: path.to_vec(),
+            value: self.values.get(path).cloned(),
+            bitmap,
+            siblings,
+        }
+    }
+
+    fn node(&self, depth: usize, prefix: &Hash) -> Hash {
+        self.nodes.get(&(depth as u16, *prefix)).copied().unwrap_or(EMPTY_ROOT)
+    }
+
+    fn store(&mut self, depth: usize, prefix: Hash, hash: Hash) {
+        if hash == EMPTY_ROOT {
+            self.nodes.remove(&(depth as u16, prefix));
+        } else {
+            self.nodes.insert((depth as u16, prefix), hash);
+        }
+    }
+}
+
+/// Proof that `path` holds `value` (or nothing, if `value` is None) under a root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub path: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+    pub bitmap: Vec<u8>,        // Bit `depth` set if the sibling at that depth is non-empty
+    pub siblings: Vec<Vec<u8>>, // Non-empty siblings, leaf to root
+}
+
+impl MerkleProof {
+    /// Recompute the root from the leaf and siblings
+    pub fn verify(&self, root: &[u8]) -> bool {
+        let path: Hash = match self.path.as_slice().try_into() {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        if self.bitmap.len() != DEPTH / 8 {
+            return false;
+        }
+        let mut hash = match &self.value {
+            Some(v) => leaf_hash(&path, v),
+            None => EMPTY_ROOT,
+        };
+        let mut siblings = self.siblings.iter();
+        for depth in (0..DEPTH).rev() {
+            let sibling: Hash = if self.bitmap[depth / 8] & (0x80 >> (depth % 8)) != 0 {
+                match siblings.next().and_then(|s| s.as_slice().try_into().ok()) {
+                    Some(s) => s,
+                    None => return false,
+                }
+            } else {
+                EMPTY_ROOT
+            };
+            hash = match bit(&path, depth) {
+                true => node_hash(&sibling, &hash),
+                false => node_hash(&hash, &sibling),
+            };
+        }
+        siblings.next().is_none() && hash.as_slice() == root
+    }
+}
+
+/// Bit `i` of a path, most significant first
+fn bit(path: &Hash, i: usize) -> bool {
+    path[i / 8] & (0x80 >> (i % 8)) != 0
+}
+
+/// First `depth` bits of a path, the rest zeroed
+fn prefix(path: &Hash, depth: usize) -> Hash {
+    let mut out = *path;
+    let (full, rem) = (depth / 8, depth % 8);
+    if full < out.len() {
+        out[full] &= (0xFF00u16 >> rem) as u8;
+        out[full + 1..].fill(0);
+    }
+    out
+}
+
+/// Prefix of the sibling of the depth-`depth + 1` node on `path`
+fn sibling_prefix(path: &Hash, depth: usize) -> Hash {
+    let mut out = prefix(path, depth + 1);
+    out[depth / 8] ^= 0x80 >> (depth % 8);
+    out
+}
+
+fn leaf_hash(path: &Hash, value: &[u8]) -> Hash {
+    let mut hasher = Sha3_256::new();
+    hasher.update([0u8]);
+    hasher.update(path);
+    hasher.update(Sha3_256::digest(value));
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    if *left == EMPTY_ROOT && *right == EMPTY_ROOT {
+        return EMPTY_ROOT;
+    }
+    let mut hasher = Sha3_256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
@@ -8,16 +8,33 @@ pub mod transaction;
 pub mod state;
 pub mod account;
 pub mod receipt;
+pub mod log_index;
 pub mod merkle;
 pub mod history;
+pub mod snapshot;
+pub mod state_history;
 pub mod audit;
+pub mod block_store;
+pub mod kv_store;
+pub mod tx_pool;
+pub mod executor;
+pub mod parallel;
+pub mod fork_choice;
 
 // -- Public re-exports for ease of use across protocol layers --
 pub use block::{Block, BlockHeader};
-pub use transaction::{Transaction, SignedTransaction};
-pub use state::{State, StateUpdate, StorageProof};
+pub use transaction::{Transaction, SignedTransaction, TransactionError, TxSignatureScheme};
+pub use state::{AccountDelta, AccountProof, AccountState, Checkpoint, State, StateDiff, StateUpdate, StorageProof};
 pub use account::{Account, Balance, Nonce};
-pub use receipt::{Receipt, EventLog};
-pub use merkle::{MerkleTree, MerkleProof};
+pub use receipt::{Bloom, Receipt, EventLog};
+pub use log_index::{BlockBloom, IndexedBlock, LogFilter, LogIndex, MatchedLog, MAX_QUERY_RANGE};
+pub use merkle::{MerkleTree, MerkleProof, EMPTY_ROOT};
 pub use history::{History, LedgerIterator};
+pub use state_history::{HistoryBounds, HistoryError, StateHistory, StorageMode};
+pub use snapshot::{Snapshot, SnapshotError, SnapshotImporter, SnapshotManifest, SnapshotTrust};
 pub use audit::{LedgerAudit, AuditLog, AuditError};
+pub use block_store::{BlockStore, StoredBlock};
+pub use kv_store::{Column, KvError, KvStore, MemoryStore, SledStore, WriteBatch};
+pub use tx_pool::{TxPool, TxPoolConfig, TxPoolError};
+pub use executor::{AccountAccess, Executor, ExecutionResult, ExecutionError};
+pub use fork_choice::{Chain, ChainError, ForkChoice, ImportOutcome};
@@ -0,0 +1,476 @@
+//! OCOS-Chain: Parallel Transaction Execution (Block-STM)
+//!
+//! Runs a block's transactions optimistically on a pool of worker threads.
+//! Every incarnation of a transaction executes against a multi-version memory
+//! holding the writes of lower transactions and records which version of each
+//! account it read. After executing, a transaction is validated: if any read
+//! would now see a different version, the incarnation is aborted, its writes
+//! become estimates that make higher transactions wait for it, and it runs again.
+//!
+//! A shared scheduler hands out execution and validation tasks in block order.
+//! Once every transaction is executed and validated, each output equals what
+//! sequential execution produces; the writes are then applied to the state in
+//! block order, so journal, state update and root match the sequential path.
+
+use crate::ledger::executor::{AccountAccess, ExecutionError, Executor};
+use crate::ledger::receipt::Receipt;
+use crate::ledger::state::{AccountState, State};
+use crate::ledger::transaction::{SignedTransaction, TransactionError};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Mutex, MutexGuard, OnceLock, RwLock};
+
+/// Blocks with fewer transactions run sequentially
+pub const MIN_PARALLEL_TXS: usize = 16;
+
+/// Result of one transaction: receipt and gas used, or why it failed
+pub type TxOutcome = Result<(Receipt, u64), ExecutionError>;
+
+/// Worker threads for a block of `tx_count` transactions (1 = run sequentially)
+pub fn worker_count(tx_count: usize) -> usize {
+    if tx_count < MIN_PARALLEL_TXS {
+        return 1;
+    }
+    std::thread::available_parallelism().map_or(1, |n| n.get()).min(tx_count)
+}
+
+/// Execute `txs` on `workers` threads, then apply the writes of the successful
+/// ones to `state` in block order. Outcomes are in block order.
+pub fn execute_transactions(
+    state: &mut State,
+    txs: &[SignedTransaction],
+    chain_id: u64,
+    base_fee: u128,
+    workers: usize,
+) -> Vec<TxOutcome> {
+    let outputs = {
+        let block = BlockStm::new(state, txs, chain_id, base_fee);
+        std::thread::scope(|scope| {
+            for _ in 0..workers.max(1) {
+                scope.spawn(|| block.work());
+            }
+        });
+        block.into_outputs()
+    };
+
+    outputs
+        .into_iter()
+        .map(|output| {
+            if output.result.is_ok() {
+                for (address, account) in output.writes {
+                    state.update_account(address, account);
+                }
+            }
+            output.result
+        })
+        .collect()
+}
+
+/// Final output of a transaction's last incarnation
+struct TxOutput {
+    result: TxOutcome,
+    writes: Vec<(Vec<u8>, AccountState)>, // In first-write order; empty if it failed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Task {
+    Execute(usize, usize),  // (tx index, incarnation)
+    Validate(usize, usize),
+}
+
+/// Where a speculative read got its value from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadOrigin {
+    Base,                  // Pre-block state
+    Written(usize, usize), // (tx index, incarnation) of a lower transaction
+}
+
+enum MvEntry {
+    Written(usize, AccountState), // (incarnation, value)
+    Estimate,                     // Writer was aborted; its next value is unknown
+}
+
+enum MvRead {
+    Base,
+    Written(ReadOrigin, AccountState),
+    Blocked(usize), // Estimate left by this transaction
+}
+
+/// Multi-version memory: every transaction's latest writes, per account
+struct MvMemory {
+    data: RwLock<HashMap<Vec<u8>, BTreeMap<usize, MvEntry>>>,
+    written: Vec<Mutex<Vec<Vec<u8>>>>,             // Accounts written by each tx's last incarnation
+    reads: Vec<Mutex<Vec<(Vec<u8>, ReadOrigin)>>>, // Read set of each tx's last incarnation
+}
+
+impl MvMemory {
+    fn new(tx_count: usize) -> Self {
+        MvMemory {
+            data: RwLock::new(HashMap::new()),
+            written: (0..tx_count).map(|_| Mutex::new(vec![])).collect(),
+            reads: (0..tx_count).map(|_| Mutex::new(vec![])).collect(),
+        }
+    }
+
+    /// Latest write to `address` by a transaction below `tx_index`
+    fn read(&self, address: &[u8], tx_index: usize) -> MvRead {
+        let data = self.data.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match data.get(address).and_then(|versions| versions.range(..tx_index).next_back()) {
+            None => MvRead::Base,
+            Some((&writer, MvEntry::Estimate)) => MvRead::Blocked(writer),
+            Some((&writer, MvEntry::Written(incarnation, account))) => {
+                MvRead::Written(ReadOrigin::Written(writer, *incarnation), account.clone())
+            }
+        }
+    }
+
+    /// Publish an incarnation's writes and read set; true if it wrote an
+    /// account the previous incarnation did not (higher reads may be stale)
+    fn record(&self, tx_index: usize, incarnation: usize, reads: Vec<(Vec<u8>, ReadOrigin)>, writes: &[(Vec<u8>, AccountState)]) -> bool {
+        let mut written = lock(&self.written[tx_index]);
+        {
+            let mut data = self.data.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for (address, account) in writes {
+                data.entry(address.clone()).or_default().insert(tx_index, MvEntry::Written(incarnation, account.clone()));
+            }
+            for stale in written.iter().filter(|a| !writes.iter().any(|(w, _)| w == *a)) {
+                if let Some(versions) = data.get_mut(stale) {
+                    versions.remove(&tx_index);
+                }
+            }
+        }
+        let wrote_new_account = writes.iter().any(|(address, _)| !written.contains(address));
+        *written = writes.iter().map(|(address, _)| address.clone()).collect();
+        *lock(&self.reads[tx_index]) = reads;
+        wrote_new_account
+    }
+
+    /// Turn an aborted transaction's writes into estimates
+    fn mark_estimates(&self, tx_index: usize) {
+        let written = lock(&self.written[tx_index]);
+        let mut data = self.data.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for address in written.iter() {
+            if let Some(entry) = data.get_mut(address).and_then(|versions| versions.get_mut(&tx_index)) {
+                *entry = MvEntry::Estimate;
+            }
+        }
+    }
+
+    /// True if every read of the last incarnation would still see the same version
+    fn validate(&self, tx_index: usize) -> bool {
+        lock(&self.reads[tx_index]).iter().all(|(address, origin)| match self.read(address, tx_index) {
+            MvRead::Base => *origin == ReadOrigin::Base,
+            MvRead::Written(current, _) => *origin == current,
+            MvRead::Blocked(_) => false,
+        })
+    }
+}
+
+/// One incarnation's view: its own writes, then lower transactions' writes, then the base state
+struct SpeculativeView<'a> {
+    base: &'a State,
+    memory: &'a MvMemory,
+    tx_index: usize,
+    reads: Vec<(Vec<u8>, ReadOrigin)>,
+    writes: Vec<(Vec<u8>, AccountState)>,
+    blocked_on: Option<usize>, // Set on reading an estimate; the run is then discarded
+}
+
+impl AccountAccess for SpeculativeView<'_> {
+    fn account(&mut self, address: &[u8]) -> Option<AccountState> {
+        if let Some((_, account)) = self.writes.iter().find(|(a, _)| a.as_slice() == address) {
+            return Some(account.clone());
+        }
+        if self.blocked_on.is_some() {
+            return None;
+        }
+        match self.memory.read(address, self.tx_index) {
+            MvRead::Base => {
+                self.reads.push((address.to_vec(), ReadOrigin::Base));
+                self.base.get_account(address).cloned()
+            }
+            MvRead::Written(origin, account) => {
+                self.reads.push((address.to_vec(), origin));
+                Some(account)
+            }
+            MvRead::Blocked(writer) => {
+                self.blocked_on = Some(writer);
+                None
+            }
+        }
+    }
+
+    fn set_account(&mut self, address: &[u8], account: AccountState) {
+        match self.writes.iter_mut().find(|(a, _)| a.as_slice() == address) {
+            Some((_, slot)) => *slot = account,
+            None => self.writes.push((address.to_vec(), account)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    ReadyToExecute,
+    Executing,
+    Executed,
+    Aborting,
+}
+
+/// Collaborative scheduler: lowest pending execution or validation first
+struct Scheduler {
+    tx_count: usize,
+    execution_idx: AtomicUsize,
+    validation_idx: AtomicUsize,
+    decrease_cnt: AtomicUsize, // Bumped whenever an index moves back
+    active_tasks: AtomicUsize,
+    done: AtomicBool,
+    status: Vec<Mutex<(usize, Status)>>, // (incarnation, status)
+    dependents: Vec<Mutex<Vec<usize>>>,  // Transactions waiting for this one to finish executing
+}
+
+impl Scheduler {
+    fn new(tx_count: usize) -> Self {
+        Scheduler {
+            tx_count,
+            execution_idx: AtomicUsize::new(0),
+            validation_idx: AtomicUsize::new(0),
+            decrease_cnt: AtomicUsize::new(0),
+            active_tasks: AtomicUsize::new(0),
+            done: AtomicBool::new(false),
+            status: (0..tx_count).map(|_| Mutex::new((0, Status::ReadyToExecute))).collect(),
+            dependents: (0..tx_count).map(|_| Mutex::new(vec![])).collect(),
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.done.load(SeqCst)
+    }
+
+    fn next_task(&self) -> Option<Task> {
+        if self.validation_idx.load(SeqCst) < self.execution_idx.load(SeqCst) {
+            self.next_validation().map(|(tx, incarnation)| Task::Validate(tx, incarnation))
+        } else {
+            self.next_execution().map(|(tx, incarnation)| Task::Execute(tx, incarnation))
+        }
+    }
+
+    fn next_execution(&self) -> Option<(usize, usize)> {
+        if self.execution_idx.load(SeqCst) >= self.tx_count {
+            self.check_done();
+            return None;
+        }
+        self.active_tasks.fetch_add(1, SeqCst);
+        let version = self.try_incarnate(self.execution_idx.fetch_add(1, SeqCst));
+        if version.is_none() {
+            self.active_tasks.fetch_sub(1, SeqCst);
+        }
+        version
+    }
+
+    fn next_validation(&self) -> Option<(usize, usize)> {
+        if self.validation_idx.load(SeqCst) >= self.tx_count {
+            self.check_done();
+            return None;
+        }
+        self.active_tasks.fetch_add(1, SeqCst);
+        let tx_index = self.validation_idx.fetch_add(1, SeqCst);
+        if tx_index < self.tx_count {
+            let (incarnation, status) = *lock(&self.status[tx_index]);
+            if status == Status::Executed {
+                return Some((tx_index, incarnation));
+            }
+        }
+        self.active_tasks.fetch_sub(1, SeqCst);
+        None
+    }
+
+    fn try_incarnate(&self, tx_index: usize) -> Option<(usize, usize)> {
+        let mut status = lock(self.status.get(tx_index)?);
+        if status.1 != Status::ReadyToExecute {
+            return None;
+        }
+        status.1 = Status::Executing;
+        Some((tx_index, status.0))
+    }
+
+    /// Done once both indices passed the block with no task in flight and no
+    /// index moved back meanwhile
+    fn check_done(&self) {
+        let observed = self.decrease_cnt.load(SeqCst);
+        let lowest = self.execution_idx.load(SeqCst).min(self.validation_idx.load(SeqCst));
+        if lowest >= self.tx_count && self.active_tasks.load(SeqCst) == 0 && observed == self.decrease_cnt.load(SeqCst) {
+            self.done.store(true, SeqCst);
+        }
+    }
+
+    fn decrease_execution_idx(&self, target: usize) {
+        self.execution_idx.fetch_min(target, SeqCst);
+        self.decrease_cnt.fetch_add(1, SeqCst);
+    }
+
+    fn decrease_validation_idx(&self, target: usize) {
+        self.validation_idx.fetch_min(target, SeqCst);
+        self.decrease_cnt.fetch_add(1, SeqCst);
+    }
+
+    /// Park `tx_index` until `blocking` finishes executing; false if it already has
+    fn add_dependency(&self, tx_index: usize, blocking: usize) -> bool {
+        {
+            let mut dependents = lock(&self.dependents[blocking]);
+            if lock(&self.status[blocking]).1 == Status::Executed {
+                return false;
+            }
+            lock(&self.status[tx_index]).1 = Status::Aborting;
+            dependents.push(tx_index);
+        }
+        self.active_tasks.fetch_sub(1, SeqCst);
+        true
+    }
+
+    fn set_ready(&self, tx_index: usize) {
+        let mut status = lock(&self.status[tx_index]);
+        *status = (status.0 + 1, Status::ReadyToExecute);
+    }
+
+    fn finish_execution(&self, tx_index: usize, incarnation: usize, wrote_new_account: bool) -> Option<Task> {
+        lock(&self.status[tx_index]).1 = Status::Executed;
+        let dependents = std::mem::take(&mut *lock(&self.dependents[tx_index]));
+        if let Some(&lowest) = dependents.iter().min() {
+            for &dependent in &dependents {
+                self.set_ready(dependent);
+            }
+            self.decrease_execution_idx(lowest);
+        }
+        if self.validation_idx.load(SeqCst) > tx_index {
+            if !wrote_new_account {
+                return Some(Task::Validate(tx_index, incarnation));
+            }
+            // Higher transactions may have read around the new write
+            self.decrease_validation_idx(tx_index);
+        }
+        self.active_tasks.fetch_sub(1, SeqCst);
+        None
+    }
+
+    fn try_validation_abort(&self, tx_index: usize, incarnation: usize) -> bool {
+        let mut status = lock(&self.status[tx_index]);
+        if *status != (incarnation, Status::Executed) {
+            return false;
+        }
+        status.1 = Status::Aborting;
+        true
+    }
+
+    fn finish_validation(&self, tx_index: usize, aborted: bool) -> Option<Task> {
+        if aborted {
+            self.set_ready(tx_index);
+            self.decrease_validation_idx(tx_index + 1);
+            if self.execution_idx.load(SeqCst) > tx_index {
+                if let Some((tx, incarnation)) = self.try_incarnate(tx_index) {
+                    return Some(Task::Execute(tx, incarnation));
+                }
+            }
+        }
+        self.active_tasks.fetch_sub(1, SeqCst);
+        None
+    }
+}
+
+/// Shared state of one block's parallel execution
+struct BlockStm<'a> {
+    base: &'a State,
+    txs: &'a [SignedTransaction],
+    chain_id: u64,
+    base_fee: u128,
+    verified: Vec<OnceLock<Result<(), TransactionError>>>, // Signature checks, once per tx
+    memory: MvMemory,
+    scheduler: Scheduler,
+    outputs: Vec<Mutex<Option<TxOutput>>>,
+}
+
+impl<'a> BlockStm<'a> {
+    fn new(base: &'a State, txs: &'a [SignedTransaction], chain_id: u64, base_fee: u128) -> Self {
+        BlockStm {
+            base,
+            txs,
+            chain_id,
+            base_fee,
+            verified: (0..txs.len()).map(|_| OnceLock::new()).collect(),
+            memory: MvMemory::new(txs.len()),
+            scheduler: Scheduler::new(txs.len()),
+            outputs: (0..txs.len()).map(|_| Mutex::new(None)).collect(),
+        }
+    }
+
+    /// Worker loop: run tasks until the scheduler reports the block done
+    fn work(&self) {
+        let mut task = None;
+        while !self.scheduler.done() {
+            task = match task {
+                Some(Task::Execute(tx_index, incarnation)) => self.execute(tx_index, incarnation),
+                Some(Task::Validate(tx_index, incarnation)) => self.validate(tx_index, incarnation),
+                None => {
+                    let next = self.scheduler.next_task();
+                    if next.is_none() {
+                        std::thread::yield_now();
+                    }
+                    next
+                }
+            };
+        }
+    }
+
+    fn execute(&self, tx_index: usize, incarnation: usize) -> Option<Task> {
+        let tx = &self.txs[tx_index];
+        loop {
+            let mut view = SpeculativeView {
+                base: self.base,
+                memory: &self.memory,
+                tx_index,
+                reads: vec![],
+                writes: vec![],
+                blocked_on: None,
+            };
+            let result = match self.verified[tx_index].get_or_init(|| tx.verify(self.chain_id)) {
+                Ok(()) => Executor::apply_transaction(&mut view, tx, self.base_fee),
+                Err(err) => Err(err.clone().into()),
+            };
+            if let Some(blocking) = view.blocked_on {
+                if self.scheduler.add_dependency(tx_index, blocking) {
+                    return None;
+                }
+                continue; // Dependency already resolved: run again right away
+            }
+
+            // A failed transaction writes nothing, but its reads decided the failure
+            let writes = if result.is_ok() { view.writes } else { vec![] };
+            let wrote_new_account = self.memory.record(tx_index, incarnation, view.reads, &writes);
+            *lock(&self.outputs[tx_index]) = Some(TxOutput { result, writes });
+            return self.scheduler.finish_execution(tx_index, incarnation, wrote_new_account);
+        }
+    }
+
+    fn validate(&self, tx_index: usize, incarnation: usize) -> Option<Task> {
+        let aborted = !self.memory.validate(tx_index) && self.scheduler.try_validation_abort(tx_index, incarnation);
+        if aborted {
+            self.memory.mark_estimates(tx_index);
+        }
+        self.scheduler.finish_validation(tx_index, aborted)
+    }
+
+    fn into_outputs(self) -> Vec<TxOutput> {
+        self.outputs
+            .into_iter()
+            .map(|output| {
+                output
+                    .into_inner()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .expect("every transaction is executed before the scheduler finishes")
+            })
+            .collect()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
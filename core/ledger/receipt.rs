@@ -2,9 +2,23 @@
 //!
 //! Records execution result of transactions, including status, gas used, and
 //! generated event logs. Essential for user feedback, contract tracing, and auditing.
+//!
+//! A `Bloom` summarizes the addresses and topics of a set of logs in 2048 bits:
+//! each item sets three bits picked from its SHA3-256 hash. Block headers carry
+//! the bloom of their receipts, so log queries can skip blocks that certainly
+//! hold no match (a set bit pattern may still be a false positive).
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// Size of a log bloom in bytes (2048 bits)
+pub const BLOOM_BYTES: usize = 256;
+
+/// Bits set per bloom item
+const BLOOM_HASHES: usize = 3;
 
 /// Transaction receipt structure: result of transaction execution
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Receipt {
     pub tx_hash: Vec<u8>,
     pub status: bool,           // Success (true) or failure (false)
@@ -13,7 +27,7 @@ pub struct Receipt {
 }
 
 /// Event log structure: for smart contract events, transfers, DAO actions, etc.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EventLog {
     pub address: Vec<u8>,       // Address emitting the event (contract or account)
     pub topics: Vec<Vec<u8>>,   // Indexed event parameters (for filtering)
@@ -40,6 +54,15 @@ impl Receipt {
             logs,
         }
     }
+
+    /// Bloom over the addresses and topics of this receipt's logs
+    pub fn bloom(&self) -> Bloom {
+        let mut bloom = Bloom::new();
+        for log in &self.logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
 }
 
 impl EventLog {
@@ -52,3 +75,79 @@ impl EventLog {
         }
     }
 }
+
+/// Log bloom filter over event addresses and topics
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Bloom(Vec<u8>);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom(vec![0u8; BLOOM_BYTES])
+    }
+}
+
+impl Bloom {
+    /// Empty bloom (matches nothing)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bloom from its raw bytes (e.g. `BlockHeader::logs_bloom`); None unless exactly `BLOOM_BYTES` long
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == BLOOM_BYTES).then(|| Bloom(bytes.to_vec()))
+    }
+
+    /// Combined bloom of a block's receipts
+    pub fn from_receipts(receipts: &[Receipt]) -> Self {
+        let mut bloom = Bloom::new();
+        for receipt in receipts {
+            bloom.accrue_bloom(&receipt.bloom());
+        }
+        bloom
+    }
+
+    /// Add one item (an address or a topic)
+    pub fn accrue(&mut self, item: &[u8]) {
+        for (byte, mask) in Self::bits(item) {
+            self.0[byte] |= mask;
+        }
+    }
+
+    /// Add a log's address and every topic
+    pub fn accrue_log(&mut self, log: &EventLog) {
+        self.accrue(&log.address);
+        for topic in &log.topics {
+            self.accrue(topic);
+        }
+    }
+
+    /// Union with another bloom
+    pub fn accrue_bloom(&mut self, other: &Bloom) {
+        for (byte, other) in self.0.iter_mut().zip(&other.0) {
+            *byte |= other;
+        }
+    }
+
+    /// False means `item` is certainly absent; true means it may be present
+    pub fn contains(&self, item: &[u8]) -> bool {
+        Self::bits(item).all(|(byte, mask)| self.0[byte] & mask == mask)
+    }
+
+    /// No bit set
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Byte index and mask of each bit an item sets: three 11-bit indices from its hash
+    fn bits(item: &[u8]) -> impl Iterator<Item = (usize, u8)> {
+        let hash = Sha3_256::digest(item);
+        (0..BLOOM_HASHES).map(move |i| {
+            let bit = (u16::from_be_bytes([hash[2 * i], hash[2 * i + 1]]) & 0x07ff) as usize;
+            (BLOOM_BYTES - 1 - bit / 8, 1u8 << (bit % 8))
+        })
+    }
+}
@@ -2,58 +2,366 @@
 //!
 //! Enables creation, storage, loading and verification of blockchain state snapshots.
 //! Used for fast sync, backup/restore, DAO audit, and chain data export.
+//!
+//! A snapshot holds the full state (accounts and storage) after a finalized
+//! block, sorted by key and packed into zstd-compressed chunks of at most
+//! `chunk_size` uncompressed bytes. The manifest lists the block, the state root
+//! and the hash of every chunk, so chunks can be fetched and verified one by one.
+//! `SnapshotImporter` only returns a state whose rebuilt root equals the
+//! manifest's; the manifest itself is checked against a `SnapshotTrust` anchor:
+//! either a trusted checkpoint (block hash and post-state root), or the header
+//! of the next block verified by a light client, whose parent hash names the
+//! snapshot block and whose state root commits its post-state.
 
-use crate::ledger::state::State;
-use std::collections::HashMap;
+use crate::ledger::block::{Block, BlockHeader};
+use crate::ledger::state::{AccountState, State};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
-/// Snapshot struct: Immutable copy of the ledger state at a given block height
-#[derive(Debug, Clone)]
-pub struct Snapshot {
-    pub block_height: u64,                     // Snapshot alınan blokun nömrəsi
-    pub state_root: Vec<u8>,                   // Merkle root və ya state root hash
-    pub accounts: HashMap<Vec<u8>, AccountSnapshot>, // Bütün account və balanslar
-    pub metadata: Option<HashMap<String, String>>,   // Əlavə audit və info üçün
+pub const SNAPSHOT_VERSION: u32 = 1;
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Upper bound for a chunk's uncompressed size (a single oversized entry may exceed `chunk_size`)
+pub const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+const MAGIC: &[u8; 8] = b"OCOSSNAP";
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+const ENTRY_ACCOUNT: u8 = 0;
+const ENTRY_STORAGE: u8 = 1;
+
+/// Snapshot export/import errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("Snapshot I/O error: {0}")]
+    Io(String),
+    #[error("Malformed snapshot: {0}")]
+    Malformed(String),
+    #[error("Unsupported snapshot version {0}")]
+    UnsupportedVersion(u32),
+    #[error("State has uncommitted writes")]
+    UncommittedState,
+    #[error("Chunk {0} is not in the manifest")]
+    UnknownChunk(usize),
+    #[error("Chunk {0} does not match its manifest hash")]
+    ChunkHashMismatch(usize),
+    #[error("Chunk {0} was not imported")]
+    MissingChunk(usize),
+    #[error("Rebuilt state root does not match the manifest")]
+    StateRootMismatch,
+    #[error("Snapshot block does not match the trusted block")]
+    UntrustedBlock,
+    #[error("Snapshot state root does not match the trusted state root")]
+    UntrustedStateRoot,
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        SnapshotError::Io(err.to_string())
+    }
+}
+
+impl SnapshotError {
+    fn malformed(reason: impl ToString) -> Self {
+        SnapshotError::Malformed(reason.to_string())
+    }
+}
+
+/// Bir chunk-ın manifestdəki qeydi
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub hash: Vec<u8>, // Sıxılmış baytların SHA3-256 hash-i
+    pub raw_len: u64,  // Açılmış ölçü
+    pub entries: u64,  // Account və storage qeydlərinin sayı
+}
+
+/// Snapshot manifesti: blok, state root və chunk hash-ları
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub block: Block,                        // Snapshot alınan finalized blok
+    pub state_root: Vec<u8>,                 // Blokdan sonrakı state root
+    pub chunk_size: u64,
+    pub chunks: Vec<ChunkInfo>,
+    pub metadata: BTreeMap<String, String>,  // Əlavə audit və info üçün
+}
+
+impl SnapshotManifest {
+    pub fn height(&self) -> u64 {
+        self.block.height()
+    }
+
+    pub fn block_hash(&self) -> Vec<u8> {
+        self.block.hash()
+    }
+
+    /// Manifestin bloku və state root-u etibarlı mənbə ilə üst-üstə düşürmü
+    pub fn verify(&self, trust: &SnapshotTrust) -> Result<(), SnapshotError> {
+        let (block_hash, height, state_root) = match trust {
+            SnapshotTrust::Checkpoint { block_hash, state_root } => (block_hash, self.height(), state_root),
+            SnapshotTrust::ChildHeader(child) => (&child.parent_hash, child.height.wrapping_sub(1), &child.state_root),
+        };
+        if self.block_hash() != *block_hash || self.height() != height {
+            return Err(SnapshotError::UntrustedBlock);
+        }
+        if self.state_root != *state_root {
+            return Err(SnapshotError::UntrustedStateRoot);
+        }
+        Ok(())
+    }
+}
+
+/// Snapshot-ın yoxlandığı etibarlı mənbə
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotTrust {
+    /// Kənar kanaldan alınmış checkpoint (məs. release-də sabitlənmiş): blok hash-i və ondan sonrakı state root
+    Checkpoint { block_hash: Vec<u8>, state_root: Vec<u8> },
+    /// Light client-in yoxladığı növbəti blokun header-i (`LightClient::verified_header(height + 1)`)
+    ChildHeader(BlockHeader),
 }
 
+/// Snapshot struct: manifest və sıxılmış chunk-lar
 #[derive(Debug, Clone)]
-pub struct AccountSnapshot {
-    pub balance: u128,
-    pub nonce: u64,
-    pub code_hash: Option<Vec<u8>>,
-    pub storage_root: Option<Vec<u8>>,
+pub struct Snapshot {
+    pub manifest: SnapshotManifest,
+    pub chunks: Vec<Vec<u8>>,
 }
 
 impl Snapshot {
-    /// State-dən snapshot yarat (blok nömrəsi və root hash daxilində)
-    pub fn from_state(height: u64, state_root: Vec<u8>, state: &State) -> Self {
-        let mut accounts = HashMap::new();
-        for (addr, acc) in &state.accounts {
-            accounts.insert(addr.clone(), AccountSnapshot {
-                balance: acc.balance,
-                nonce: acc.nonce,
-                code_hash: acc.code_hash.clone(),
-                storage_root: acc.storage_root.clone(),
-            });
-        }
-        Snapshot {
-            block_height: height,
-            state_root,
-            accounts,
-            metadata: None,
-        }
-    }
-
-    /// Snapshot-ı yüklə və state-ə bərpa et
-    pub fn restore_state(&self) -> State {
-        let mut state = State::new();
-        for (addr, acc_snap) in &self.accounts {
-            state.update_account(addr.clone(), crate::ledger::state::AccountState {
-                balance: acc_snap.balance,
-                nonce: acc_snap.nonce,
-                code_hash: acc_snap.code_hash.clone(),
-                storage_root: acc_snap.storage_root.clone(),
-            });
-        }
-        state
+    /// Finalized blokdan sonrakı (commit olunmuş) state-dən snapshot yarat
+    pub fn from_state(block: Block, state: &State) -> Result<Self, SnapshotError> {
+        Self::with_chunk_size(block, state, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Chunk ölçüsünü seçərək snapshot yarat
+    pub fn with_chunk_size(block: Block, state: &State, chunk_size: usize) -> Result<Self, SnapshotError> {
+        if state.has_uncommitted() {
+            return Err(SnapshotError::UncommittedState);
+        }
+        let mut accounts: Vec<_> = state.accounts.iter().collect();
+        accounts.sort_by(|a, b| a.0.cmp(b.0));
+        let mut storage: Vec<_> = state.storage.iter().collect();
+        storage.sort_by(|a, b| a.0.cmp(b.0));
+        let entries = accounts
+            .into_iter()
+            .map(|(address, account)| (ENTRY_ACCOUNT, address.as_slice(), account.encode()))
+            .chain(storage.into_iter().map(|(key, value)| (ENTRY_STORAGE, key.as_slice(), value.clone())));
+
+        let mut chunks = vec![];
+        let mut infos = vec![];
+        let mut raw = vec![];
+        let mut count = 0u64;
+        for entry in entries {
+            let encoded = bincode::serialize(&entry).map_err(SnapshotError::malformed)?;
+            if !raw.is_empty() && raw.len() + encoded.len() > chunk_size {
+                Self::seal_chunk(&mut raw, &mut count, &mut chunks, &mut infos)?;
+            }
+            raw.extend_from_slice(&encoded);
+            count += 1;
+        }
+        if !raw.is_empty() {
+            Self::seal_chunk(&mut raw, &mut count, &mut chunks, &mut infos)?;
+        }
+
+        Ok(Snapshot {
+            manifest: SnapshotManifest {
+                version: SNAPSHOT_VERSION,
+                block,
+                state_root: state.state_root(),
+                chunk_size: chunk_size as u64,
+                chunks: infos,
+                metadata: BTreeMap::new(),
+            },
+            chunks,
+        })
+    }
+
+    /// Manifesti etibarlı mənbə ilə, sonra bütün chunk-ları yoxla və state-i bərpa et
+    pub fn restore_trusted(&self, trust: &SnapshotTrust) -> Result<State, SnapshotError> {
+        self.manifest.verify(trust)?;
+        self.restore_state()
+    }
+
+    /// Bütün chunk-ları yoxla və state-i bərpa et (manifestin özü yoxlanmır)
+    pub fn restore_state(&self) -> Result<State, SnapshotError> {
+        if self.chunks.len() != self.manifest.chunks.len() {
+            return Err(SnapshotError::MissingChunk(self.chunks.len().min(self.manifest.chunks.len())));
+        }
+        let mut importer = SnapshotImporter::new(self.manifest.clone())?;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            importer.import_chunk(index, chunk)?;
+        }
+        importer.finish()
+    }
+
+    /// Fayl formatı: magic, manifest, sonra hər chunk (uzunluq + baytlar)
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        let manifest = bincode::serialize(&self.manifest).map_err(SnapshotError::malformed)?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(manifest.len() as u64).to_be_bytes())?;
+        writer.write_all(&manifest)?;
+        for chunk in &self.chunks {
+            writer.write_all(&(chunk.len() as u64).to_be_bytes())?;
+            writer.write_all(chunk)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// `write_to` ilə yazılmış snapshot-ı oxu (chunk-lar hələ yoxlanmayıb)
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(SnapshotError::malformed("not a snapshot file"));
+        }
+        let manifest = read_section(&mut reader, MAX_MANIFEST_SIZE)?;
+        let manifest: SnapshotManifest = bincode::deserialize(&manifest).map_err(SnapshotError::malformed)?;
+        if manifest.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(manifest.version));
+        }
+        let chunks = manifest.chunks
+            .iter()
+            .map(|info| read_section(&mut reader, max_compressed_len(info.raw_len.min(MAX_CHUNK_SIZE))))
+            .collect::<Result<_, _>>()?;
+        Ok(Snapshot { manifest, chunks })
+    }
+
+    /// Snapshot-ı fayla yaz
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Snapshot-ı fayldan oxu
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    fn seal_chunk(raw: &mut Vec<u8>, count: &mut u64, chunks: &mut Vec<Vec<u8>>, infos: &mut Vec<ChunkInfo>) -> Result<(), SnapshotError> {
+        let compressed = zstd::bulk::compress(raw, COMPRESSION_LEVEL)?;
+        infos.push(ChunkInfo {
+            hash: Sha3_256::digest(&compressed).to_vec(),
+            raw_len: raw.len() as u64,
+            entries: *count,
+        });
+        chunks.push(compressed);
+        raw.clear();
+        *count = 0;
+        Ok(())
+    }
+}
+
+/// Chunk-ları istənilən ardıcıllıqla qəbul edib state-i yığan importer
+pub struct SnapshotImporter {
+    manifest: SnapshotManifest,
+    state: State,
+    imported: Vec<bool>,
+}
+
+impl SnapshotImporter {
+    pub fn new(manifest: SnapshotManifest) -> Result<Self, SnapshotError> {
+        if manifest.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(manifest.version));
+        }
+        if manifest.chunks.iter().any(|c| c.raw_len > MAX_CHUNK_SIZE) {
+            return Err(SnapshotError::malformed("chunk exceeds the maximum size"));
+        }
+        let imported = vec![false; manifest.chunks.len()];
+        Ok(SnapshotImporter { manifest, state: State::new(), imported })
+    }
+
+    pub fn manifest(&self) -> &SnapshotManifest {
+        &self.manifest
+    }
+
+    /// Chunk-ın manifestdəki hash-la uyğunluğunu yoxla
+    pub fn verify_chunk(&self, index: usize, chunk: &[u8]) -> Result<(), SnapshotError> {
+        let info = self.manifest.chunks.get(index).ok_or(SnapshotError::UnknownChunk(index))?;
+        if Sha3_256::digest(chunk).as_slice() != info.hash.as_slice() {
+            return Err(SnapshotError::ChunkHashMismatch(index));
+        }
+        Ok(())
+    }
+
+    /// Chunk-ı yoxla, aç və qeydlərini state-ə yaz (hamısı və ya heç biri)
+    pub fn import_chunk(&mut self, index: usize, chunk: &[u8]) -> Result<(), SnapshotError> {
+        self.verify_chunk(index, chunk)?;
+        if self.imported[index] {
+            return Ok(());
+        }
+        let info = &self.manifest.chunks[index];
+        let raw = zstd::bulk::decompress(chunk, info.raw_len as usize)?;
+        if raw.len() as u64 != info.raw_len {
+            return Err(SnapshotError::malformed(format!("chunk {} has the wrong size", index)));
+        }
+
+        let mut entries = vec![];
+        let mut rest = raw.as_slice();
+        while !rest.is_empty() {
+            let (kind, key, value): (u8, Vec<u8>, Vec<u8>) =
+                bincode::deserialize_from(&mut rest).map_err(SnapshotError::malformed)?;
+            entries.push((kind, key, value));
+        }
+        if entries.len() as u64 != info.entries {
+            return Err(SnapshotError::malformed(format!("chunk {} has the wrong entry count", index)));
+        }
+        let mut accounts = vec![];
+        let mut storage = vec![];
+        for (kind, key, value) in entries {
+            match kind {
+                ENTRY_ACCOUNT => {
+                    let account = AccountState::decode(&value)
+                        .ok_or_else(|| SnapshotError::malformed("undecodable account"))?;
+                    accounts.push((key, account));
+                }
+                ENTRY_STORAGE => storage.push((key, value)),
+                other => return Err(SnapshotError::malformed(format!("unknown entry kind {}", other))),
+            }
+        }
+
+        for (address, account) in accounts {
+            self.state.update_account(address, account);
+        }
+        for (key, value) in storage {
+            self.state.set_storage(key, value);
+        }
+        self.imported[index] = true;
+        Ok(())
+    }
+
+    /// Hələ import olunmamış chunk-ların indeksləri
+    pub fn missing_chunks(&self) -> Vec<usize> {
+        self.imported.iter().enumerate().filter(|(_, done)| !**done).map(|(i, _)| i).collect()
+    }
+
+    /// Bütün chunk-lar gəldikdən sonra state root-u manifestlə müqayisə et
+    pub fn finish(mut self) -> Result<State, SnapshotError> {
+        if let Some(&index) = self.missing_chunks().first() {
+            return Err(SnapshotError::MissingChunk(index));
+        }
+        if self.state.commit() != self.manifest.state_root {
+            return Err(SnapshotError::StateRootMismatch);
+        }
+        Ok(self.state)
+    }
+}
+
+/// zstd-nin ən pis halda sıxılmış ölçüsü üçün yuxarı hədd
+fn max_compressed_len(raw_len: u64) -> u64 {
+    raw_len + raw_len / 128 + 1024
+}
+
+fn read_section<R: Read>(reader: &mut R, max_len: u64) -> Result<Vec<u8>, SnapshotError> {
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_be_bytes(len);
+    if len > max_len {
+        return Err(SnapshotError::malformed("section exceeds its size limit"));
     }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
 }
@@ -2,14 +2,59 @@
 //!
 //! Manages blockchain state: account balances, storage, state proofs, and delta updates.
 //! Designed for secure, auditable, and deterministic ledger state operations.
+//!
+//! Accounts and storage are mirrored into a sparse Merkle tree. Writes mark keys
+//! dirty; `commit` folds them into the tree and returns the new state root, so
+//! only keys touched by a block are rehashed. Proofs reflect the last commit.
+//! Committed keys are also queued for the key-value store (`take_write_batch`),
+//! and `load` rebuilds the state and its trie from the store after a restart.
+//!
+//! Inside a `checkpoint`, every write journals the previous value, so a failed
+//! transaction is undone with `revert_to` and `changes_since` reports the exact
+//! net change of every account and storage key touched. `diff_since` keeps both
+//! sides of each change, which fork choice uses to move one state between blocks.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::ledger::kv_store::{Column, KvError, KvStore, WriteBatch};
+use crate::ledger::merkle::{MerkleProof, MerkleTree};
 
-use std::collections::HashMap;
+const ACCOUNT_DOMAIN: &[u8] = b"OCOS-ACCOUNT";
+const STORAGE_DOMAIN: &[u8] = b"OCOS-STORAGE";
 
 /// State struct: Main mapping of account addresses to their state
 #[derive(Debug, Clone)]
 pub struct State {
     pub accounts: HashMap<Vec<u8>, AccountState>, // address → state
     pub storage: HashMap<Vec<u8>, Vec<u8>>,       // generic key-value storage (optional)
+    trie: MerkleTree,                             // Authenticated copy as of the last commit
+    dirty_accounts: HashSet<Vec<u8>>,
+    dirty_storage: HashSet<Vec<u8>>,
+    unsaved_accounts: HashSet<Vec<u8>>, // Committed but not yet handed to the store
+    unsaved_storage: HashSet<Vec<u8>>,
+    journal: Vec<JournalEntry>, // Previous values of writes made under open checkpoints
+    open_checkpoints: usize,
+}
+
+/// Undo record of one write
+#[derive(Debug, Clone)]
+enum JournalEntry {
+    Account(Vec<u8>, Option<AccountState>),
+    Storage(Vec<u8>, Option<Vec<u8>>),
+}
+
+/// Journal position to revert to or report changes since
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    journal_len: usize,
+    depth: usize,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl State {
@@ -18,7 +63,31 @@ impl State {
         Self {
             accounts: HashMap::new(),
             storage: HashMap::new(),
+            trie: MerkleTree::new(),
+            dirty_accounts: HashSet::new(),
+            dirty_storage: HashSet::new(),
+            unsaved_accounts: HashSet::new(),
+            unsaved_storage: HashSet::new(),
+            journal: vec![],
+            open_checkpoints: 0,
+        }
+    }
+
+    /// Rebuild the state (and its trie) from the store
+    pub fn load(store: &dyn KvStore) -> Result<Self, KvError> {
+        let mut state = State::new();
+        for (address, bytes) in store.iter(Column::Accounts)? {
+            let account = AccountState::decode(&bytes)
+                .ok_or_else(|| KvError::corrupted(Column::Accounts, "undecodable account"))?;
+            state.update_account(address, account);
         }
+        for (key, value) in store.iter(Column::Storage)? {
+            state.set_storage(key, value);
+        }
+        state.commit();
+        state.unsaved_accounts.clear();
+        state.unsaved_storage.clear();
+        Ok(state)
     }
 
     /// Get account state for an address
@@ -28,32 +97,234 @@ impl State {
 
     /// Mutably get account state for updating
     pub fn get_account_mut(&mut self, address: &[u8]) -> Option<&mut AccountState> {
+        self.touch_account(address);
         self.accounts.get_mut(address)
     }
 
     /// Update or insert an account state
     pub fn update_account(&mut self, address: Vec<u8>, account: AccountState) {
+        self.touch_account(&address);
         self.accounts.insert(address, account);
     }
 
     /// Delete an account (used in dust sweeping, account removal)
     pub fn remove_account(&mut self, address: &[u8]) {
+        self.touch_account(address);
         self.accounts.remove(address);
     }
 
     /// Update key-value storage
     pub fn set_storage(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.touch_storage(&key);
         self.storage.insert(key, value);
     }
 
+    /// Open a checkpoint; writes from here on can be reverted
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.open_checkpoints += 1;
+        Checkpoint { journal_len: self.journal.len(), depth: self.open_checkpoints - 1 }
+    }
+
+    /// Undo every write since `checkpoint` and close it (and any checkpoint opened after it)
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        while self.journal.len() > checkpoint.journal_len {
+            match self.journal.pop() {
+                Some(JournalEntry::Account(address, previous)) => {
+                    self.dirty_accounts.insert(address.clone());
+                    match previous {
+                        Some(account) => self.accounts.insert(address, account),
+                        None => self.accounts.remove(&address),
+                    };
+                }
+                Some(JournalEntry::Storage(key, previous)) => {
+                    self.dirty_storage.insert(key.clone());
+                    match previous {
+                        Some(value) => self.storage.insert(key, value),
+                        None => self.storage.remove(&key),
+                    };
+                }
+                None => break,
+            }
+        }
+        self.close(checkpoint);
+    }
+
+    /// Keep the writes since `checkpoint` and close it; they stay revertible
+    /// through any enclosing checkpoint
+    pub fn release(&mut self, checkpoint: Checkpoint) {
+        self.close(checkpoint);
+    }
+
+    /// Net change of every account and storage key written since `checkpoint`,
+    /// in the order they were first touched
+    pub fn changes_since(&self, checkpoint: Checkpoint) -> StateUpdate {
+        let mut accounts: Vec<(&Vec<u8>, &Option<AccountState>)> = vec![];
+        let mut storage: Vec<(&Vec<u8>, &Option<Vec<u8>>)> = vec![];
+        let mut seen_accounts = HashSet::new();
+        let mut seen_storage = HashSet::new();
+        for entry in &self.journal[checkpoint.journal_len.min(self.journal.len())..] {
+            match entry {
+                JournalEntry::Account(address, previous) if seen_accounts.insert(address) => accounts.push((address, previous)),
+                JournalEntry::Storage(key, previous) if seen_storage.insert(key) => storage.push((key, previous)),
+                _ => {}
+            }
+        }
+
+        let mut update = StateUpdate { account_deltas: vec![], storage_deltas: vec![] };
+        for (address, before) in accounts {
+            let after = self.accounts.get(address);
+            for delta in AccountDelta::between(before.as_ref(), after) {
+                update.account_deltas.push((address.clone(), delta));
+            }
+        }
+        for (key, before) in storage {
+            if let Some(value) = self.storage.get(key).filter(|v| Some(*v) != before.as_ref()) {
+                update.storage_deltas.push((key.clone(), value.clone()));
+            }
+        }
+        update
+    }
+
+    /// Values before and after every account and storage key written since
+    /// `checkpoint` (keys written back to their old value are left out)
+    pub fn diff_since(&self, checkpoint: Checkpoint) -> StateDiff {
+        let mut diff = StateDiff::default();
+        let mut seen_accounts = HashSet::new();
+        let mut seen_storage = HashSet::new();
+        for entry in &self.journal[checkpoint.journal_len.min(self.journal.len())..] {
+            match entry {
+                JournalEntry::Account(address, before) if seen_accounts.insert(address) => {
+                    let after = self.accounts.get(address);
+                    if before.as_ref() != after {
+                        diff.accounts.push((address.clone(), before.clone(), after.cloned()));
+                    }
+                }
+                JournalEntry::Storage(key, before) if seen_storage.insert(key) => {
+                    let after = self.storage.get(key);
+                    if before.as_ref() != after {
+                        diff.storage.push((key.clone(), before.clone(), after.cloned()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        diff
+    }
+
+    /// Write the `after` side of `diff` (`forward`) or restore its `before` side;
+    /// like any write, the change reaches the trie on the next `commit`
+    pub fn apply_diff(&mut self, diff: &StateDiff, forward: bool) {
+        for (address, before, after) in &diff.accounts {
+            let value = if forward { after } else { before };
+            self.touch_account(address);
+            match value {
+                Some(account) => self.accounts.insert(address.clone(), account.clone()),
+                None => self.accounts.remove(address),
+            };
+        }
+        for (key, before, after) in &diff.storage {
+            let value = if forward { after } else { before };
+            self.touch_storage(key);
+            match value {
+                Some(value) => self.storage.insert(key.clone(), value.clone()),
+                None => self.storage.remove(key),
+            };
+        }
+    }
+
+    fn close(&mut self, checkpoint: Checkpoint) {
+        self.open_checkpoints = checkpoint.depth;
+        if self.open_checkpoints == 0 {
+            self.journal.clear();
+        }
+    }
+
+    fn touch_account(&mut self, address: &[u8]) {
+        self.dirty_accounts.insert(address.to_vec());
+        if self.open_checkpoints > 0 {
+            self.journal.push(JournalEntry::Account(address.to_vec(), self.accounts.get(address).cloned()));
+        }
+    }
+
+    fn touch_storage(&mut self, key: &[u8]) {
+        self.dirty_storage.insert(key.to_vec());
+        if self.open_checkpoints > 0 {
+            self.journal.push(JournalEntry::Storage(key.to_vec(), self.storage.get(key).cloned()));
+        }
+    }
+
     /// Get from key-value storage
     pub fn get_storage(&self, key: &[u8]) -> Option<&Vec<u8>> {
         self.storage.get(key)
     }
+
+    /// Fold every write since the last commit into the trie; returns the new state root
+    pub fn commit(&mut self) -> Vec<u8> {
+        for address in std::mem::take(&mut self.dirty_accounts) {
+            let value = self.accounts.get(&address).map(AccountState::encode);
+            self.trie.update(MerkleTree::path_of(ACCOUNT_DOMAIN, &address), value);
+            self.unsaved_accounts.insert(address);
+        }
+        for key in std::mem::take(&mut self.dirty_storage) {
+            let value = self.storage.get(&key).cloned();
+            self.trie.update(MerkleTree::path_of(STORAGE_DOMAIN, &key), value);
+            self.unsaved_storage.insert(key);
+        }
+        self.trie.root()
+    }
+
+    /// Store writes (current values, deletes for removed keys) for everything
+    /// committed since the last call
+    pub fn take_write_batch(&mut self) -> WriteBatch {
+        let mut batch = WriteBatch::new();
+        for address in std::mem::take(&mut self.unsaved_accounts) {
+            match self.accounts.get(&address) {
+                Some(account) => batch.put(Column::Accounts, address, account.encode()),
+                None => batch.delete(Column::Accounts, address),
+            }
+        }
+        for key in std::mem::take(&mut self.unsaved_storage) {
+            match self.storage.get(&key) {
+                Some(value) => batch.put(Column::Storage, key.clone(), value.clone()),
+                None => batch.delete(Column::Storage, key),
+            }
+        }
+        batch
+    }
+
+    /// State root as of the last commit
+    pub fn state_root(&self) -> Vec<u8> {
+        self.trie.root()
+    }
+
+    /// True if writes happened since the last commit
+    pub fn has_uncommitted(&self) -> bool {
+        !self.dirty_accounts.is_empty() || !self.dirty_storage.is_empty()
+    }
+
+    /// Proof of an account (or of its absence) as of the last commit
+    pub fn prove_account(&self, address: &[u8]) -> AccountProof {
+        let proof = self.trie.prove(&MerkleTree::path_of(ACCOUNT_DOMAIN, address));
+        AccountProof {
+            address: address.to_vec(),
+            account: proof.value.as_deref().and_then(AccountState::decode),
+            proof,
+        }
+    }
+
+    /// Proof of a storage key (or of its absence) as of the last commit
+    pub fn prove_storage(&self, key: &[u8]) -> StorageProof {
+        let proof = self.trie.prove(&MerkleTree::path_of(STORAGE_DOMAIN, key));
+        StorageProof {
+            key: key.to_vec(),
+            value: proof.value.clone(),
+            proof,
+        }
+    }
 }
 
 /// Individual account state: balance, nonce, and custom fields
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccountState {
     pub balance: u128,
     pub nonce: u64,
@@ -72,26 +343,161 @@ impl AccountState {
             storage_root: None,
         }
     }
+
+    /// Canonical encoding committed in the state trie
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(24);
+        out.extend_from_slice(&self.balance.to_be_bytes());
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        for field in [&self.code_hash, &self.storage_root] {
+            match field {
+                Some(bytes) => {
+                    out.push(1);
+                    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                    out.extend_from_slice(bytes);
+                }
+                None => out.push(0),
+            }
+        }
+        out
+    }
+
+    /// Inverse of `encode` (None on malformed input)
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let balance = u128::from_be_bytes(bytes.get(..16)?.try_into().ok()?);
+        let nonce = u64::from_be_bytes(bytes.get(16..24)?.try_into().ok()?);
+        let mut rest = &bytes[24..];
+        let code_hash = take_optional(&mut rest)?;
+        let storage_root = take_optional(&mut rest)?;
+        if !rest.is_empty() {
+            return None;
+        }
+        Some(AccountState { balance, nonce, code_hash, storage_root })
+    }
 }
 
 /// State update struct: changes for a block/tx (delta)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StateUpdate {
     pub account_deltas: Vec<(Vec<u8>, AccountDelta)>, // address, changes
     pub storage_deltas: Vec<(Vec<u8>, Vec<u8>)>,      // key, value
 }
 
-#[derive(Debug, Clone)]
+/// Values before and after every key a block wrote, so a state can be moved
+/// across the block in either direction (`State::apply_diff`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: Vec<(Vec<u8>, Option<AccountState>, Option<AccountState>)>, // address, before, after
+    pub storage: Vec<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)>,            // key, before, after
+}
+
+impl StateDiff {
+    /// The account as it was before the diff, if the diff wrote it
+    pub fn account_before(&self, address: &[u8]) -> Option<&Option<AccountState>> {
+        self.accounts.iter().find(|(a, _, _)| a.as_slice() == address).map(|(_, before, _)| before)
+    }
+
+    /// Store writes (values after the diff, deletes for removed keys)
+    pub fn write_batch(&self) -> WriteBatch {
+        let mut batch = WriteBatch::new();
+        for (address, _, after) in &self.accounts {
+            match after {
+                Some(account) => batch.put(Column::Accounts, address.clone(), account.encode()),
+                None => batch.delete(Column::Accounts, address.clone()),
+            }
+        }
+        for (key, _, after) in &self.storage {
+            match after {
+                Some(value) => batch.put(Column::Storage, key.clone(), value.clone()),
+                None => batch.delete(Column::Storage, key.clone()),
+            }
+        }
+        batch
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AccountDelta {
     BalanceChange(i128),
     NonceInc(u64),
     CodeChange(Vec<u8>),
     StorageRootChange(Vec<u8>),
+    Removed,
 }
 
-#[derive(Debug, Clone)]
+impl AccountDelta {
+    /// Deltas turning `before` into `after` (a missing account counts as empty)
+    pub fn between(before: Option<&AccountState>, after: Option<&AccountState>) -> Vec<AccountDelta> {
+        let Some(after) = after else {
+            return before.map(|_| vec![AccountDelta::Removed]).unwrap_or_default();
+        };
+        let empty = AccountState::new(0);
+        let before = before.unwrap_or(&empty);
+        let mut deltas = vec![];
+        if after.balance != before.balance {
+            deltas.push(AccountDelta::BalanceChange(after.balance as i128 - before.balance as i128));
+        }
+        if after.nonce > before.nonce {
+            deltas.push(AccountDelta::NonceInc(after.nonce - before.nonce));
+        }
+        if let Some(code) = after.code_hash.as_ref().filter(|c| Some(*c) != before.code_hash.as_ref()) {
+            deltas.push(AccountDelta::CodeChange(code.clone()));
+        }
+        if let Some(root) = after.storage_root.as_ref().filter(|r| Some(*r) != before.storage_root.as_ref()) {
+            deltas.push(AccountDelta::StorageRootChange(root.clone()));
+        }
+        deltas
+    }
+}
+
+/// Proof of an account's state (None = no such account) under a state root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountProof {
+    pub address: Vec<u8>,
+    pub account: Option<AccountState>,
+    pub proof: MerkleProof,
+}
+
+impl AccountProof {
+    /// Check the proof against `state_root` only
+    pub fn verify(&self, state_root: &[u8]) -> bool {
+        self.proof.path.as_slice() == MerkleTree::path_of(ACCOUNT_DOMAIN, &self.address)
+            && self.proof.value == self.account.as_ref().map(AccountState::encode)
+            && self.proof.verify(state_root)
+    }
+
+    /// Proven balance (0 for an absent account)
+    pub fn balance(&self) -> u128 {
+        self.account.as_ref().map(|a| a.balance).unwrap_or(0)
+    }
+}
+
+/// Proof of a storage value (None = unset) under a state root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StorageProof {
     pub key: Vec<u8>,
-    pub value: Vec<u8>,
-    pub merkle_proof: Vec<Vec<u8>>, // Merkle path for proof
+    pub value: Option<Vec<u8>>,
+    pub proof: MerkleProof,
+}
+
+impl StorageProof {
+    /// Check the proof against `state_root` only
+    pub fn verify(&self, state_root: &[u8]) -> bool {
+        self.proof.path.as_slice() == MerkleTree::path_of(STORAGE_DOMAIN, &self.key)
+            && self.proof.value == self.value
+            && self.proof.verify(state_root)
+    }
+}
+
+/// Read one `encode`d optional field, advancing `bytes`
+fn take_optional(bytes: &mut &[u8]) -> Option<Option<Vec<u8>>> {
+    let (&tag, rest) = bytes.split_first()?;
+    if tag == 0 {
+        *bytes = rest;
+        return Some(None);
+    }
+    let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+    let value = rest.get(4..4 + len)?.to_vec();
+    *bytes = &rest[4 + len..];
+    Some(Some(value))
 }
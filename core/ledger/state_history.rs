@@ -0,0 +1,303 @@
+//! OCOS-Chain: Historical State (Pruned & Archive Modes)
+//!
+//! Records every version of each account and storage key written by finalized
+//! blocks, so state can be read as of a past height ("balance of X at H").
+//! Versions live in the `History` column only; a query is a reverse range scan
+//! for the newest version at or below the height, and just the oldest and latest
+//! heights are kept in memory.
+//!
+//! An archive node keeps every version. A pruned node keeps the last
+//! `keep_recent` heights plus the state at every `checkpoint_interval`-th
+//! height; other superseded versions are deleted and queries for them fail with
+//! `HistoryError::Pruned`. Trie nodes are out of scope: they are never persisted
+//! (`State::load` rebuilds the trie from live accounts) and the in-memory trie
+//! drops emptied nodes, so history versions are all there is to collect.
+
+use crate::ledger::kv_store::{Column, KvError, KvStore, MemoryStore, WriteBatch};
+use crate::ledger::state::{AccountState, State};
+use std::sync::Arc;
+
+/// Heights of history a pruned node keeps by default
+pub const DEFAULT_KEEP_RECENT: u64 = 256;
+
+/// Interval of the checkpoint heights a pruned node keeps by default
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10_000;
+
+const META_BASE: &[u8] = b"history_base";
+const META_OLDEST: &[u8] = b"history_oldest";
+const META_LATEST: &[u8] = b"history_latest";
+
+/// How much state history a node keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageMode {
+    /// Every version since genesis (or the snapshot the node started from)
+    Archive,
+    /// Versions of the last `keep_recent` finalized heights, plus the state at
+    /// every multiple of `checkpoint_interval` (0 = no checkpoints)
+    Pruned { keep_recent: u64, checkpoint_interval: u64 },
+}
+
+impl StorageMode {
+    /// Whether a pruned node still answers queries for `height` once it falls
+    /// below the recent window
+    pub fn is_checkpoint(&self, height: u64) -> bool {
+        match *self {
+            StorageMode::Pruned { checkpoint_interval, .. } if checkpoint_interval > 0 => height % checkpoint_interval == 0,
+            _ => false,
+        }
+    }
+
+    /// Highest checkpoint height strictly below `height`
+    fn checkpoint_below(&self, height: u64) -> Option<u64> {
+        match *self {
+            StorageMode::Pruned { checkpoint_interval, .. } if checkpoint_interval > 0 && height > 0 => {
+                Some((height - 1) / checkpoint_interval * checkpoint_interval)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for StorageMode {
+    fn default() -> Self {
+        StorageMode::Pruned { keep_recent: DEFAULT_KEEP_RECENT, checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL }
+    }
+}
+
+/// Historical query errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HistoryError {
+    #[error("State at height {requested} was pruned (oldest kept: {oldest})")]
+    Pruned { requested: u64, oldest: u64 },
+    #[error("Height {requested} is above the latest recorded height {latest}")]
+    Unknown { requested: u64, latest: u64 },
+    #[error("History storage error: {0}")]
+    Storage(#[from] KvError),
+}
+
+/// Heights a staged block moves the history to; applied with `StateHistory::advance`
+/// once its batch is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryBounds {
+    pub oldest: u64,
+    pub latest: u64,
+}
+
+/// Versioned account and storage values of finalized heights, read from a store
+pub struct StateHistory {
+    pub mode: StorageMode,
+    store: Arc<dyn KvStore>,
+    /// Height of the full state history started from (genesis or snapshot)
+    base: u64,
+    oldest: u64,
+    latest: u64,
+}
+
+impl StateHistory {
+    /// History over `store` starting at `height` with the full `state` as its
+    /// base; the base versions go to the store with `batch`
+    pub fn new(store: Arc<dyn KvStore>, mode: StorageMode, height: u64, state: &State, batch: &mut WriteBatch) -> Self {
+        for (address, account) in &state.accounts {
+            batch.put(Column::History, encode_row(Column::Accounts, address, height), encode_value(Some(account.encode().as_slice())));
+        }
+        for (key, value) in &state.storage {
+            batch.put(Column::History, encode_row(Column::Storage, key, height), encode_value(Some(value.as_slice())));
+        }
+        for key in [META_BASE, META_OLDEST, META_LATEST] {
+            batch.put(Column::Meta, key.to_vec(), height.to_be_bytes().to_vec());
+        }
+        StateHistory { mode, store, base: height, oldest: height, latest: height }
+    }
+
+    /// History over its own in-memory store, starting at `height` with `state`
+    pub fn in_memory(mode: StorageMode, height: u64, state: &State) -> Self {
+        let store = Arc::new(MemoryStore::new());
+        let mut batch = WriteBatch::new();
+        let history = Self::new(store.clone(), mode, height, state, &mut batch);
+        store.write(batch).expect("in-memory writes cannot fail");
+        history
+    }
+
+    /// Reopen history kept in `store`; only its height bounds are read
+    pub fn load(store: Arc<dyn KvStore>, mode: StorageMode) -> Result<Self, KvError> {
+        let meta_height = |key| -> Result<Option<u64>, KvError> {
+            store.get(Column::Meta, key)?.map(|bytes| decode_height(Column::Meta, &bytes)).transpose()
+        };
+        let oldest = meta_height(META_OLDEST)?.unwrap_or(0);
+        let latest = meta_height(META_LATEST)?.unwrap_or(0);
+        let base = meta_height(META_BASE)?.unwrap_or(oldest);
+        Ok(StateHistory { mode, store, base, oldest, latest })
+    }
+
+    /// Record the state writes of a finalized block (its `State::take_write_batch`)
+    /// in the history's own store
+    pub fn record_block(&mut self, height: u64, writes: &WriteBatch) -> Result<(), KvError> {
+        let mut batch = WriteBatch::new();
+        let bounds = self.stage_block(height, writes, &mut batch)?;
+        self.store.write(batch)?;
+        self.advance(bounds);
+        Ok(())
+    }
+
+    /// Stage the versions a finalized block writes, and the deletions of what the
+    /// mode no longer keeps, into `batch`. Heights below `height` must already be
+    /// in the store.
+    pub fn stage_block(&self, height: u64, writes: &WriteBatch, batch: &mut WriteBatch) -> Result<HistoryBounds, KvError> {
+        let latest = self.latest.max(height);
+        let mut oldest = self.oldest;
+        if let StorageMode::Pruned { keep_recent, .. } = self.mode {
+            let cutoff = latest.saturating_sub(keep_recent);
+            if cutoff > oldest {
+                self.stage_prune(cutoff, batch)?;
+                oldest = cutoff;
+                batch.put(Column::Meta, META_OLDEST.to_vec(), oldest.to_be_bytes().to_vec());
+            }
+        }
+        for (column, key, value) in &writes.ops {
+            if !matches!(column, Column::Accounts | Column::Storage) {
+                continue;
+            }
+            batch.put(Column::History, encode_row(*column, key, height), encode_value(value.as_deref()));
+            if matches!(self.mode, StorageMode::Pruned { .. }) {
+                batch.put(Column::HistoryIndex, encode_index(height, *column, key), vec![]);
+            }
+        }
+        batch.put(Column::Meta, META_LATEST.to_vec(), latest.to_be_bytes().to_vec());
+        Ok(HistoryBounds { oldest, latest })
+    }
+
+    /// Move to the bounds of a block staged with `stage_block` once it is written
+    pub fn advance(&mut self, bounds: HistoryBounds) {
+        self.oldest = bounds.oldest;
+        self.latest = bounds.latest;
+    }
+
+    /// Account as of the end of block `height`
+    pub fn account_at(&self, address: &[u8], height: u64) -> Result<Option<AccountState>, HistoryError> {
+        Ok(self.value_at(Column::Accounts, address, height)?.and_then(|bytes| AccountState::decode(&bytes)))
+    }
+
+    /// Storage value as of the end of block `height`
+    pub fn storage_at(&self, key: &[u8], height: u64) -> Result<Option<Vec<u8>>, HistoryError> {
+        self.value_at(Column::Storage, key, height)
+    }
+
+    /// Lowest height every query can be answered for; older checkpoint heights
+    /// stay queryable on a pruned node
+    pub fn oldest_height(&self) -> u64 {
+        self.oldest
+    }
+
+    /// Highest recorded height
+    pub fn latest_height(&self) -> u64 {
+        self.latest
+    }
+
+    /// Versions currently kept, across all keys (scans the whole column)
+    pub fn version_count(&self) -> Result<usize, KvError> {
+        Ok(self.store.iter(Column::History)?.len())
+    }
+
+    fn value_at(&self, column: Column, key: &[u8], height: u64) -> Result<Option<Vec<u8>>, HistoryError> {
+        if height > self.latest {
+            return Err(HistoryError::Unknown { requested: height, latest: self.latest });
+        }
+        if height < self.oldest && !(height >= self.base && self.mode.is_checkpoint(height)) {
+            return Err(HistoryError::Pruned { requested: height, oldest: self.oldest });
+        }
+        let newest = self.store.last_in_range(Column::History, &encode_row(column, key, 0), &encode_row(column, key, height))?;
+        match newest {
+            Some((_, value)) => Ok(decode_value(&value)?),
+            None => Ok(None),
+        }
+    }
+
+    /// Delete every version written above `self.oldest` and at or below `cutoff`
+    /// that is hidden by a newer one and not visible at a checkpoint height
+    fn stage_prune(&self, cutoff: u64, batch: &mut WriteBatch) -> Result<(), KvError> {
+        // Index keys are longer than 8 bytes, so the upper bound takes in all of `cutoff`
+        let written = self.store.range(
+            Column::HistoryIndex,
+            &(self.oldest + 1).to_be_bytes(),
+            &(cutoff + 1).to_be_bytes(),
+        )?;
+        for (index, _) in written {
+            let (height, column, key) = decode_index(&index)?;
+            let kept = self.mode.checkpoint_below(height);
+            let from = encode_row(column, &key, kept.map_or(0, |k| k + 1));
+            if height > 0 {
+                for (row, _) in self.store.range(Column::History, &from, &encode_row(column, &key, height - 1))? {
+                    batch.delete(Column::History, row);
+                }
+            }
+            // A deletion with nothing before it: the key is simply absent
+            let row = encode_row(column, &key, height);
+            let deleted = match self.store.get(Column::History, &row)? {
+                Some(value) => decode_value(&value)?.is_none(),
+                None => false,
+            };
+            let older = match kept {
+                Some(k) => self.store.last_in_range(Column::History, &encode_row(column, &key, 0), &encode_row(column, &key, k))?,
+                None => None,
+            };
+            if deleted && older.is_none() {
+                batch.delete(Column::History, row);
+            }
+            batch.delete(Column::HistoryIndex, index);
+        }
+        Ok(())
+    }
+}
+
+/// History row key: column id, key length (u32), key, height (all big-endian)
+fn encode_row(column: Column, key: &[u8], height: u64) -> Vec<u8> {
+    let mut row = Vec::with_capacity(13 + key.len());
+    row.push(column.id());
+    row.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    row.extend_from_slice(key);
+    row.extend_from_slice(&height.to_be_bytes());
+    row
+}
+
+/// Index key of a version: height (big-endian), column id, key
+fn encode_index(height: u64, column: Column, key: &[u8]) -> Vec<u8> {
+    let mut index = Vec::with_capacity(9 + key.len());
+    index.extend_from_slice(&height.to_be_bytes());
+    index.push(column.id());
+    index.extend_from_slice(key);
+    index
+}
+
+fn decode_index(index: &[u8]) -> Result<(u64, Column, Vec<u8>), KvError> {
+    let corrupted = || KvError::corrupted(Column::HistoryIndex, "malformed index key");
+    let height = decode_height(Column::HistoryIndex, index.get(..8).ok_or_else(corrupted)?)?;
+    let column = match index.get(8) {
+        Some(id) if *id == Column::Accounts.id() => Column::Accounts,
+        Some(id) if *id == Column::Storage.id() => Column::Storage,
+        _ => return Err(corrupted()),
+    };
+    Ok((height, column, index[9..].to_vec()))
+}
+
+/// Version value: tag 0 for a deleted key, tag 1 followed by the value otherwise
+fn encode_value(value: Option<&[u8]>) -> Vec<u8> {
+    match value {
+        Some(bytes) => [&[1u8][..], bytes].concat(),
+        None => vec![0],
+    }
+}
+
+fn decode_value(value: &[u8]) -> Result<Option<Vec<u8>>, KvError> {
+    match value.split_first() {
+        Some((0, _)) => Ok(None),
+        Some((1, rest)) => Ok(Some(rest.to_vec())),
+        _ => Err(KvError::corrupted(Column::History, "bad version tag")),
+    }
+}
+
+fn decode_height(column: Column, bytes: &[u8]) -> Result<u64, KvError> {
+    bytes
+        .try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| KvError::corrupted(column, "height is not 8 bytes"))
+}
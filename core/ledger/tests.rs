@@ -7,28 +7,51 @@ mod tests {
     use super::*;
     use crate::ledger::{
         block::Block, 
-        transaction::{SignedTransaction, Transaction}, 
-        state::{State, AccountState}, 
-        receipt::{Receipt, EventLog},
+        transaction::{address_of, SignedTransaction, Transaction, TxSignatureScheme, MAINNET_CHAIN_ID}, 
+        state::{AccountDelta, State, AccountState}, 
+        merkle::EMPTY_ROOT,
+        receipt::{Bloom, Receipt, EventLog},
+        log_index::{IndexedBlock, LogFilter, LogIndex, MatchedLog},
         block_store::BlockStore,
-        tx_pool::TxPool,
-        executor::Executor,
-        snapshot::Snapshot,
+        kv_store::{Column, KvError, KvStore, MemoryStore, SledStore, WriteBatch},
+        tx_pool::{TxPool, TxPoolConfig, TxPoolError},
+        executor::{ExecutionError, Executor},
+        parallel,
+        snapshot::{Snapshot, SnapshotError, SnapshotImporter, SnapshotTrust},
         history::History,
+        fork_choice::{Chain, ChainError, ImportOutcome},
+        state_history::{HistoryError, StorageMode},
     };
+    use crate::consensus::{
+        block::ExecutionSummary,
+        consensus_engine::{ConsensusEngine, ConsensusMode},
+        validator::Validator,
+    };
+
+    use crate::crypto::{DilithiumKeypair, Ed25519Keypair, Secp256k1Keypair};
+    use std::sync::Arc;
 
     fn dummy_address(val: u8) -> Vec<u8> { vec![val; 20] }
-    fn dummy_tx(sender: u8, recipient: u8, nonce: u64, amount: u128) -> SignedTransaction {
-        SignedTransaction {
-            sender: dummy_address(sender),
-            recipient: dummy_address(recipient),
+    fn dummy_key(seed: u8) -> Ed25519Keypair { Ed25519Keypair::from_private_key(&[seed; 32]).unwrap() }
+    /// Account controlled by `dummy_key(seed)`
+    fn sender_address(seed: u8) -> Vec<u8> { address_of(&dummy_key(seed).public) }
+    fn transfer(recipient: u8, nonce: u64, amount: u128, scheme: TxSignatureScheme) -> Transaction {
+        Transaction {
+            chain_id: MAINNET_CHAIN_ID,
             nonce,
-            amount,
             gas_limit: 1_000,
-            hash: vec![nonce as u8; 32],
-            signature: vec![],
+            max_fee_per_gas: 1,
+            max_priority_fee_per_gas: 1,
+            to: dummy_address(recipient),
+            value: amount,
+            payload: vec![],
+            scheme,
         }
     }
+    fn dummy_tx(sender: u8, recipient: u8, nonce: u64, amount: u128) -> SignedTransaction {
+        let key = dummy_key(sender);
+        transfer(recipient, nonce, amount, TxSignatureScheme::Ed25519).sign(&key.public, &key.private).unwrap()
+    }
 
     #[test]
     fn test_state_account_update() {
@@ -42,14 +65,74 @@ mod tests {
     #[test]
     fn test_tx_pool_add_and_evict() {
         let mut pool = TxPool::new(1); // TTL 1s
+        let mut state = State::new();
+        state.update_account(sender_address(2), AccountState::new(10_000));
         let tx = dummy_tx(2, 3, 1, 100);
-        pool.add_transaction(tx.clone()).unwrap();
+        pool.add_transaction(tx.clone(), &state).unwrap();
         assert_eq!(pool.len(), 1);
         std::thread::sleep(std::time::Duration::from_secs(2));
         pool.evict_expired();
         assert!(pool.is_empty());
     }
 
+    #[test]
+    fn test_tx_pool_pending_queued_replacement_and_selection() {
+        let priced = |seed: u8, nonce, fee_cap, tip| {
+            let key = dummy_key(seed);
+            let mut tx = transfer(9, nonce, 10, TxSignatureScheme::Ed25519);
+            tx.max_fee_per_gas = fee_cap;
+            tx.max_priority_fee_per_gas = tip;
+            tx.sign(&key.public, &key.private).unwrap()
+        };
+        let mut state = State::new();
+        for seed in 1..=4 {
+            state.update_account(sender_address(seed), AccountState::new(1_000_000));
+        }
+        let mut pool = TxPool::with_config(TxPoolConfig { max_size: 5, max_per_sender: 3, ..Default::default() });
+        pool.base_fee = 10;
+
+        // Nonces 1, 2 are pending; 4 waits behind the gap at 3
+        for nonce in [1, 2, 4] {
+            pool.add_transaction(priced(1, nonce, 20, 5), &state).unwrap();
+        }
+        assert_eq!((pool.pending_count(), pool.queued_count()), (2, 1));
+        assert_eq!(pool.add_transaction(priced(1, 5, 20, 5), &state), Err(TxPoolError::SenderLimitReached));
+
+        // Same-nonce replacement needs a 10% bump on both caps
+        assert_eq!(pool.add_transaction(priced(1, 2, 21, 5), &state), Err(TxPoolError::ReplacementUnderpriced));
+        let bump = priced(1, 2, 22, 6);
+        let replaced = pool.add_transaction(bump.clone(), &state).unwrap();
+        assert!(replaced.is_some() && pool.get(&replaced.unwrap()).is_none());
+        assert_eq!(pool.pending_for(&sender_address(1))[1].hash, bump.hash);
+
+        // A full pool evicts its cheapest tail, never a better-paying transaction
+        pool.add_transaction(priced(2, 1, 40, 30), &state).unwrap();
+        pool.add_transaction(priced(3, 1, 40, 1), &state).unwrap();
+        assert_eq!(pool.add_transaction(priced(4, 1, 40, 1), &state), Err(TxPoolError::PoolFull));
+        let evicted = pool.add_transaction(priced(4, 1, 40, 20), &state).unwrap();
+        assert_eq!(evicted, Some(priced(3, 1, 40, 1).hash));
+        assert_eq!(pool.len(), 5);
+
+        // Highest tip first, each sender in nonce order; the queued nonce 4 is left out
+        let selected: Vec<_> = pool.select_for_block(4_000).map(|tx| (tx.sender.clone(), tx.nonce())).collect();
+        assert_eq!(selected, vec![
+            (sender_address(2), 1),
+            (sender_address(4), 1),
+            (sender_address(1), 1),
+            (sender_address(1), 2),
+        ]);
+        assert_eq!(pool.select_for_block(2_500).count(), 2);
+
+        // After a block including sender 1's nonce 1, it is dropped and sender 2 can no longer pay
+        state.update_account(sender_address(1), AccountState { nonce: 1, ..AccountState::new(1_000_000) });
+        state.update_account(sender_address(2), AccountState::new(100));
+        let dropped = pool.revalidate(&state, 10);
+        assert_eq!(dropped.len(), 2);
+        assert_eq!((pool.pending_count(), pool.queued_count()), (2, 1));
+        assert!(pool.remove_transaction(&sender_address(4), 1).is_some());
+        assert_eq!(pool.len(), 2);
+    }
+
     #[test]
     fn test_block_store_insert_and_rollback() {
         let mut store = BlockStore::new();
@@ -65,18 +148,250 @@ mod tests {
     #[test]
     fn test_executor_successful_transfer() {
         let mut state = State::new();
-        let sender = dummy_address(10);
+        let sender = sender_address(10);
         let recipient = dummy_address(20);
         state.update_account(sender.clone(), AccountState::new(2000));
         state.update_account(recipient.clone(), AccountState::new(500));
         let tx = dummy_tx(10, 20, 1, 1000);
 
-        let result = Executor::execute_transaction(&mut state, &tx).unwrap();
+        let result = Executor::execute_transaction(&mut state, &tx, MAINNET_CHAIN_ID, 0).unwrap();
         assert_eq!(state.get_account(&sender).unwrap().balance, 2000 - 1000 - 1000);
         assert_eq!(state.get_account(&recipient).unwrap().balance, 500 + 1000);
         assert!(result.1.status);
     }
 
+    #[test]
+    fn test_transactions_are_verified_before_execution() {
+        let mut state = State::new();
+        let ed = dummy_key(1);
+        let secp = Secp256k1Keypair::from_private_key(&[2u8; 32]).unwrap();
+        let pq = DilithiumKeypair::generate().unwrap();
+        let signed = [
+            transfer(9, 1, 10, TxSignatureScheme::Ed25519).sign(&ed.public, &ed.private).unwrap(),
+            transfer(9, 1, 10, TxSignatureScheme::Secp256k1).sign(&secp.public, &secp.private).unwrap(),
+            transfer(9, 1, 10, TxSignatureScheme::Dilithium).sign(&pq.public, &pq.private).unwrap(),
+        ];
+        for tx in &signed {
+            assert!(tx.verify(MAINNET_CHAIN_ID).is_ok());
+            state.update_account(tx.sender.clone(), AccountState::new(5_000));
+        }
+
+        // Replay on another chain, tampered fields and a swapped key are all rejected
+        let wrong_chain = Executor::execute_transaction(&mut state, &signed[0], MAINNET_CHAIN_ID + 1, 0);
+        assert!(matches!(wrong_chain, Err(ExecutionError::WrongChainId)));
+        let mut tampered = signed[1].clone();
+        tampered.tx.value = 4_000;
+        tampered.hash = SignedTransaction::new(tampered.tx.clone(), tampered.public_key.clone(), tampered.signature.clone()).hash;
+        assert!(matches!(Executor::execute_transaction(&mut state, &tampered, MAINNET_CHAIN_ID, 0), Err(ExecutionError::InvalidSignature)));
+        let mut stolen = signed[2].clone();
+        stolen.sender = signed[0].sender.clone();
+        assert!(matches!(Executor::execute_transaction(&mut state, &stolen, MAINNET_CHAIN_ID, 0), Err(ExecutionError::InvalidSignature)));
+        assert_eq!(state.get_account(&signed[1].sender).unwrap().balance, 5_000);
+
+        for tx in &signed {
+            Executor::execute_transaction(&mut state, tx, MAINNET_CHAIN_ID, 0).unwrap();
+        }
+        assert_eq!(state.get_account(&dummy_address(9)).unwrap().balance, 30);
+        assert_eq!(state.get_account(&signed[2].sender).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn test_journaled_state_reverts_failed_transactions() {
+        let mut state = State::new();
+        let sender = sender_address(10);
+        state.update_account(sender.clone(), AccountState::new(5_000));
+        state.update_account(dummy_address(20), AccountState::new(u128::MAX - 100));
+
+        // A transaction reports every account it changed, recipient included
+        let (changes, _, _) = Executor::execute_transaction(&mut state, &dummy_tx(10, 21, 1, 500), MAINNET_CHAIN_ID, 0).unwrap();
+        assert_eq!(changes.account_deltas, vec![
+            (sender.clone(), AccountDelta::BalanceChange(-1_500)),
+            (sender.clone(), AccountDelta::NonceInc(1)),
+            (dummy_address(21), AccountDelta::BalanceChange(500)),
+        ]);
+
+        // The second transfer overflows the recipient after debiting the sender: all of it is undone
+        let block = Block {
+            header: Default::default(),
+            transactions: vec![dummy_tx(10, 20, 2, 200), dummy_tx(10, 21, 2, 100)],
+        };
+        let result = Executor::execute_block(&mut state, &block, MAINNET_CHAIN_ID);
+        assert!(matches!(result.errors.as_slice(), [ExecutionError::BalanceOverflow]));
+        assert_eq!(state.get_account(&dummy_address(20)).unwrap().balance, u128::MAX - 100);
+        assert_eq!(state.get_account(&sender).unwrap().balance, 5_000 - 1_500 - 1_100);
+        assert_eq!(state.get_account(&sender).unwrap().nonce, 2);
+        assert_eq!(result.state_update.account_deltas, vec![
+            (sender.clone(), AccountDelta::BalanceChange(-1_100)),
+            (sender.clone(), AccountDelta::NonceInc(1)),
+            (dummy_address(21), AccountDelta::BalanceChange(100)),
+            (address_of(&[]), AccountDelta::BalanceChange(1_000)), // Tip to the (keyless) proposer
+        ]);
+
+        // Nested checkpoints
+        let outer = state.checkpoint();
+        state.set_storage(b"k".to_vec(), b"v1".to_vec());
+        let inner = state.checkpoint();
+        state.set_storage(b"k".to_vec(), b"v2".to_vec());
+        state.remove_account(&sender);
+        state.revert_to(inner);
+        assert_eq!(state.get_storage(b"k"), Some(&b"v1".to_vec()));
+        assert!(state.get_account(&sender).is_some());
+        assert_eq!(state.changes_since(outer).storage_deltas, vec![(b"k".to_vec(), b"v1".to_vec())]);
+        state.revert_to(outer);
+        assert!(state.get_storage(b"k").is_none());
+    }
+
+    #[test]
+    fn test_parallel_execution_matches_sequential() {
+        let signed = |seed: u8, to: Vec<u8>, nonce, amount| {
+            let key = dummy_key(seed);
+            let mut tx = transfer(0, nonce, amount, TxSignatureScheme::Ed25519);
+            tx.to = to;
+            tx.sign(&key.public, &key.private).unwrap()
+        };
+        let mut state = State::new();
+        for seed in 1..=9 {
+            state.update_account(sender_address(seed), AccountState::new(1_000_000));
+        }
+
+        // Disjoint transfers with nonce chains, a shared hot recipient, a spend
+        // that fails before its funding and succeeds after, and a bad nonce
+        let mut txs = vec![signed(10, dummy_address(50), 1, 5_000)];
+        for nonce in 1..=5 {
+            for seed in 1..=8 {
+                txs.push(signed(seed, dummy_address(100 + seed), nonce, 10 * nonce as u128));
+            }
+        }
+        txs.push(signed(9, sender_address(10), 1, 20_000));
+        txs.extend((1..=8).map(|seed| signed(seed, dummy_address(200), 6, 1)));
+        txs.push(signed(10, dummy_address(50), 1, 5_000));
+        txs.push(signed(1, dummy_address(50), 9, 1));
+
+        let mut sequential = state.clone();
+        let checkpoint = sequential.checkpoint();
+        let expected: Vec<_> = txs
+            .iter()
+            .map(|tx| Executor::execute_transaction(&mut sequential, tx, MAINNET_CHAIN_ID, 0).map(|(_, receipt, gas)| (receipt.tx_hash, gas)))
+            .map(|outcome| format!("{:?}", outcome))
+            .collect();
+        let expected_update = sequential.changes_since(checkpoint);
+        sequential.release(checkpoint);
+        let expected_root = sequential.commit();
+        assert_eq!(expected.iter().filter(|o| o.starts_with("Err")).count(), 2);
+
+        for workers in [2, 4, 8] {
+            let mut parallel_state = state.clone();
+            let checkpoint = parallel_state.checkpoint();
+            let outcomes: Vec<_> = parallel::execute_transactions(&mut parallel_state, &txs, MAINNET_CHAIN_ID, 0, workers)
+                .into_iter()
+                .map(|outcome| format!("{:?}", outcome.map(|(receipt, gas)| (receipt.tx_hash, gas))))
+                .collect();
+            assert_eq!(outcomes, expected);
+            assert_eq!(parallel_state.changes_since(checkpoint), expected_update);
+            parallel_state.release(checkpoint);
+            assert_eq!(parallel_state.commit(), expected_root);
+            assert_eq!(parallel_state.accounts, sequential.accounts);
+        }
+
+        // Large blocks take the parallel path inside the executor
+        let block = Block { header: Default::default(), transactions: txs };
+        let result = Executor::execute_block(&mut state, &block, MAINNET_CHAIN_ID);
+        assert_eq!(result.receipts.len(), block.transactions.len() - 2);
+        assert_eq!(result.tips, 1_000 * result.receipts.len() as u128);
+        let mut with_tips = sequential.clone();
+        with_tips.update_account(address_of(&[]), AccountState::new(result.tips));
+        assert_eq!(result.state_root, with_tips.commit());
+    }
+
+    #[test]
+    fn test_block_fees_and_issuance_are_paid_out() {
+        let engine = ConsensusEngine::new(vec![
+            Validator::new("val1".to_string(), 300, vec![1; 32]),
+            Validator::new("val2".to_string(), 100, vec![2; 32]),
+        ], ConsensusMode::ProofOfStake);
+        let distributor = engine.reward_distributor();
+
+        let mut state = State::new();
+        state.update_account(sender_address(10), AccountState::new(5_000));
+        let mut block = Block { header: Default::default(), transactions: vec![dummy_tx(10, 20, 1, 1_000)] };
+        block.header.height = 1;
+        block.header.proposer_public_key = vec![1; 32];
+        let supply = |state: &State| state.accounts.values().map(|a| a.balance).sum::<u128>();
+        let supply_before = supply(&state);
+
+        let result = Executor::execute_block_with_rewards(&mut state, &block, MAINNET_CHAIN_ID, &distributor);
+        assert_eq!(result.fees_collected, 1_000);
+        let dist = result.rewards.unwrap();
+        assert!(dist.is_balanced());
+        assert_eq!(dist.proposer.as_deref(), Some("val1"));
+
+        // Fees move to the payees instead of vanishing; supply grows by the issuance only
+        assert_eq!(supply(&state), supply_before + dist.minted);
+        assert_eq!(state.get_account(b"treasury").unwrap().balance, dist.treasury);
+        assert!(state.get_account(b"val1").unwrap().balance > state.get_account(b"val2").unwrap().balance);
+    }
+
+    #[test]
+    fn test_base_fee_is_burned_and_tips_go_to_the_proposer() {
+        let engine = ConsensusEngine::new(vec![
+            Validator::new("val1".to_string(), 300, vec![1; 32]),
+        ], ConsensusMode::ProofOfStake);
+        let distributor = engine.reward_distributor();
+        let key = dummy_key(10);
+        let priced = |nonce: u64, max_fee: u128, tip: u128| {
+            let mut tx = transfer(20, nonce, 100, TxSignatureScheme::Ed25519);
+            tx.max_fee_per_gas = max_fee;
+            tx.max_priority_fee_per_gas = tip;
+            tx.sign(&key.public, &key.private).unwrap()
+        };
+
+        let mut state = State::new();
+        state.update_account(sender_address(10), AccountState::new(10_000));
+        let mut block = Block {
+            header: Default::default(),
+            transactions: vec![priced(1, 5, 1), priced(2, 1, 1)],
+        };
+        block.header.height = 1;
+        block.header.base_fee_per_gas = 2;
+        block.header.proposer_public_key = vec![1; 32];
+        let supply = |state: &State| state.accounts.values().map(|a| a.balance).sum::<u128>();
+        let supply_before = supply(&state);
+
+        let result = Executor::execute_block_with_rewards(&mut state, &block, MAINNET_CHAIN_ID, &distributor);
+        // Pays min(5, 2 + 1) = 3 per gas; the second tx's cap is below the base fee
+        assert!(matches!(result.errors.as_slice(), [ExecutionError::FeeCapBelowBaseFee]));
+        assert_eq!((result.fees_burned, result.tips, result.fees_collected), (2_000, 1_000, 3_000));
+        assert_eq!(state.get_account(&sender_address(10)).unwrap().balance, 10_000 - 3_000 - 100);
+
+        let dist = result.rewards.unwrap();
+        assert!(dist.is_balanced());
+        assert_eq!(state.get_account(&address_of(&[1; 32])).unwrap().balance, 1_000);
+        assert_eq!(supply(&state), supply_before + dist.minted - result.fees_burned);
+
+        // Without a distributor the same account collects the tips; only the burn leaves the supply
+        let mut state = State::new();
+        state.update_account(sender_address(10), AccountState::new(10_000));
+        let result = Executor::execute_block(&mut state, &block, MAINNET_CHAIN_ID);
+        assert!(result.rewards.is_none());
+        assert_eq!(state.get_account(&address_of(&[1; 32])).unwrap().balance, 1_000);
+        assert_eq!(supply(&state), supply_before - result.fees_burned);
+    }
+
+    #[test]
+    fn test_transactions_past_the_block_gas_cap_are_not_executed() {
+        let mut state = State::new();
+        state.update_account(sender_address(10), AccountState::new(10_000));
+        let block = Block {
+            header: Default::default(),
+            transactions: vec![dummy_tx(10, 20, 1, 100), dummy_tx(10, 20, 2, 100), dummy_tx(10, 20, 3, 100)],
+        };
+        let result = Executor::execute_block_capped(&mut state, &block, MAINNET_CHAIN_ID, 2_500, None);
+        assert_eq!((result.gas_used, result.receipts.len()), (2_000, 2));
+        assert!(matches!(result.errors.as_slice(), [ExecutionError::BlockGasLimitReached]));
+        assert_eq!(state.get_account(&sender_address(10)).unwrap().nonce, 2);
+        assert_eq!(state.get_account(&dummy_address(20)).unwrap().balance, 200);
+    }
+
     #[test]
     fn test_receipt_and_eventlog() {
         let tx_hash = vec![0xab; 32];
@@ -89,14 +404,189 @@ mod tests {
         assert_eq!(receipt.logs.len(), 1);
     }
 
+    #[test]
+    fn test_log_bloom_and_filter_index() {
+        let (transfer, approval, token) = (vec![0xdd; 32], vec![0x8c; 32], dummy_address(100));
+        let receipt = |tx: u8, log: EventLog| Receipt::success(vec![tx; 32], 21_000, vec![log]);
+        let mut index = LogIndex::new();
+        for height in 1..=20u8 {
+            let mut receipts = vec![receipt(height, EventLog::new(dummy_address(101), vec![approval.clone()], vec![]))];
+            if height % 5 == 0 {
+                let topics = vec![transfer.clone(), dummy_address(height)];
+                receipts.push(receipt(height + 100, EventLog::new(token.clone(), topics, vec![height])));
+            }
+            index.add_block(height as u64, IndexedBlock::new(vec![height; 32], receipts)).unwrap();
+        }
+
+        let bloom = index.bloom(5).unwrap().bloom.clone();
+        assert!(bloom.contains(&token) && bloom.contains(&transfer) && bloom.contains(&approval));
+        assert!(!index.bloom(4).unwrap().bloom.contains(&token));
+        assert_eq!(index.get(5).unwrap().unwrap().receipts.len(), 2);
+        assert_eq!(index.get(21).unwrap(), None);
+        assert_eq!(Bloom::from_bytes(bloom.as_bytes()), Some(bloom));
+        assert!(Bloom::from_bytes(&[0; 10]).is_none());
+
+        // Blooms leave only the blocks with a token transfer to scan
+        let filter = LogFilter::new(1, 20).with_address(token.clone()).with_topic(0, vec![transfer.clone()]);
+        assert_eq!(index.candidates(&filter), vec![5, 10, 15, 20]);
+        let logs = index.query(&filter).unwrap();
+        assert_eq!(logs.iter().map(|l| l.height).collect::<Vec<_>>(), vec![5, 10, 15, 20]);
+        assert_eq!((logs[0].tx_index, logs[0].log_index, &logs[0].tx_hash), (1, 1, &vec![105; 32]));
+        assert_eq!(logs[0].log.data, vec![5]);
+
+        // Alternatives at a later topic position, within a narrower range
+        let filter = LogFilter::new(6, 20).with_topic(1, vec![dummy_address(5), dummy_address(10), dummy_address(20)]);
+        assert_eq!(index.query(&filter).unwrap().iter().map(|l| l.height).collect::<Vec<_>>(), vec![10, 20]);
+        assert_eq!(index.query(&LogFilter::new(1, 20)).unwrap().len(), 24);
+        assert!(index.query(&LogFilter::new(9, 3)).unwrap().is_empty());
+    }
+
     #[test]
     fn test_snapshot_restore() {
         let mut state = State::new();
         let addr = dummy_address(55);
         state.update_account(addr.clone(), AccountState::new(999));
-        let snapshot = Snapshot::from_state(42, vec![9;32], &state);
-        let restored = snapshot.restore_state();
+        state.commit();
+        let mut block = Block::default();
+        block.header.height = 42;
+        let snapshot = Snapshot::from_state(block, &state).unwrap();
+        let restored = snapshot.restore_state().unwrap();
         assert_eq!(restored.get_account(&addr).unwrap().balance, 999);
+        assert_eq!(snapshot.manifest.height(), 42);
+    }
+
+    #[test]
+    fn test_snapshot_chunks_verify_and_bootstrap_chain() {
+        let genesis = Block::default();
+        let mut genesis_state = State::new();
+        for i in 0..50u8 {
+            genesis_state.update_account(dummy_address(i), AccountState::new(1_000 + i as u128));
+            genesis_state.set_storage(vec![b's', i], vec![i; 16]);
+        }
+        genesis_state.update_account(sender_address(10), AccountState::new(10_000));
+        genesis_state.commit();
+        let mut chain = Chain::new(genesis.clone(), genesis_state);
+        let mut b1 = child_block(&genesis, 1);
+        b1.transactions.push(dummy_tx(10, 20, 1, 500));
+        chain.seal_block(&mut b1).unwrap();
+        chain.import_block(b1.clone()).unwrap();
+        chain.finalize(&b1.hash()).unwrap();
+
+        // Uncommitted state cannot be snapshotted
+        let mut dirty = chain.state.clone();
+        dirty.set_storage(b"x".to_vec(), b"y".to_vec());
+        assert_eq!(Snapshot::from_state(b1.clone(), &dirty).unwrap_err(), SnapshotError::UncommittedState);
+
+        let snapshot = chain.snapshot().unwrap();
+        assert_eq!(snapshot.manifest.block_hash(), b1.hash());
+        assert_eq!(snapshot.manifest.state_root, chain.state.state_root());
+        let small = Snapshot::with_chunk_size(b1.clone(), &chain.state, 512).unwrap();
+        assert!(small.chunks.len() > 3);
+        assert_eq!(small.manifest.chunks.iter().map(|c| c.entries).sum::<u64>(), 102); // + the proposer's tip account
+
+        // Chunks verify independently and can arrive in any order
+        let mut importer = SnapshotImporter::new(small.manifest.clone()).unwrap();
+        let mut tampered = small.chunks[1].clone();
+        tampered[0] ^= 1;
+        assert_eq!(importer.import_chunk(1, &tampered), Err(SnapshotError::ChunkHashMismatch(1)));
+        assert_eq!(importer.import_chunk(9_999, &small.chunks[0]), Err(SnapshotError::UnknownChunk(9_999)));
+        for index in (1..small.chunks.len()).rev() {
+            importer.import_chunk(index, &small.chunks[index]).unwrap();
+        }
+        assert_eq!(importer.missing_chunks(), vec![0]);
+        importer.import_chunk(0, &small.chunks[0]).unwrap();
+        let restored = importer.finish().unwrap();
+        assert_eq!(restored.state_root(), chain.state.state_root());
+        assert_eq!(restored.get_storage(&[b's', 7]), Some(&vec![7; 16]));
+
+        // A manifest claiming another root is rejected after assembly
+        let mut forged = small.clone();
+        forged.manifest.state_root = vec![0; 32];
+        assert_eq!(forged.restore_state().unwrap_err(), SnapshotError::StateRootMismatch);
+
+        // A new node bootstraps from the snapshot file and keeps following the chain
+        let path = std::env::temp_dir().join(format!("ocos-ledger-snapshot-{}", std::process::id()));
+        small.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut bytes = vec![];
+        loaded.write_to(&mut bytes).unwrap();
+        assert!(matches!(Snapshot::read_from(&bytes[..4]), Err(SnapshotError::Io(_))));
+        // The manifest must match a trusted anchor: a light-client-verified child
+        // header commits the block (parent hash) and its post-state (state root)
+        let mut verified_child = child_block(&b1, 9).header;
+        verified_child.state_root = chain.state.state_root();
+        let trusted = SnapshotTrust::ChildHeader(verified_child.clone());
+        let mut tampered = loaded.clone();
+        tampered.manifest.block.header.timestamp += 1;
+        assert_eq!(tampered.restore_trusted(&trusted).unwrap_err(), SnapshotError::UntrustedBlock);
+        assert!(matches!(Chain::from_snapshot(&tampered, &trusted, None), Err(ChainError::Snapshot(SnapshotError::UntrustedBlock))));
+        let mut lying_child = verified_child.clone();
+        lying_child.state_root = vec![0; 32];
+        assert_eq!(loaded.restore_trusted(&SnapshotTrust::ChildHeader(lying_child)).unwrap_err(), SnapshotError::UntrustedStateRoot);
+        let checkpoint = SnapshotTrust::Checkpoint { block_hash: b1.hash(), state_root: vec![0; 32] };
+        assert_eq!(forged.manifest.verify(&checkpoint), Ok(()));
+        assert_eq!(forged.restore_trusted(&checkpoint).unwrap_err(), SnapshotError::StateRootMismatch);
+        let checkpoint = SnapshotTrust::Checkpoint { block_hash: b1.hash(), state_root: chain.state.state_root() };
+        assert_eq!(loaded.restore_trusted(&checkpoint).unwrap().state_root(), chain.state.state_root());
+        for db in [None, Some(Arc::new(MemoryStore::new()) as Arc<dyn KvStore>)] {
+            let mut node = Chain::from_snapshot(&loaded, &trusted, db).unwrap();
+            assert_eq!(node.head, b1.hash());
+            assert_eq!(node.state.state_root(), chain.state.state_root());
+            let mut b2 = child_block(&b1, 1);
+            b2.transactions.push(dummy_tx(10, 21, 2, 700));
+            node.seal_block(&mut b2).unwrap();
+            assert_eq!(node.import_block(b2).unwrap(), ImportOutcome::Extended);
+            assert_eq!(node.state.get_account(&dummy_address(21)).unwrap().balance, 1_021 + 700);
+        }
+    }
+
+    #[test]
+    fn test_state_trie_roots_and_proofs() {
+        let mut state = State::new();
+        assert_eq!(state.commit(), EMPTY_ROOT.to_vec());
+        state.update_account(dummy_address(1), AccountState::new(100));
+        state.update_account(dummy_address(2), AccountState::new(200));
+        state.set_storage(b"bridge/locked".to_vec(), 42u64.to_be_bytes().to_vec());
+        let root = state.commit();
+        assert_ne!(root, EMPTY_ROOT.to_vec());
+
+        // Inclusion and non-inclusion, checked against the root alone
+        let proof = state.prove_account(&dummy_address(1));
+        assert_eq!(proof.balance(), 100);
+        assert!(proof.verify(&root));
+        let missing = state.prove_account(&dummy_address(3));
+        assert!(missing.account.is_none() && missing.verify(&root));
+        assert!(state.prove_storage(b"bridge/locked").verify(&root));
+        let unset = state.prove_storage(b"bridge/unset");
+        assert!(unset.value.is_none() && unset.verify(&root));
+
+        // Tampered proofs fail
+        let mut forged = proof.clone();
+        forged.account.as_mut().unwrap().balance = 1_000;
+        assert!(!forged.verify(&root));
+        let mut forged = missing.clone();
+        forged.address = dummy_address(1);
+        assert!(!forged.verify(&root));
+
+        // Roots only move on commit, and changes are undone by reverting them
+        state.get_account_mut(&dummy_address(1)).unwrap().balance = 150;
+        assert_eq!(state.state_root(), root);
+        let updated = state.commit();
+        assert_ne!(updated, root);
+        assert!(!proof.verify(&updated));
+        assert!(state.prove_account(&dummy_address(1)).verify(&updated));
+        state.get_account_mut(&dummy_address(1)).unwrap().balance = 100;
+        assert_eq!(state.commit(), root);
+
+        // Executing a block commits its post-state
+        state.update_account(sender_address(10), AccountState::new(5_000));
+        let block = Block { header: Default::default(), transactions: vec![dummy_tx(10, 2, 1, 300)] };
+        let result = Executor::execute_block(&mut state, &block, MAINNET_CHAIN_ID);
+        assert!(result.errors.is_empty());
+        assert_eq!(result.state_root, state.state_root());
+        let proof = state.prove_account(&dummy_address(2));
+        assert!(proof.verify(&result.state_root) && proof.balance() == 500);
     }
 
     #[test]
@@ -112,4 +602,335 @@ mod tests {
         let tx_hash = &txs[0].hash;
         assert!(hist.get_tx_by_hash(tx_hash).is_some());
     }
+
+    fn child_block(parent: &Block, tag: u64) -> Block {
+        let mut block = Block::default();
+        block.header.parent_hash = parent.hash();
+        block.header.height = parent.height() + 1;
+        block.header.timestamp = tag;
+        block.header.logs_bloom = Bloom::new().as_bytes().to_vec(); // No receipts, no logs
+        block
+    }
+
+    #[test]
+    fn test_fork_choice_reorg_to_longer_branch() {
+        let genesis = Block::default();
+        let mut chain = Chain::new(genesis.clone(), State::new());
+
+        let a1 = child_block(&genesis, 1);
+        let a2 = child_block(&a1, 1);
+        assert_eq!(chain.import_block(a1.clone()).unwrap(), ImportOutcome::Extended);
+        assert_eq!(chain.import_block(a2.clone()).unwrap(), ImportOutcome::Extended);
+
+        // Competing blocks at the same height coexist in the tree
+        let b1 = child_block(&genesis, 2);
+        let b2 = child_block(&b1, 2);
+        let b3 = child_block(&b2, 2);
+        let mut outcomes = vec![chain.import_block(b1.clone()).unwrap()];
+        assert_eq!(chain.store.blocks_at_height(1).len(), 2);
+        outcomes.push(chain.import_block(b2.clone()).unwrap());
+        outcomes.push(chain.import_block(b3.clone()).unwrap());
+
+        // The switch happens at the height tie (smallest hash) or once B is longer
+        let reorgs: Vec<&ImportOutcome> = outcomes.iter()
+            .filter(|o| matches!(o, ImportOutcome::Reorg { .. }))
+            .collect();
+        assert_eq!(reorgs.len(), 1);
+        if let ImportOutcome::Reorg { common_ancestor, reverted, .. } = reorgs[0] {
+            assert_eq!(common_ancestor, &genesis.hash());
+            assert_eq!(reverted, &vec![a1.hash(), a2.hash()]);
+        }
+        assert_eq!(chain.head, b3.hash());
+        assert_eq!(chain.store.get_block_by_height(1).unwrap().hash(), b1.hash());
+
+        // Finalizing B prunes the A branch; blocks building on A are rejected
+        chain.finalize(&b1.hash()).unwrap();
+        assert!(chain.store.get_block_by_hash(&a1.hash()).is_none());
+        assert_eq!(chain.store.len(), 4);
+        assert!(chain.import_block(child_block(&a2, 3)).is_err());
+    }
+
+    #[test]
+    fn test_reorg_moves_state_through_block_diffs() {
+        let balance = |chain: &Chain, seed| chain.state.get_account(&dummy_address(seed)).map(|a| a.balance);
+        let genesis = Block::default();
+        let mut genesis_state = State::new();
+        genesis_state.update_account(sender_address(10), AccountState::new(10_000));
+        genesis_state.commit();
+        let mut chain = Chain::new(genesis.clone(), genesis_state);
+        let mut a1 = child_block(&genesis, 1);
+        a1.transactions.push(dummy_tx(10, 20, 1, 500));
+        chain.seal_block(&mut a1).unwrap();
+        chain.import_block(a1.clone()).unwrap();
+
+        // B spends the same nonce elsewhere; its blocks execute on B's states while A is the head
+        let mut b1 = child_block(&genesis, 2);
+        b1.transactions.push(dummy_tx(10, 21, 1, 300));
+        chain.seal_block(&mut b1).unwrap();
+        chain.import_block(b1.clone()).unwrap();
+        let mut b2 = child_block(&b1, 2);
+        chain.seal_block(&mut b2).unwrap();
+        chain.import_block(b2.clone()).unwrap();
+        assert_eq!(chain.head, b2.hash());
+        assert_eq!((balance(&chain, 20), balance(&chain, 21)), (None, Some(300)));
+        assert_eq!(chain.state.state_root(), chain.diffs[&b2.hash()].state_root);
+        assert_eq!(chain.account_at(&dummy_address(21), 1), Ok(Some(AccountState::new(300))));
+
+        // A overtakes B again: B's diffs are undone and A's replayed
+        let a2 = child_block(&a1, 1);
+        let a3 = child_block(&a2, 1);
+        chain.import_block(a2).unwrap();
+        chain.import_block(a3.clone()).unwrap();
+        assert_eq!(chain.head, a3.hash());
+        assert_eq!((balance(&chain, 20), balance(&chain, 21)), (Some(500), None));
+        assert_eq!(chain.state.state_root(), chain.diffs[&a1.hash()].state_root); // a2 and a3 are empty
+        assert_eq!(chain.state.get_account(&sender_address(10)).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn test_finalization_prunes_side_branches_below_the_new_tip() {
+        let genesis = Block::default();
+        let mut chain = Chain::new(genesis.clone(), State::new());
+        let a1 = child_block(&genesis, 1);
+        let a2 = child_block(&a1, 1);
+        let a3 = child_block(&a2, 1);
+        let b2 = child_block(&a1, 2);
+        let c3 = child_block(&a2, 3);
+        for block in [&a1, &a2, &a3, &b2, &c3] {
+            chain.import_block(block.clone()).unwrap();
+        }
+
+        // A header must sit one above its parent
+        let mut skipping = child_block(&a3, 1);
+        skipping.header.height = 7;
+        assert_eq!(chain.import_block(skipping), Err(ChainError::InvalidHeight { expected: 4, found: 7 }));
+
+        // Finalizing a2 drops b2 (forked below it) but keeps both of its children
+        chain.finalize(&a2.hash()).unwrap();
+        assert_eq!(chain.store.latest_finalized().unwrap().hash, a2.hash());
+        assert!(chain.store.get_stored(&b2.hash()).is_none());
+        assert!(chain.store.get_stored(&a1.hash()).unwrap().finalized);
+        let mut leaves: Vec<Vec<u8>> = chain.store.leaves().iter().map(|b| b.hash.clone()).collect();
+        leaves.sort();
+        let mut expected = vec![a3.hash(), c3.hash()];
+        expected.sort();
+        assert_eq!(leaves, expected);
+        assert_eq!(chain.store.common_ancestor(&a3.hash(), &c3.hash()), Some(a2.hash()));
+        assert!(!chain.store.is_descendant(&a3.hash(), &c3.hash()));
+        assert_eq!(chain.import_block(child_block(&b2, 2)), Err(ChainError::UnknownParent));
+    }
+
+    #[test]
+    fn test_archive_and_pruned_state_history() {
+        let balance_at = |chain: &Chain, height| chain.account_at(&dummy_address(20), height).map(|a| a.map(|a| a.balance));
+        let modes = [
+            StorageMode::Archive,
+            StorageMode::Pruned { keep_recent: 2, checkpoint_interval: 0 },
+            StorageMode::Pruned { keep_recent: 2, checkpoint_interval: 2 },
+        ];
+        for mode in modes {
+            let genesis = Block::default();
+            let mut genesis_state = State::new();
+            genesis_state.update_account(sender_address(10), AccountState::new(100_000));
+            let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+            let mut chain = Chain::open(db.clone(), genesis.clone(), genesis_state).unwrap().with_storage_mode(mode);
+            let mut parent = genesis.clone();
+            for nonce in 1..=6 {
+                let mut block = child_block(&parent, 1);
+                block.transactions.push(dummy_tx(10, 20, nonce, 100));
+                chain.seal_block(&mut block).unwrap();
+                chain.import_block(block.clone()).unwrap();
+                if nonce <= 5 {
+                    chain.finalize(&block.hash()).unwrap();
+                }
+                parent = block;
+            }
+
+            // Unfinalized heights come from block diffs, finalized ones from history
+            assert_eq!(balance_at(&chain, 6), Ok(Some(600)));
+            assert_eq!(balance_at(&chain, 5), Ok(Some(500)));
+            assert_eq!(balance_at(&chain, 7), Err(ChainError::UnknownBlock));
+            match mode {
+                StorageMode::Archive => {
+                    assert_eq!(balance_at(&chain, 0), Ok(None));
+                    assert_eq!(balance_at(&chain, 2), Ok(Some(200)));
+                    assert_eq!(chain.state_history.version_count(), Ok(6 + 5 + 5)); // sender, recipient, proposer
+                }
+                StorageMode::Pruned { checkpoint_interval: 0, .. } => {
+                    assert_eq!(balance_at(&chain, 3), Ok(Some(300)));
+                    assert_eq!(
+                        balance_at(&chain, 2),
+                        Err(ChainError::History(HistoryError::Pruned { requested: 2, oldest: 3 }))
+                    );
+                    // Superseded versions are collected: heights 3..=5 for all three accounts
+                    assert_eq!(chain.state_history.version_count(), Ok(9));
+                }
+                StorageMode::Pruned { .. } => {
+                    // Checkpoint heights 0 and 2 stay queryable below the recent window
+                    assert_eq!(balance_at(&chain, 0), Ok(None));
+                    assert_eq!(balance_at(&chain, 2), Ok(Some(200)));
+                    assert_eq!(
+                        balance_at(&chain, 1),
+                        Err(ChainError::History(HistoryError::Pruned { requested: 1, oldest: 3 }))
+                    );
+                    // Only height 1 is collected: sender 0, 2..=5 and the others 2..=5
+                    assert_eq!(chain.state_history.version_count(), Ok(5 + 4 + 4));
+                }
+            }
+
+            // History survives a restart
+            drop(chain);
+            let chain = Chain::open(db, genesis, State::new()).unwrap().with_storage_mode(mode);
+            assert_eq!(balance_at(&chain, 4), Ok(Some(400)));
+            assert_eq!(chain.state_history.oldest_height(), if mode == StorageMode::Archive { 0 } else { 3 });
+        }
+    }
+
+    #[test]
+    fn test_chain_log_queries_cover_finalized_and_pending_blocks() {
+        let heights = |logs: Vec<MatchedLog>| logs.iter().map(|l| l.height).collect::<Vec<_>>();
+        let genesis = Block::default();
+        let mut genesis_state = State::new();
+        genesis_state.update_account(sender_address(10), AccountState::new(100_000));
+        let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+        let mut chain = Chain::open(db.clone(), genesis.clone(), genesis_state).unwrap();
+        let mut parent = genesis.clone();
+        for nonce in 1..=4 {
+            // Transfers log from the recipient: 21, 20, 21, 20
+            let mut block = child_block(&parent, 1);
+            block.transactions.push(dummy_tx(10, 20 + (nonce % 2) as u8, nonce, 100));
+            chain.seal_block(&mut block).unwrap();
+            chain.import_block(block.clone()).unwrap();
+            parent = block;
+        }
+        let b2 = chain.store.get_block_by_height(2).unwrap().hash();
+        chain.finalize(&b2).unwrap();
+
+        // Height 1 comes from the index, height 3 from a pending block
+        let filter = LogFilter::new(0, u64::MAX).with_address(dummy_address(21));
+        assert_eq!(heights(chain.logs(&filter).unwrap()), vec![1, 3]);
+        assert_eq!(chain.logs.candidates(&filter), vec![1]);
+        assert!(chain.logs.bloom(2).unwrap().bloom.contains(&dummy_address(20)));
+        assert!(heights(chain.logs(&LogFilter::new(5, 9)).unwrap()).is_empty());
+
+        // Receipts stay in the store, only blooms are reloaded on restart;
+        // unfinalized blocks are gone until re-imported
+        assert!(db.get(Column::Logs, &2u64.to_be_bytes()).unwrap().is_some());
+        drop(chain);
+        let chain = Chain::open(db, genesis, State::new()).unwrap();
+        assert!(chain.logs.bloom(2).is_some() && chain.logs.bloom(3).is_none());
+        assert_eq!(heights(chain.logs(&LogFilter::new(0, 10).with_address(dummy_address(20))).unwrap()), vec![2]);
+    }
+
+    /// Store whose writes fail while `failing` is set
+    #[derive(Default)]
+    struct FlakyStore {
+        inner: MemoryStore,
+        failing: std::sync::atomic::AtomicBool,
+    }
+
+    impl KvStore for FlakyStore {
+        fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, KvError> {
+            self.inner.get(column, key)
+        }
+        fn write(&self, batch: WriteBatch) -> Result<(), KvError> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(KvError::Io("disk full".into()));
+            }
+            self.inner.write(batch)
+        }
+        fn iter(&self, column: Column) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+            self.inner.iter(column)
+        }
+        fn range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, KvError> {
+            self.inner.range(column, from, to)
+        }
+        fn last_in_range(&self, column: Column, from: &[u8], to: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, KvError> {
+            self.inner.last_in_range(column, from, to)
+        }
+    }
+
+    #[test]
+    fn test_failed_finalization_write_leaves_block_pending() {
+        let db = Arc::new(FlakyStore::default());
+        let genesis = Block::default();
+        let mut genesis_state = State::new();
+        genesis_state.update_account(sender_address(10), AccountState::new(10_000));
+        let mut chain = Chain::open(db.clone(), genesis.clone(), genesis_state).unwrap();
+        let mut b1 = child_block(&genesis, 1);
+        b1.transactions.push(dummy_tx(10, 20, 1, 500));
+        chain.seal_block(&mut b1).unwrap();
+        chain.import_block(b1.clone()).unwrap();
+
+        db.failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(chain.finalize(&b1.hash()), Err(ChainError::Storage(KvError::Io("disk full".into()))));
+        assert_eq!(chain.persisted_height, 0);
+        assert!(chain.history.get_block_hash(1).is_none());
+        assert_eq!(chain.state_history.latest_height(), 0);
+        assert!(chain.logs.bloom(1).is_none());
+
+        // Retrying once the store recovers writes the block
+        db.failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        chain.finalize(&b1.hash()).unwrap();
+        assert_eq!(chain.persisted_height, 1);
+        assert_eq!(chain.history.get_block_hash(1), Some(&b1.hash()));
+        assert_eq!(chain.account_at(&dummy_address(20), 1).unwrap().unwrap().balance, 500);
+        assert!(chain.logs.bloom(1).is_some());
+    }
+
+    #[test]
+    fn test_chain_recovers_from_kv_store_after_restart() {
+        let path = std::env::temp_dir().join(format!("ocos-ledger-kv-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let backends: Vec<Arc<dyn KvStore>> = vec![Arc::new(MemoryStore::new()), Arc::new(SledStore::open(&path).unwrap())];
+        for db in backends {
+            let genesis = Block::default();
+            let mut genesis_state = State::new();
+            genesis_state.update_account(sender_address(10), AccountState::new(10_000));
+            let mut chain = Chain::open(db.clone(), genesis.clone(), genesis_state).unwrap();
+
+            let mut b1 = child_block(&genesis, 1);
+            b1.transactions.push(dummy_tx(10, 20, 1, 500));
+            chain.seal_block(&mut b1).unwrap();
+            chain.import_block(b1.clone()).unwrap();
+            let mut b2 = child_block(&b1, 1);
+            b2.transactions.push(dummy_tx(10, 21, 2, 700));
+            chain.seal_block(&mut b2).unwrap();
+
+            // A header claiming other gas than its execution is refused
+            let mut unmetered = b2.clone();
+            unmetered.header.gas_used = 0;
+            assert_eq!(
+                chain.import_block(unmetered),
+                Err(ChainError::GasUsedMismatch { expected: 1_000, found: 0 })
+            );
+            let mut unlogged = b2.clone();
+            unlogged.header.logs_bloom = Bloom::new().as_bytes().to_vec();
+            assert_eq!(chain.import_block(unlogged), Err(ChainError::LogsBloomMismatch));
+            chain.import_block(b2.clone()).unwrap();
+            chain.finalize(&b1.hash()).unwrap();
+            let root_at_b1 = chain.diffs[&b1.hash()].state_root.clone();
+            assert_eq!(chain.execution_summary(&b1.hash()).unwrap(), ExecutionSummary { gas_used: 1_000, state_root: root_at_b1.clone(), logs_bloom: b1.header.logs_bloom.clone() });
+            drop(chain);
+
+            // Restart resumes from the last finalized block; unfinalized b2 is re-imported
+            let mut chain = Chain::open(db.clone(), genesis.clone(), State::new()).unwrap();
+            assert_eq!(chain.head, b1.hash());
+            assert_eq!(chain.persisted_height, 1);
+            assert_eq!(chain.state.state_root(), root_at_b1);
+            assert_eq!(chain.state.get_account(&dummy_address(20)).unwrap().balance, 500);
+            assert!(chain.state.get_account(&dummy_address(21)).is_none());
+            assert_eq!(chain.history.get_block_txs(1).unwrap(), &vec![b1.transactions[0].hash.clone()]);
+            assert!(chain.history.get_tx_by_hash(&b1.transactions[0].hash).is_some());
+
+            assert_eq!(chain.import_block(b2.clone()).unwrap(), ImportOutcome::Extended);
+            chain.finalize(&b2.hash()).unwrap();
+            let chain = Chain::open(db, genesis, State::new()).unwrap();
+            assert_eq!(chain.store.get_latest_block().unwrap().hash(), b2.hash());
+            assert_eq!(chain.store.len(), 3);
+            assert_eq!(chain.state.get_account(&sender_address(10)).unwrap().nonce, 2);
+            assert_eq!(chain.state.get_account(&dummy_address(21)).unwrap().balance, 700);
+        }
+        let _ = std::fs::remove_dir_all(&path);
+    }
 }
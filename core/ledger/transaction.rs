@@ -0,0 +1,196 @@
+//! OCOS-Chain: Ledger Transaction Module
+//!
+//! Canonical transaction format. The sender signs a deterministic hash of the
+//! unsigned fields, domain-separated by chain id, so a transaction signed for one
+//! network cannot be replayed on another. The signature scheme is tagged inside
+//! the signed fields and the sender address is derived from the public key, so a
+//! signature only ever authorizes the account it debits.
+//!
+//! Fees follow EIP-1559: the sender caps the total price per gas
+//! (`max_fee_per_gas`) and the tip to the proposer (`max_priority_fee_per_gas`).
+//! A transaction pays `min(max_fee, base_fee + priority_fee)` per gas; the
+//! base-fee part is burned and the rest goes to the proposer.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use crate::crypto::{
+    dilithium_sign, dilithium_verify, ed25519_sign, ed25519_verify, secp_sign, secp_verify,
+    hashing::hash_to_20_bytes,
+    Ed25519Keypair,
+};
+
+/// Chain id of the OCOS mainnet
+pub const MAINNET_CHAIN_ID: u64 = 7_770;
+
+/// Signature algorithm of the sender key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TxSignatureScheme {
+    Ed25519,
+    Secp256k1, // ECDSA, DER signatures over SEC1 public keys
+    Dilithium,
+}
+
+impl TxSignatureScheme {
+    /// Tag committed in the signing hash
+    pub fn tag(&self) -> u8 {
+        match self {
+            TxSignatureScheme::Ed25519 => 1,
+            TxSignatureScheme::Secp256k1 => 2,
+            TxSignatureScheme::Dilithium => 3,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(TxSignatureScheme::Ed25519),
+            2 => Some(TxSignatureScheme::Secp256k1),
+            3 => Some(TxSignatureScheme::Dilithium),
+            _ => None,
+        }
+    }
+
+    pub fn verify(&self, public_key: &[u8], msg: &[u8], signature: &[u8]) -> bool {
+        match self {
+            TxSignatureScheme::Ed25519 => ed25519_verify(public_key, msg, signature),
+            TxSignatureScheme::Secp256k1 => secp_verify(public_key, msg, signature),
+            TxSignatureScheme::Dilithium => dilithium_verify(public_key, msg, signature),
+        }
+    }
+
+    fn sign(&self, private_key: &[u8], msg: &[u8]) -> Result<Vec<u8>, TransactionError> {
+        match self {
+            TxSignatureScheme::Ed25519 => {
+                // The underlying signer panics on malformed keys
+                Ed25519Keypair::from_private_key(private_key).map_err(|_| TransactionError::SigningFailed)?;
+                Ok(ed25519_sign(private_key, msg))
+            }
+            TxSignatureScheme::Secp256k1 => secp_sign(private_key, msg).map_err(|_| TransactionError::SigningFailed),
+            TxSignatureScheme::Dilithium => dilithium_sign(private_key, msg).map_err(|_| TransactionError::SigningFailed),
+        }
+    }
+}
+
+/// Transaction validation errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TransactionError {
+    #[error("Transaction is for chain {found}, expected {expected}")]
+    WrongChainId { expected: u64, found: u64 },
+    #[error("Sender address does not match the public key")]
+    InvalidSender,
+    #[error("Transaction hash does not match its contents")]
+    InvalidHash,
+    #[error("Invalid transaction signature")]
+    InvalidSignature,
+    #[error("Signing failed (invalid key)")]
+    SigningFailed,
+}
+
+/// Unsigned transaction fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,          // Cap on base fee + tip
+    pub max_priority_fee_per_gas: u128, // Cap on the tip paid to the proposer
+    pub to: Vec<u8>,
+    pub value: u128,
+    pub payload: Vec<u8>,
+    pub scheme: TxSignatureScheme,
+}
+
+impl Transaction {
+    /// Deterministic hash the sender signs (length-prefixed fields, chain id first)
+    pub fn signing_hash(&self) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"OCOS-TX");
+        hasher.update(self.chain_id.to_be_bytes());
+        hasher.update(self.nonce.to_be_bytes());
+        hasher.update(self.gas_limit.to_be_bytes());
+        hasher.update(self.max_fee_per_gas.to_be_bytes());
+        hasher.update(self.max_priority_fee_per_gas.to_be_bytes());
+        hasher.update((self.to.len() as u64).to_be_bytes());
+        hasher.update(&self.to);
+        hasher.update(self.value.to_be_bytes());
+        hasher.update((self.payload.len() as u64).to_be_bytes());
+        hasher.update(&self.payload);
+        hasher.update([self.scheme.tag()]);
+        hasher.finalize().to_vec()
+    }
+
+    /// Most the sender can be charged for gas
+    pub fn max_fee(&self) -> Option<u128> {
+        (self.gas_limit as u128).checked_mul(self.max_fee_per_gas)
+    }
+
+    /// Price per gas paid under `base_fee` (None if the fee cap is below it)
+    pub fn effective_gas_price(&self, base_fee: u128) -> Option<u128> {
+        if self.max_fee_per_gas < base_fee {
+            return None;
+        }
+        Some(self.max_fee_per_gas.min(base_fee.saturating_add(self.max_priority_fee_per_gas)))
+    }
+
+    /// Tip per gas paid to the proposer under `base_fee`
+    pub fn priority_fee(&self, base_fee: u128) -> Option<u128> {
+        self.effective_gas_price(base_fee).map(|price| price - base_fee)
+    }
+
+    /// Sign with the sender's key pair (of `self.scheme`)
+    pub fn sign(self, public_key: &[u8], private_key: &[u8]) -> Result<SignedTransaction, TransactionError> {
+        let signature = self.scheme.sign(private_key, &self.signing_hash())?;
+        Ok(SignedTransaction::new(self, public_key.to_vec(), signature))
+    }
+}
+
+/// Transaction with its sender key and signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    pub tx: Transaction,
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub sender: Vec<u8>, // Derived from `public_key`
+    pub hash: Vec<u8>,   // Transaction id: signing hash bound to the signature
+}
+
+impl SignedTransaction {
+    pub fn new(tx: Transaction, public_key: Vec<u8>, signature: Vec<u8>) -> Self {
+        let sender = address_of(&public_key);
+        let hash = Self::compute_hash(&tx, &signature);
+        SignedTransaction { tx, public_key, signature, sender, hash }
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.tx.nonce
+    }
+
+    /// Check chain id, sender derivation, hash and signature before execution
+    pub fn verify(&self, chain_id: u64) -> Result<(), TransactionError> {
+        if self.tx.chain_id != chain_id {
+            return Err(TransactionError::WrongChainId { expected: chain_id, found: self.tx.chain_id });
+        }
+        if self.sender != address_of(&self.public_key) {
+            return Err(TransactionError::InvalidSender);
+        }
+        if self.hash != Self::compute_hash(&self.tx, &self.signature) {
+            return Err(TransactionError::InvalidHash);
+        }
+        if !self.tx.scheme.verify(&self.public_key, &self.tx.signing_hash(), &self.signature) {
+            return Err(TransactionError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    fn compute_hash(tx: &Transaction, signature: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.update(tx.signing_hash());
+        hasher.update(signature);
+        hasher.finalize().to_vec()
+    }
+}
+
+/// Account address of a public key (first 20 bytes of its SHA3-256 hash)
+pub fn address_of(public_key: &[u8]) -> Vec<u8> {
+    hash_to_20_bytes(public_key).to_vec()
+}
@@ -2,19 +2,62 @@
 //!
 //! Handles pending transactions prior to block inclusion. Supports filtering, prioritization,
 //! expiration, replay protection, and mempool auditing.
+//!
+//! Each sender's transactions are kept by nonce. Those that follow the account
+//! nonce without a gap are *pending* (executable now); the rest are *queued*
+//! until the gap is filled. A transaction with an already pooled nonce replaces
+//! it only if both fee caps rise by at least `price_bump_bps`. When the pool is
+//! full, the cheapest tail transaction of any sender is evicted to make room.
+//! After every block, `revalidate` drops included, stale and unaffordable
+//! transactions against the new state and base fee.
 
-use crate::ledger::transaction::SignedTransaction;
-use std::collections::{HashMap, VecDeque};
+use crate::ledger::state::State;
+use crate::ledger::transaction::{SignedTransaction, TransactionError, MAINNET_CHAIN_ID};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 
-/// TxPool struct: holds all pending transactions
-pub struct TxPool {
-    /// Address → Nonce → Transaction
-    pub pending: HashMap<Vec<u8>, HashMap<u64, TxPoolEntry>>,
-    /// Expiration time in seconds for each tx
-    pub tx_ttl: Duration,
-    /// Ordered queue for eviction & auditing
-    pub ordered_queue: VecDeque<TxPoolEntry>,
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Pool admission errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TxPoolError {
+    #[error("Invalid transaction: {0}")]
+    Invalid(#[from] TransactionError),
+    #[error("Transaction already known")]
+    AlreadyKnown,
+    #[error("Nonce {nonce} is already used (account nonce {account_nonce})")]
+    NonceTooLow { nonce: u64, account_nonce: u64 },
+    #[error("Sender cannot afford the transaction's fee cap and value")]
+    InsufficientBalance,
+    #[error("Replacement must raise both fee caps by the minimum bump")]
+    ReplacementUnderpriced,
+    #[error("Sender has too many pooled transactions")]
+    SenderLimitReached,
+    #[error("Pool is full and the transaction pays less than every evictable one")]
+    PoolFull,
+}
+
+/// Pool limits and admission rules
+#[derive(Debug, Clone)]
+pub struct TxPoolConfig {
+    pub chain_id: u64,
+    pub max_size: usize,        // Transactions across all senders
+    pub max_per_sender: usize,  // Transactions per sender (pending + queued)
+    pub price_bump_bps: u64,    // Minimum fee increase for a same-nonce replacement
+    pub tx_ttl: Duration,       // Pooled transactions older than this are dropped
+}
+
+impl Default for TxPoolConfig {
+    fn default() -> Self {
+        TxPoolConfig {
+            chain_id: MAINNET_CHAIN_ID,
+            max_size: 4_096,
+            max_per_sender: 16,
+            price_bump_bps: 1_000, // 10%
+            tx_ttl: Duration::from_secs(3 * 3_600),
+        }
+    }
 }
 
 /// TxPoolEntry: Stores transaction with timestamp
@@ -22,81 +65,269 @@ pub struct TxPool {
 pub struct TxPoolEntry {
     pub tx: SignedTransaction,
     pub timestamp: u64, // UNIX epoch seconds
+    pub seq: u64,       // Arrival order (tie-breaker between equal prices)
+}
+
+/// One sender's pooled transactions and the account view they were checked against
+#[derive(Default)]
+struct SenderTxs {
+    account_nonce: u64,
+    balance: u128,
+    txs: BTreeMap<u64, TxPoolEntry>,
+}
+
+impl SenderTxs {
+    /// Nonces executable now: a gap-free run from the account nonce
+    fn pending(&self) -> impl Iterator<Item = &TxPoolEntry> {
+        let mut expected = self.account_nonce + 1;
+        self.txs.range(expected..).map_while(move |(&nonce, entry)| {
+            (nonce == expected).then(|| {
+                expected += 1;
+                entry
+            })
+        })
+    }
+
+    fn pending_len(&self) -> usize {
+        self.pending().count()
+    }
+}
+
+/// TxPool struct: holds all pooled transactions
+pub struct TxPool {
+    pub config: TxPoolConfig,
+    /// Base fee of the next block; orders and filters block selection
+    pub base_fee: u128,
+    senders: HashMap<Vec<u8>, SenderTxs>,
+    /// Tx hash → (sender, nonce)
+    by_hash: HashMap<Vec<u8>, (Vec<u8>, u64)>,
+    next_seq: u64,
 }
 
 impl TxPool {
     /// Create new, empty mempool with specified tx TTL (seconds)
     pub fn new(ttl_seconds: u64) -> Self {
+        Self::with_config(TxPoolConfig { tx_ttl: Duration::from_secs(ttl_seconds), ..Default::default() })
+    }
+
+    pub fn with_config(config: TxPoolConfig) -> Self {
         Self {
-            pending: HashMap::new(),
-            tx_ttl: Duration::from_secs(ttl_seconds),
-            ordered_queue: VecDeque::new(),
+            config,
+            base_fee: 0,
+            senders: HashMap::new(),
+            by_hash: HashMap::new(),
+            next_seq: 0,
         }
     }
 
-    /// Add a new transaction (replay and duplicate check)
-    pub fn add_transaction(&mut self, tx: SignedTransaction) -> Result<(), &'static str> {
-        let sender = tx.sender.clone();
-        let nonce = tx.nonce;
-        let now = current_unix_timestamp();
+    /// Add a transaction checked against the sender's account in `state`.
+    /// Returns the hash of the transaction it replaced or evicted, if any.
+    pub fn add_transaction(&mut self, tx: SignedTransaction, state: &State) -> Result<Option<Vec<u8>>, TxPoolError> {
+        tx.verify(self.config.chain_id)?;
+        if self.by_hash.contains_key(&tx.hash) {
+            return Err(TxPoolError::AlreadyKnown);
+        }
+        let (account_nonce, balance) = state.get_account(&tx.sender)
+            .map(|a| (a.nonce, a.balance))
+            .unwrap_or((0, 0));
+        let nonce = tx.nonce();
+        if nonce <= account_nonce {
+            return Err(TxPoolError::NonceTooLow { nonce, account_nonce });
+        }
+        let affordable = tx.tx.max_fee()
+            .and_then(|fee| fee.checked_add(tx.tx.value))
+            .is_some_and(|cost| cost <= balance);
+        if !affordable {
+            return Err(TxPoolError::InsufficientBalance);
+        }
 
-        // Replay/duplicate protection
-        if let Some(map) = self.pending.get(&sender) {
-            if map.contains_key(&nonce) {
-                return Err("Duplicate/replay transaction");
+        // Replace-by-fee
+        let pooled = self.senders.get(&tx.sender);
+        if let Some(existing) = pooled.and_then(|s| s.txs.get(&nonce)) {
+            if !Self::outbids(&tx, &existing.tx, self.config.price_bump_bps) {
+                return Err(TxPoolError::ReplacementUnderpriced);
             }
+            let replaced = existing.tx.hash.clone();
+            self.by_hash.remove(&replaced);
+            self.insert(tx, account_nonce, balance);
+            return Ok(Some(replaced));
         }
-        let entry = TxPoolEntry { tx: tx.clone(), timestamp: now };
-
-        self.pending.entry(sender.clone())
-            .or_insert_with(HashMap::new)
-            .insert(nonce, entry.clone());
 
-        self.ordered_queue.push_back(entry);
-        Ok(())
+        if pooled.map_or(0, |s| s.txs.len()) >= self.config.max_per_sender {
+            return Err(TxPoolError::SenderLimitReached);
+        }
+        let mut evicted = None;
+        if self.len() >= self.config.max_size {
+            let victim = self.cheapest_tail().ok_or(TxPoolError::PoolFull)?;
+            if Self::fee_key(&victim.tx) >= Self::fee_key(&tx) {
+                return Err(TxPoolError::PoolFull);
+            }
+            let (victim_sender, victim_nonce) = (victim.tx.sender.clone(), victim.tx.nonce());
+            evicted = self.remove_transaction(&victim_sender, victim_nonce).map(|t| t.hash);
+        }
+        self.insert(tx, account_nonce, balance);
+        Ok(evicted)
     }
 
     /// Get next valid transaction for a given sender (by nonce)
     pub fn get_next_tx(&self, sender: &[u8], current_nonce: u64) -> Option<&SignedTransaction> {
-        self.pending.get(sender)
-            .and_then(|map| map.get(&(current_nonce + 1)))
+        self.senders.get(sender)
+            .and_then(|s| s.txs.get(&(current_nonce + 1)))
             .map(|entry| &entry.tx)
     }
 
-    /// Remove a transaction after block inclusion
+    /// Get a pooled transaction by hash
+    pub fn get(&self, hash: &[u8]) -> Option<&SignedTransaction> {
+        let (sender, nonce) = self.by_hash.get(hash)?;
+        self.senders.get(sender)?.txs.get(nonce).map(|e| &e.tx)
+    }
+
+    /// Remove a transaction (e.g. after block inclusion)
     pub fn remove_transaction(&mut self, sender: &[u8], nonce: u64) -> Option<SignedTransaction> {
-        if let Some(map) = self.pending.get_mut(sender) {
-            if let Some(entry) = map.remove(&nonce) {
-                return Some(entry.tx);
+        let sender_txs = self.senders.get_mut(sender)?;
+        let entry = sender_txs.txs.remove(&nonce)?;
+        if sender_txs.txs.is_empty() {
+            self.senders.remove(sender);
+        }
+        self.by_hash.remove(&entry.tx.hash);
+        Some(entry.tx)
+    }
+
+    /// Re-check every transaction after a block: drop included or stale nonces,
+    /// transactions the sender can no longer afford and expired ones. Returns
+    /// the hashes of dropped transactions.
+    pub fn revalidate(&mut self, state: &State, base_fee: u128) -> Vec<Vec<u8>> {
+        self.base_fee = base_fee;
+        let now = current_unix_timestamp();
+        let ttl = self.config.tx_ttl.as_secs();
+        let mut dropped = vec![];
+        for (sender, sender_txs) in self.senders.iter_mut() {
+            let (account_nonce, balance) = state.get_account(sender)
+                .map(|a| (a.nonce, a.balance))
+                .unwrap_or((0, 0));
+            sender_txs.account_nonce = account_nonce;
+            sender_txs.balance = balance;
+            sender_txs.txs.retain(|&nonce, entry| {
+                let affordable = entry.tx.tx.max_fee()
+                    .and_then(|fee| fee.checked_add(entry.tx.tx.value))
+                    .is_some_and(|cost| cost <= balance);
+                let keep = nonce > account_nonce && affordable && now.saturating_sub(entry.timestamp) <= ttl;
+                if !keep {
+                    dropped.push(entry.tx.hash.clone());
+                }
+                keep
+            });
+        }
+        self.senders.retain(|_, s| !s.txs.is_empty());
+        for hash in &dropped {
+            self.by_hash.remove(hash);
+        }
+        dropped
+    }
+
+    /// Pending transactions that fit in `gas_limit`, highest tip first. Each
+    /// sender's transactions stay in nonce order; a sender whose next
+    /// transaction does not fit or is priced below the base fee is skipped.
+    pub fn select_for_block(&self, gas_limit: u64) -> impl Iterator<Item = &SignedTransaction> + '_ {
+        // Heap of each sender's next pending transaction
+        let mut heap: BinaryHeap<_> = self.senders
+            .iter()
+            .filter_map(|(sender, s)| s.txs.get(&(s.account_nonce + 1)).map(|e| self.selection_key(sender, e)))
+            .collect();
+
+        let mut selected = vec![];
+        let mut remaining = gas_limit;
+        while let Some((tip, _, sender, nonce)) = heap.pop() {
+            let sender_txs = &self.senders[sender];
+            let entry = &sender_txs.txs[&nonce];
+            if tip.is_none() || entry.tx.tx.gas_limit > remaining {
+                continue;
+            }
+            remaining -= entry.tx.tx.gas_limit;
+            selected.push(&entry.tx);
+            if let Some(next) = sender_txs.txs.get(&(nonce + 1)) {
+                heap.push(self.selection_key(sender, next));
             }
         }
-        None
+        selected.into_iter()
     }
 
     /// Evict expired transactions (audit for dropped tx)
     pub fn evict_expired(&mut self) {
         let now = current_unix_timestamp();
-        while let Some(entry) = self.ordered_queue.front() {
-            if now - entry.timestamp > self.tx_ttl.as_secs() {
-                // Remove from pending map
-                let sender = entry.tx.sender.clone();
-                let nonce = entry.tx.nonce;
-                self.remove_transaction(&sender, nonce);
-                self.ordered_queue.pop_front();
-            } else {
-                break;
-            }
+        let ttl = self.config.tx_ttl.as_secs();
+        let expired: Vec<(Vec<u8>, u64)> = self.senders
+            .iter()
+            .flat_map(|(sender, s)| s.txs.iter().map(move |(nonce, e)| (sender, *nonce, e.timestamp)))
+            .filter(|(_, _, timestamp)| now.saturating_sub(*timestamp) > ttl)
+            .map(|(sender, nonce, _)| (sender.clone(), nonce))
+            .collect();
+        for (sender, nonce) in expired {
+            self.remove_transaction(&sender, nonce);
         }
     }
 
+    /// Executable transactions across all senders
+    pub fn pending_count(&self) -> usize {
+        self.senders.values().map(SenderTxs::pending_len).sum()
+    }
+
+    /// Transactions waiting for a nonce gap to be filled
+    pub fn queued_count(&self) -> usize {
+        self.len() - self.pending_count()
+    }
+
+    /// Executable transactions of one sender, in nonce order
+    pub fn pending_for(&self, sender: &[u8]) -> Vec<&SignedTransaction> {
+        self.senders.get(sender)
+            .map(|s| s.pending().map(|e| &e.tx).collect())
+            .unwrap_or_default()
+    }
+
     /// Get current pool size
     pub fn len(&self) -> usize {
-        self.ordered_queue.len()
+        self.by_hash.len()
     }
 
     /// Check if the pool is empty
     pub fn is_empty(&self) -> bool {
-        self.ordered_queue.is_empty()
+        self.by_hash.is_empty()
+    }
+
+    fn insert(&mut self, tx: SignedTransaction, account_nonce: u64, balance: u128) {
+        let (sender, nonce) = (tx.sender.clone(), tx.nonce());
+        self.by_hash.insert(tx.hash.clone(), (sender.clone(), nonce));
+        let entry = TxPoolEntry { tx, timestamp: current_unix_timestamp(), seq: self.next_seq };
+        self.next_seq += 1;
+        let sender_txs = self.senders.entry(sender).or_default();
+        sender_txs.account_nonce = account_nonce;
+        sender_txs.balance = balance;
+        sender_txs.txs.insert(nonce, entry);
+    }
+
+    /// True if `new` raises both fee caps of `old` by at least `bump_bps`
+    fn outbids(new: &SignedTransaction, old: &SignedTransaction, bump_bps: u64) -> bool {
+        let bumped = |fee: u128| fee.saturating_mul(BPS_DENOMINATOR + bump_bps as u128) / BPS_DENOMINATOR;
+        new.tx.max_fee_per_gas >= bumped(old.tx.max_fee_per_gas).max(old.tx.max_fee_per_gas + 1)
+            && new.tx.max_priority_fee_per_gas >= bumped(old.tx.max_priority_fee_per_gas)
+    }
+
+    /// Eviction order: tip cap, then fee cap
+    fn fee_key(tx: &SignedTransaction) -> (u128, u128) {
+        (tx.tx.max_priority_fee_per_gas, tx.tx.max_fee_per_gas)
+    }
+
+    /// Lowest-paying highest-nonce transaction of any sender (evicting it opens no nonce gap)
+    fn cheapest_tail(&self) -> Option<&TxPoolEntry> {
+        self.senders
+            .values()
+            .filter_map(|s| s.txs.values().next_back())
+            .min_by_key(|e| (Self::fee_key(&e.tx), Reverse(e.seq)))
+    }
+
+    /// Heap key: effective tip (None = below the base fee), then earliest arrival
+    fn selection_key<'a>(&self, sender: &'a [u8], entry: &TxPoolEntry) -> (Option<u128>, Reverse<u64>, &'a [u8], u64) {
+        (entry.tx.tx.priority_fee(self.base_fee), Reverse(entry.seq), sender, entry.tx.nonce())
     }
 }
 